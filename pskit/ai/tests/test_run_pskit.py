@@ -0,0 +1,79 @@
+# Checks that the params the webserver writes to form_data.json can be run by `main`.
+# Run from the pskit directory: `python -m unittest ai.tests.test_run_pskit`
+# The model and feature modules are replaced by stubs, so no model weights are needed.
+
+import json
+import os
+import sys
+import tempfile
+import types
+import unittest
+from unittest import mock
+
+
+def _stub(name, **attrs):
+    module = types.ModuleType(name)
+    module.__dict__.update(attrs)
+    return module
+
+
+STUBS = {
+    "ai.config": _stub("ai.config", path=None),
+    "ai.feature": _stub(
+        "ai.feature",
+        empirical_feats=_stub("ai.feature.empirical_feats", run=lambda **_: {}),
+        esm2=_stub("ai.feature.esm2", run=lambda **_: {}),
+        saprot=_stub("ai.feature.saprot", run=lambda **_: {}),
+    ),
+    "ai.utils": _stub("ai.utils", download_pdb=lambda ids, _dir: (ids, {})),
+    "ai.INABe": _stub("ai.INABe", predict=lambda **_: {}),
+}
+
+with mock.patch.dict(sys.modules, STUBS):
+    from ai import run_pskit
+
+
+class TestMain(unittest.TestCase):
+    def run_upload(self, form):
+        """Run `main` on the params written for an upload with the given form fields."""
+        with tempfile.TemporaryDirectory() as home:
+            input_dir = os.path.join(home, "uploads")
+            output_dir = os.path.join(home, "results")
+            os.makedirs(input_dir)
+            os.makedirs(output_dir)
+            # 与 webserver 的 task_params 一致：selection 只用于上传校验，不写入任务参数
+            params = {k: v for k, v in form.items() if k != "selection"}
+            params.update(input_dir=input_dir, output_dir=output_dir)
+            params = json.loads(json.dumps(params))
+
+            run_pskit.main(params)
+            error_file = os.path.join(output_dir, "error.json")
+            if os.path.exists(error_file):
+                with open(error_file) as f:
+                    return json.load(f)
+            return {}
+
+    def test_upload_with_selection(self):
+        for task_name, extra in [
+            ("pred_bs", {"ligand_type": "RNA"}),
+            ("emp_feats", {"emp_feats": "dssp"}),
+            ("lm_embed", {"model_type": "both"}),
+        ]:
+            form = {
+                "task_id": "t1",
+                "task_name": task_name,
+                "input_method": "file",
+                "selection": "chain A and not water",
+                **extra,
+            }
+            with self.subTest(task_name=task_name):
+                self.assertEqual(self.run_upload(form), {})
+
+    def test_selection_is_not_a_task_param(self):
+        for cls in run_pskit.class_map.values():
+            with self.assertRaises(TypeError):
+                cls(selection="chain A")
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,531 @@
+// `pskit` command-line tool: the pskit-core operations of the web tools, for shell pipelines
+// and batch jobs. Output files are named like the web downloads (`<base>.<key>.<ext>`).
+
+use clap::{Args, Parser, Subcommand};
+use pskit_core::annotate::compute_binding_pairs;
+use pskit_core::basepair::{compute_base_pairs, BasePairCriteria};
+use pskit_core::batch::{run_batch, BatchOptions, BatchSource};
+use pskit_core::chem::ModifiedResidues;
+use pskit_core::contact::{d_map, knn_map, Representative};
+use pskit_core::export::{write_binding_pairs_csv, write_dot_bracket, write_map, MapFormat};
+use pskit_core::split::{extract_fragment, split_by_chain, split_complex};
+use pskit_core::{PskitError, ResidueNumber, ResidueRange, Selection};
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{Cursor, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process::ExitCode;
+
+#[derive(Parser)]
+#[command(
+    name = "pskit",
+    version,
+    about = "Split structures, cut fragments and compute residue maps"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Args)]
+struct Inputs {
+    /// Structure files or glob patterns (quote them to bypass the shell); `-` reads stdin.
+    #[arg(required = true)]
+    inputs: Vec<String>,
+    /// Input format [default: from the file extension, otherwise from the content]
+    #[arg(short, long, value_parser = ["pdb", "cif"])]
+    format: Option<String>,
+    /// Atom selection applied first, e.g. "chain A and not water".
+    #[arg(short, long)]
+    selection: Option<String>,
+    /// Directory for the output files [default: stdout, for a single input]
+    #[arg(short, long)]
+    out_dir: Option<PathBuf>,
+    /// Files processed in parallel [default: one per core]
+    #[arg(short, long, default_value_t = 0, hide_default_value = true)]
+    jobs: usize,
+    /// Report progress on stderr.
+    #[arg(long)]
+    progress: bool,
+}
+
+#[derive(Args)]
+struct MapArgs {
+    /// Only residues of this chain.
+    #[arg(short, long)]
+    chain: Option<String>,
+    /// Residue point: auto, ca, cb, c1', p, sidechain, min_heavy or centroid.
+    #[arg(short, long, default_value = "auto")]
+    representative: String,
+    /// Output format: npy, csv or rr.
+    #[arg(long, default_value = "npy")]
+    output: String,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Write one file per chain.
+    SplitChains {
+        #[command(flatten)]
+        inputs: Inputs,
+    },
+    /// Split a protein–nucleic complex into its protein ("Prot") and nucleic ("NA") parts.
+    SplitComplex {
+        #[command(flatten)]
+        inputs: Inputs,
+        /// Modified residues: polymer or ligand.
+        #[arg(long)]
+        modified: Option<String>,
+    },
+    /// Cut residues start..=end (e.g. 52A) out of one chain; open ends when omitted.
+    Fragment {
+        #[command(flatten)]
+        inputs: Inputs,
+        #[arg(short, long)]
+        chain: String,
+        #[arg(long)]
+        start: Option<String>,
+        #[arg(long)]
+        end: Option<String>,
+    },
+    /// Residue distance map.
+    Dmap {
+        #[command(flatten)]
+        inputs: Inputs,
+        #[command(flatten)]
+        map: MapArgs,
+    },
+    /// Distance map keeping, per residue, the k nearest of the following residues.
+    Knn {
+        #[command(flatten)]
+        inputs: Inputs,
+        #[command(flatten)]
+        map: MapArgs,
+        #[arg(short)]
+        k: usize,
+    },
+    /// Protein–nucleic residue pairs within the cutoff, as CSV.
+    BindingPairs {
+        #[command(flatten)]
+        inputs: Inputs,
+        /// Distance cutoff in Å.
+        #[arg(long)]
+        cutoff: f64,
+        /// Modified residues: polymer or ligand.
+        #[arg(long)]
+        modified: Option<String>,
+    },
+    /// Nucleic-acid secondary structure from base pairs, as dot-bracket (.dbn).
+    DotBracket {
+        #[command(flatten)]
+        inputs: Inputs,
+        /// Modified residues: polymer or ligand.
+        #[arg(long)]
+        modified: Option<String>,
+    },
+}
+
+/// A subcommand with its options parsed, applied to one structure at a time.
+enum Operation {
+    SplitChains,
+    SplitComplex(ModifiedResidues),
+    Fragment {
+        chain: String,
+        range: ResidueRange,
+        key: String,
+    },
+    Map {
+        chain: Option<String>,
+        k: Option<usize>,
+        repr: Representative,
+        output: MapFormat,
+    },
+    BindingPairs {
+        cutoff: f64,
+        modified: ModifiedResidues,
+    },
+    DotBracket(ModifiedResidues),
+}
+
+/// Output files of one input, as `(key, bytes)`; the file is written as `<base>.<key>`.
+type Outputs = Vec<(String, Vec<u8>)>;
+
+impl Operation {
+    fn parse(command: Command) -> Result<(Operation, Inputs), PskitError> {
+        let modified = |m: Option<String>| m.map(|m| m.parse()).transpose();
+        let number = |v: &Option<String>| {
+            v.as_deref()
+                .filter(|s| !s.trim().is_empty())
+                .map(str::parse::<ResidueNumber>)
+                .transpose()
+        };
+        Ok(match command {
+            Command::SplitChains { inputs } => (Operation::SplitChains, inputs),
+            Command::SplitComplex {
+                inputs,
+                modified: m,
+            } => (
+                Operation::SplitComplex(modified(m)?.unwrap_or_default()),
+                inputs,
+            ),
+            Command::Fragment {
+                inputs,
+                chain,
+                start,
+                end,
+            } => {
+                let range = ResidueRange::new(number(&start)?, number(&end)?);
+                let key = format!(
+                    "fragment.{}.{}-{}",
+                    sanitize(&chain),
+                    sanitize(start.as_deref().unwrap_or("start")),
+                    sanitize(end.as_deref().unwrap_or("end"))
+                );
+                (Operation::Fragment { chain, range, key }, inputs)
+            }
+            Command::Dmap { inputs, map } => (Operation::map(map, None)?, inputs),
+            Command::Knn { inputs, map, k } => (Operation::map(map, Some(k))?, inputs),
+            Command::BindingPairs {
+                inputs,
+                cutoff,
+                modified: m,
+            } => (
+                Operation::BindingPairs {
+                    cutoff,
+                    modified: modified(m)?.unwrap_or_default(),
+                },
+                inputs,
+            ),
+            Command::DotBracket {
+                inputs,
+                modified: m,
+            } => (
+                Operation::DotBracket(modified(m)?.unwrap_or_default()),
+                inputs,
+            ),
+        })
+    }
+
+    fn map(map: MapArgs, k: Option<usize>) -> Result<Operation, PskitError> {
+        Ok(Operation::Map {
+            chain: map.chain,
+            k,
+            repr: map.representative.parse()?,
+            output: map.output.parse()?,
+        })
+    }
+
+    /// Whether the operation can write several files per input.
+    fn splits(&self) -> bool {
+        matches!(self, Operation::SplitChains | Operation::SplitComplex(_))
+    }
+
+    fn run(&self, data: &[u8], format: &str, selection: &Selection) -> Result<Outputs, PskitError> {
+        let structures = |parts: std::collections::HashMap<String, Vec<u8>>| {
+            let mut parts: Outputs = parts
+                .into_iter()
+                .map(|(key, bytes)| (format!("{}.{format}", sanitize(&key)), bytes))
+                .collect();
+            parts.sort();
+            parts
+        };
+        Ok(match self {
+            Operation::SplitChains => {
+                structures(split_by_chain(Cursor::new(data), format, selection)?)
+            }
+            Operation::SplitComplex(modified) => structures(split_complex(
+                Cursor::new(data),
+                format,
+                *modified,
+                selection,
+            )?),
+            Operation::Fragment { chain, range, key } => {
+                let (bytes, _, _) = extract_fragment(
+                    Cursor::new(data),
+                    chain.clone(),
+                    range.clone(),
+                    format,
+                    selection,
+                )?;
+                vec![(format!("{key}.{format}"), bytes)]
+            }
+            Operation::Map {
+                chain,
+                k,
+                repr,
+                output,
+            } => {
+                let (axis, values) = match k {
+                    Some(k) => knn_map(
+                        Cursor::new(data),
+                        chain.clone(),
+                        *k,
+                        *repr,
+                        format,
+                        selection,
+                    )?,
+                    None => d_map(Cursor::new(data), chain.clone(), *repr, format, selection)?,
+                };
+                let name = match k {
+                    Some(k) => format!("knn_map.k{k}"),
+                    None => "d_map".to_string(),
+                };
+                let ext = match output {
+                    MapFormat::Npy => "npy",
+                    MapFormat::Csv => "csv",
+                    MapFormat::CaspRr => "rr",
+                };
+                let chain = chain.as_deref().map_or("all".to_string(), sanitize);
+                vec![(
+                    format!("{name}.{chain}.{ext}"),
+                    write_map(&axis, &values, *output),
+                )]
+            }
+            Operation::BindingPairs { cutoff, modified } => {
+                let pairs = compute_binding_pairs(
+                    Cursor::new(data),
+                    *cutoff,
+                    format,
+                    *modified,
+                    selection,
+                )?;
+                vec![(
+                    format!("binding_pairs.cutoff_{}.csv", sanitize(&cutoff.to_string())),
+                    write_binding_pairs_csv(&pairs),
+                )]
+            }
+            Operation::DotBracket(modified) => {
+                let structure = compute_base_pairs(
+                    Cursor::new(data),
+                    format,
+                    *modified,
+                    selection,
+                    &BasePairCriteria::default(),
+                )?;
+                vec![(
+                    "dot_bracket.dbn".to_string(),
+                    write_dot_bracket(&structure.chains, ""),
+                )]
+            }
+        })
+    }
+}
+
+/// Same as `sanitizeKey` of the web tools.
+fn sanitize(key: &str) -> String {
+    let mut out = String::new();
+    let mut replaced = false;
+    for c in key.chars() {
+        if c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-') {
+            out.push(c);
+            replaced = false;
+        } else if !replaced {
+            out.push('_');
+            replaced = true;
+        }
+    }
+    out
+}
+
+/// Output file prefix: the file name without its structure extension.
+fn base(source: &BatchSource) -> String {
+    let BatchSource::File(path) = source else {
+        return "stdin".to_string();
+    };
+    let name = path.file_name().map_or_else(
+        || "structure".to_string(),
+        |n| n.to_string_lossy().into_owned(),
+    );
+    let lower = name.to_lowercase();
+    for ext in [".cif", ".mmcif", ".pdb", ".ent"] {
+        if lower.ends_with(ext) {
+            return name[..name.len() - ext.len()].to_string();
+        }
+    }
+    name
+}
+
+/// Output file prefix of every source. Inputs sharing a file name, like `preds/*/model.cif`,
+/// are told apart by as many of their parent directories as needed (`a_model`, `b_model`).
+fn bases(sources: &[BatchSource]) -> Result<Vec<String>, String> {
+    let names: Vec<String> = sources.iter().map(base).collect();
+    let parents: Vec<Vec<String>> = sources
+        .iter()
+        .map(|source| match source {
+            BatchSource::File(path) => path
+                .parent()
+                .into_iter()
+                .flat_map(Path::components)
+                .filter_map(|c| match c {
+                    Component::Normal(dir) => Some(sanitize(&dir.to_string_lossy())),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        })
+        .collect();
+
+    let mut bases = names.clone();
+    for depth in 1.. {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for b in &bases {
+            *counts.entry(b.as_str()).or_default() += 1;
+        }
+        let clashing: Vec<usize> = (0..bases.len())
+            .filter(|&i| counts[bases[i].as_str()] > 1)
+            .collect();
+        let Some(&first) = clashing.first() else {
+            return Ok(bases);
+        };
+        // 父目录已用尽仍重名（如同一文件传了两次）时报错，不覆盖已写出的文件
+        if clashing.iter().all(|&i| parents[i].len() < depth) {
+            let other = clashing
+                .iter()
+                .find(|&&i| i != first && bases[i] == bases[first])
+                .map_or(first, |&i| i);
+            return Err(format!(
+                "{} and {} would write the same output files",
+                sources[first].name(),
+                sources[other].name()
+            ));
+        }
+        for i in clashing {
+            let dirs = &parents[i];
+            let prefix = dirs[dirs.len().saturating_sub(depth)..].join("_");
+            bases[i] = if prefix.is_empty() {
+                names[i].clone()
+            } else {
+                format!("{prefix}_{}", names[i])
+            };
+        }
+    }
+    unreachable!()
+}
+
+/// Expand glob patterns and read stdin (`-`); plain paths are kept as given so missing files
+/// are reported.
+fn expand(patterns: &[String]) -> Result<Vec<BatchSource>, String> {
+    let mut sources = Vec::new();
+    for pattern in patterns {
+        if pattern == "-" {
+            let mut data = Vec::new();
+            std::io::stdin()
+                .read_to_end(&mut data)
+                .map_err(|e| format!("<stdin>: {e}"))?;
+            sources.push(BatchSource::Bytes {
+                name: "<stdin>".to_string(),
+                data,
+            });
+        } else if pattern.contains(['*', '?', '[']) {
+            let paths = glob::glob(pattern).map_err(|e| format!("{pattern}: {e}"))?;
+            let before = sources.len();
+            for path in paths {
+                sources.push(BatchSource::File(path.map_err(|e| e.to_string())?));
+            }
+            if sources.len() == before {
+                return Err(format!("{pattern}: no files match"));
+            }
+        } else {
+            sources.push(BatchSource::File(PathBuf::from(pattern)));
+        }
+    }
+    Ok(sources)
+}
+
+fn write_outputs(outputs: Outputs, base: &str, out_dir: Option<&Path>) -> std::io::Result<()> {
+    if let Some(dir) = out_dir {
+        for (key, bytes) in outputs {
+            std::fs::write(dir.join(format!("{base}.{key}")), bytes)?;
+        }
+        return Ok(());
+    }
+    let mut stdout = std::io::stdout().lock();
+    let written = outputs
+        .iter()
+        .try_for_each(|(_, bytes)| stdout.write_all(bytes))
+        .and_then(|_| stdout.flush());
+    // 下游（如 head）提前关闭管道不算失败
+    match written {
+        Err(err) if err.kind() != std::io::ErrorKind::BrokenPipe => Err(err),
+        _ => Ok(()),
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let (operation, inputs) = match Operation::parse(cli.command) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("pskit: {err}");
+            return ExitCode::from(2);
+        }
+    };
+    let selection: Selection = match inputs.selection.as_deref().map(str::parse).transpose() {
+        Ok(selection) => selection.unwrap_or_default(),
+        Err(err) => {
+            eprintln!("pskit: {err}");
+            return ExitCode::from(2);
+        }
+    };
+    let sources = match expand(&inputs.inputs) {
+        Ok(sources) => sources,
+        Err(err) => {
+            eprintln!("pskit: {err}");
+            return ExitCode::from(2);
+        }
+    };
+    // 标准输出只能容纳一个结果文件
+    if inputs.out_dir.is_none() && (sources.len() > 1 || operation.splits()) {
+        eprintln!("pskit: several output files would be written, pass --out-dir");
+        return ExitCode::from(2);
+    }
+    let bases = match bases(&sources) {
+        Ok(bases) => bases,
+        Err(err) => {
+            eprintln!("pskit: {err}");
+            return ExitCode::from(2);
+        }
+    };
+    if let Some(dir) = &inputs.out_dir {
+        if let Err(err) = std::fs::create_dir_all(dir) {
+            eprintln!("pskit: {}: {err}", dir.display());
+            return ExitCode::from(2);
+        }
+    }
+
+    let options = BatchOptions {
+        threads: inputs.jobs,
+        format: inputs.format.clone(),
+    };
+    // 读取/解析失败和写出失败都计入
+    let mut failed = 0;
+    let progress = run_batch(
+        &sources,
+        &options,
+        |input| operation.run(input.data, input.format, &selection),
+        |item| {
+            let source = &sources[item.index];
+            let written = item
+                .result
+                .map_err(Box::<dyn Error>::from)
+                .and_then(|outputs| {
+                    write_outputs(outputs, &bases[item.index], inputs.out_dir.as_deref())
+                        .map_err(Into::into)
+                });
+            if let Err(err) = written {
+                eprintln!("pskit: {}: {err}", source.name());
+                failed += 1;
+            }
+        },
+        |progress| {
+            if inputs.progress {
+                eprintln!("[{}/{}]", progress.done, progress.total);
+            }
+        },
+    );
+    if failed > 0 {
+        eprintln!("pskit: {failed} of {} inputs failed", progress.total);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
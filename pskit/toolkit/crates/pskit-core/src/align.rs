@@ -0,0 +1,362 @@
+use std::str::FromStr;
+
+use crate::error::PskitError;
+use crate::sequence::ChainSequence;
+
+const AMINO_ACIDS: &str = "ARNDCQEGHILKMFPSTWYVBZX*";
+
+const BLOSUM62: &str = "
+ 4 -1 -2 -2  0 -1 -1  0 -2 -1 -1 -1 -1 -2 -1  1  0 -3 -2  0 -2 -1  0 -4
+-1  5  0 -2 -3  1  0 -2  0 -3 -2  2 -1 -3 -2 -1 -1 -3 -2 -3 -1  0 -1 -4
+-2  0  6  1 -3  0  0  0  1 -3 -3  0 -2 -3 -2  1  0 -4 -2 -3  3  0 -1 -4
+-2 -2  1  6 -3  0  2 -1 -1 -3 -4 -1 -3 -3 -1  0 -1 -4 -3 -3  4  1 -1 -4
+ 0 -3 -3 -3  9 -3 -4 -3 -3 -1 -1 -3 -1 -2 -3 -1 -1 -2 -2 -1 -3 -3 -2 -4
+-1  1  0  0 -3  5  2 -2  0 -3 -2  1  0 -3 -1  0 -1 -2 -1 -2  0  3 -1 -4
+-1  0  0  2 -4  2  5 -2  0 -3 -3  1 -2 -3 -1  0 -1 -3 -2 -2  1  4 -1 -4
+ 0 -2  0 -1 -3 -2 -2  6 -2 -4 -4 -2 -3 -3 -2  0 -2 -2 -3 -3 -1 -2 -1 -4
+-2  0  1 -1 -3  0  0 -2  8 -3 -3 -1 -2 -1 -2 -1 -2 -2  2 -3  0  0 -1 -4
+-1 -3 -3 -3 -1 -3 -3 -4 -3  4  2 -3  1  0 -3 -2 -1 -3 -1  3 -3 -3 -1 -4
+-1 -2 -3 -4 -1 -2 -3 -4 -3  2  4 -2  2  0 -3 -2 -1 -2 -1  1 -4 -3 -1 -4
+-1  2  0 -1 -3  1  1 -2 -1 -3 -2  5 -1 -3 -1  0 -1 -3 -2 -2  0  1 -1 -4
+-1 -1 -2 -3 -1  0 -2 -3 -2  1  2 -1  5  0 -2 -1 -1 -1 -1  1 -3 -1 -1 -4
+-2 -3 -3 -3 -2 -3 -3 -3 -1  0  0 -3  0  6 -4 -2 -2  1  3 -1 -3 -3 -1 -4
+-1 -2 -2 -1 -3 -1 -1 -2 -2 -3 -3 -1 -2 -4  7 -1 -1 -4 -3 -2 -2 -1 -2 -4
+ 1 -1  1  0 -1  0  0  0 -1 -2 -2  0 -1 -2 -1  4  1 -3 -2 -2  0  0  0 -4
+ 0 -1  0 -1 -1 -1 -1 -2 -2 -1 -1 -1 -1 -2 -1  1  5 -2 -2  0 -1 -1  0 -4
+-3 -3 -4 -4 -2 -2 -3 -2 -2 -3 -2 -3 -1  1 -4 -3 -2 11  2 -3 -4 -3 -2 -4
+-2 -2 -2 -3 -2 -1 -2 -3  2 -1 -1 -2 -1  3 -3 -2 -2  2  7 -1 -3 -2 -1 -4
+ 0 -3 -3 -3 -1 -2 -2 -3 -3  3  1 -2  1 -1 -2 -2  0 -3 -1  4 -3 -2 -1 -4
+-2 -1  3  4 -3  0  1 -1  0 -3 -4  0 -3 -3 -2  0 -1 -4 -3 -3  4  1 -1 -4
+-1  0  0  1 -3  3  4 -2  0 -3 -3  1 -1 -3 -1  0 -1 -3 -2 -2  1  4 -1 -4
+ 0 -1 -1 -1 -2 -1 -1 -1 -1 -1 -1 -1 -1 -1 -2  0  0 -2 -1 -1 -1 -1 -1 -4
+-4 -4 -4 -4 -4 -4 -4 -4 -4 -4 -4 -4 -4 -4 -4 -4 -4 -4 -4 -4 -4 -4 -4  1
+";
+
+const PAM250: &str = "
+ 2 -2  0  0 -2  0  0  1 -1 -1 -2 -1 -1 -3  1  1  1 -6 -3  0  0  0  0 -8
+-2  6  0 -1 -4  1 -1 -3  2 -2 -3  3  0 -4  0  0 -1  2 -4 -2 -1  0 -1 -8
+ 0  0  2  2 -4  1  1  0  2 -2 -3  1 -2 -3  0  1  0 -4 -2 -2  2  1  0 -8
+ 0 -1  2  4 -5  2  3  1  1 -2 -4  0 -3 -6 -1  0  0 -7 -4 -2  3  3 -1 -8
+-2 -4 -4 -5 12 -5 -5 -3 -3 -2 -6 -5 -5 -4 -3  0 -2 -8  0 -2 -4 -5 -3 -8
+ 0  1  1  2 -5  4  2 -1  3 -2 -2  1 -1 -5  0 -1 -1 -5 -4 -2  1  3 -1 -8
+ 0 -1  1  3 -5  2  4  0  1 -2 -3  0 -2 -5 -1  0  0 -7 -4 -2  3  3 -1 -8
+ 1 -3  0  1 -3 -1  0  5 -2 -3 -4 -2 -3 -5  0  1  0 -7 -5 -1  0  0 -1 -8
+-1  2  2  1 -3  3  1 -2  6 -2 -2  0 -2 -2  0 -1 -1 -3  0 -2  1  2 -1 -8
+-1 -2 -2 -2 -2 -2 -2 -3 -2  5  2 -2  2  1 -2 -1  0 -5 -1  4 -2 -2 -1 -8
+-2 -3 -3 -4 -6 -2 -3 -4 -2  2  6 -3  4  2 -3 -3 -2 -2 -1  2 -3 -3 -1 -8
+-1  3  1  0 -5  1  0 -2  0 -2 -3  5  0 -5 -1  0  0 -3 -4 -2  1  0 -1 -8
+-1  0 -2 -3 -5 -1 -2 -3 -2  2  4  0  6  0 -2 -2 -1 -4 -2  2 -2 -2 -1 -8
+-3 -4 -3 -6 -4 -5 -5 -5 -2  1  2 -5  0  9 -5 -3 -3  0  7 -1 -4 -5 -2 -8
+ 1  0  0 -1 -3  0 -1  0  0 -2 -3 -1 -2 -5  6  1  0 -6 -5 -1 -1  0 -1 -8
+ 1  0  1  0  0 -1  0  1 -1 -1 -3  0 -2 -3  1  2  1 -2 -3 -1  0  0  0 -8
+ 1 -1  0  0 -2 -1  0  0 -1  0 -2  0 -1 -3  0  1  3 -5 -3  0  0 -1  0 -8
+-6  2 -4 -7 -8 -5 -7 -7 -3 -5 -2 -3 -4  0 -6 -2 -5 17  0 -6 -5 -6 -4 -8
+-3 -4 -2 -4  0 -4 -4 -5  0 -1 -1 -4 -2  7 -5 -3 -3  0 10 -2 -3 -4 -2 -8
+ 0 -2 -2 -2 -2 -2 -2 -1 -2  4  2 -2  2 -1 -1 -1  0 -6 -2  4 -2 -2 -1 -8
+ 0 -1  2  3 -4  1  3  0  1 -2 -3  1 -2 -4 -1  0  0 -5 -3 -2  3  2 -1 -8
+ 0  0  1  3 -5  3  3  0  2 -2 -3  0 -2 -5  0  0 -1 -6 -4 -2  2  3 -1 -8
+ 0 -1  0 -1 -3 -1 -1 -1 -1 -1 -1 -1 -1 -2 -1  0  0 -4 -2 -1 -1 -1 -1 -8
+-8 -8 -8 -8 -8 -8 -8 -8 -8 -8 -8 -8 -8 -8 -8 -8 -8 -8 -8 -8 -8 -8 -8  1
+";
+
+/// Substitution scores used to align residues.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScoringMatrix {
+    /// Nucleotide scoring when both sequences only contain `ACGTUN`, BLOSUM62 otherwise.
+    #[default]
+    Auto,
+    Blosum62,
+    Pam250,
+    /// Match +5, mismatch -4, `N` -2 against anything; `T` and `U` are the same base.
+    Nucleotide,
+}
+
+impl FromStr for ScoringMatrix {
+    type Err = PskitError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(ScoringMatrix::Auto),
+            "blosum62" | "blosum" => Ok(ScoringMatrix::Blosum62),
+            "pam250" | "pam" => Ok(ScoringMatrix::Pam250),
+            "nucleotide" | "dna" | "rna" => Ok(ScoringMatrix::Nucleotide),
+            _ => Err(PskitError::InvalidArgument(format!(
+                "Unknown scoring matrix {s:?}, expected auto, blosum62, pam250 or nucleotide"
+            ))),
+        }
+    }
+}
+
+impl ScoringMatrix {
+    /// [`Auto`](ScoringMatrix::Auto) resolved for the two sequences.
+    fn resolve(self, a: &[char], b: &[char]) -> Self {
+        let nucleotide = |s: &[char]| s.iter().all(|c| "ACGTUN".contains(*c));
+        match self {
+            ScoringMatrix::Auto if nucleotide(a) && nucleotide(b) => ScoringMatrix::Nucleotide,
+            ScoringMatrix::Auto => ScoringMatrix::Blosum62,
+            matrix => matrix,
+        }
+    }
+}
+
+/// Score lookup for one matrix; amino-acid tables index unknown letters as `X`.
+struct Scorer {
+    matrix: ScoringMatrix,
+    table: Vec<Vec<i32>>,
+}
+
+impl Scorer {
+    fn new(matrix: ScoringMatrix) -> Self {
+        let text = match matrix {
+            ScoringMatrix::Pam250 => PAM250,
+            ScoringMatrix::Nucleotide => "",
+            _ => BLOSUM62,
+        };
+        let table = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                line.split_whitespace()
+                    .map(|v| v.parse().unwrap_or_default())
+                    .collect()
+            })
+            .collect();
+        Scorer { matrix, table }
+    }
+
+    fn score(&self, a: char, b: char) -> i32 {
+        if self.matrix == ScoringMatrix::Nucleotide {
+            let base = |c: char| if c == 'U' { 'T' } else { c };
+            return match (base(a), base(b)) {
+                ('N', _) | (_, 'N') => -2,
+                (a, b) if a == b => 5,
+                _ => -4,
+            };
+        }
+        let index = |c: char| AMINO_ACIDS.find(c).unwrap_or(22);
+        self.table[index(a)][index(b)]
+    }
+}
+
+/// Which parts of the sequences must be aligned.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AlignMode {
+    /// Needleman–Wunsch: both sequences end to end.
+    #[default]
+    Global,
+    /// Smith–Waterman: the best-scoring pair of subsequences.
+    Local,
+    /// Global, but gaps at either end are free, e.g. a modeled fragment against a full-length
+    /// sequence.
+    SemiGlobal,
+}
+
+impl FromStr for AlignMode {
+    type Err = PskitError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "global" | "nw" => Ok(AlignMode::Global),
+            "local" | "sw" => Ok(AlignMode::Local),
+            "semiglobal" | "semi-global" | "glocal" => Ok(AlignMode::SemiGlobal),
+            _ => Err(PskitError::InvalidArgument(format!(
+                "Unknown alignment mode {s:?}, expected global, local or semiglobal"
+            ))),
+        }
+    }
+}
+
+/// A gap of length `L` costs `gap_open + (L - 1) * gap_extend`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AlignOptions {
+    pub mode: AlignMode,
+    pub matrix: ScoringMatrix,
+    pub gap_open: i32,
+    pub gap_extend: i32,
+}
+
+impl Default for AlignOptions {
+    fn default() -> Self {
+        AlignOptions {
+            mode: AlignMode::Global,
+            matrix: ScoringMatrix::Auto,
+            gap_open: 10,
+            gap_extend: 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Alignment {
+    pub score: i32,
+    /// Indices of the aligned residue pairs `(a, b)`, in order.
+    pub pairs: Vec<(usize, usize)>,
+    /// Gapped rows of the aligned region, `-` for gaps.
+    pub aligned_a: String,
+    pub aligned_b: String,
+    /// Identical pairs over aligned pairs.
+    pub identity: f64,
+}
+
+impl Alignment {
+    /// Position in `b` aligned to each position of `a`.
+    pub fn map_a_to_b(&self, len_a: usize) -> Vec<Option<usize>> {
+        let mut map = vec![None; len_a];
+        for &(i, j) in &self.pairs {
+            map[i] = Some(j);
+        }
+        map
+    }
+}
+
+const NEG: i32 = i32::MIN / 4;
+
+/// Align two one-letter sequences (Gotoh's affine-gap dynamic programming).
+pub fn align(a: &str, b: &str, options: &AlignOptions) -> Alignment {
+    let a: Vec<char> = a.chars().map(|c| c.to_ascii_uppercase()).collect();
+    let b: Vec<char> = b.chars().map(|c| c.to_ascii_uppercase()).collect();
+    let scorer = Scorer::new(options.matrix.resolve(&a, &b));
+    let (n, m) = (a.len(), b.len());
+    let w = m + 1;
+    let local = options.mode == AlignMode::Local;
+    let free_ends = options.mode == AlignMode::SemiGlobal;
+    let (open, extend) = (options.gap_open, options.gap_extend);
+
+    // 状态 0：a[i-1] 与 b[j-1] 对齐；1：a[i-1] 对空位；2：b[j-1] 对空位；3：回溯终点
+    let mut score = [
+        vec![NEG; (n + 1) * w],
+        vec![NEG; (n + 1) * w],
+        vec![NEG; (n + 1) * w],
+    ];
+    let mut from = [
+        vec![3u8; (n + 1) * w],
+        vec![3u8; (n + 1) * w],
+        vec![3u8; (n + 1) * w],
+    ];
+    score[0][0] = 0;
+    let lead = |len: usize| match options.mode {
+        AlignMode::Global => -open - (len as i32 - 1) * extend,
+        AlignMode::SemiGlobal => 0,
+        AlignMode::Local => NEG,
+    };
+    for i in 1..=n {
+        score[1][i * w] = lead(i);
+        from[1][i * w] = if i == 1 { 3 } else { 1 };
+    }
+    for j in 1..=m {
+        score[2][j] = lead(j);
+        from[2][j] = if j == 1 { 3 } else { 2 };
+    }
+    let best = |score: &[Vec<i32>; 3], k: usize| {
+        (0..3u8)
+            .map(|s| (score[s as usize][k], s))
+            .max_by_key(|(v, s)| (*v, std::cmp::Reverse(*s)))
+            .unwrap()
+    };
+
+    let mut top = (0, 0, 0usize);
+    for i in 1..=n {
+        for j in 1..=m {
+            let k = i * w + j;
+            let (mut v, mut s) = best(&score, k - w - 1);
+            if local && v <= 0 {
+                (v, s) = (0, 3);
+            }
+            score[0][k] = v + scorer.score(a[i - 1], b[j - 1]);
+            from[0][k] = s;
+
+            // 半全局比对中，最后一行/列上的空位不计罚分
+            let (o, e) = if free_ends && j == m {
+                (0, 0)
+            } else {
+                (open, extend)
+            };
+            let up = k - w;
+            let (v, s) = [
+                (score[0][up] - o, 0u8),
+                (score[1][up] - e, 1),
+                (score[2][up] - o, 2),
+            ]
+            .into_iter()
+            .max_by_key(|(v, s)| (*v, std::cmp::Reverse(*s)))
+            .unwrap();
+            score[1][k] = v;
+            from[1][k] = s;
+
+            let (o, e) = if free_ends && i == n {
+                (0, 0)
+            } else {
+                (open, extend)
+            };
+            let (v, s) = [
+                (score[0][k - 1] - o, 0u8),
+                (score[1][k - 1] - o, 1),
+                (score[2][k - 1] - e, 2),
+            ]
+            .into_iter()
+            .max_by_key(|(v, s)| (*v, std::cmp::Reverse(*s)))
+            .unwrap();
+            score[2][k] = v;
+            from[2][k] = s;
+
+            if local && score[0][k] > top.0 {
+                top = (score[0][k], i, j);
+            }
+        }
+    }
+
+    let (total, mut i, mut j, mut state) = if local {
+        (top.0, top.1, top.2, if top.0 > 0 { 0 } else { 3 })
+    } else {
+        let (v, s) = best(&score, n * w + m);
+        (v, n, m, s)
+    };
+
+    let mut alignment = Alignment {
+        score: total,
+        ..Default::default()
+    };
+    let (mut row_a, mut row_b) = (Vec::new(), Vec::new());
+    while state != 3 && (i > 0 || j > 0) {
+        let prev = from[state as usize][i * w + j];
+        match state {
+            0 => {
+                alignment.pairs.push((i - 1, j - 1));
+                row_a.push(a[i - 1]);
+                row_b.push(b[j - 1]);
+                i -= 1;
+                j -= 1;
+            }
+            1 => {
+                row_a.push(a[i - 1]);
+                row_b.push('-');
+                i -= 1;
+            }
+            _ => {
+                row_a.push('-');
+                row_b.push(b[j - 1]);
+                j -= 1;
+            }
+        }
+        state = prev;
+    }
+    alignment.pairs.reverse();
+    alignment.aligned_a = row_a.into_iter().rev().collect();
+    alignment.aligned_b = row_b.into_iter().rev().collect();
+    let identical = alignment
+        .pairs
+        .iter()
+        .filter(|&&(i, j)| a[i] == b[j])
+        .count();
+    alignment.identity = if alignment.pairs.is_empty() {
+        0.0
+    } else {
+        identical as f64 / alignment.pairs.len() as f64
+    };
+    alignment
+}
+
+/// Align two extracted chain sequences; see [`chain_sequences`](crate::sequence::chain_sequences).
+pub fn align_chains(a: &ChainSequence, b: &ChainSequence, options: &AlignOptions) -> Alignment {
+    align(&a.sequence, &b.sequence, options)
+}
@@ -1,102 +1,125 @@
 use pdbtbx::{
-    ContainsAtomConformer, ContainsAtomConformerResidue, ContainsAtomConformerResidueChain,
+    ContainsAtomConformer, ContainsAtomConformerResidue, ContainsAtomConformerResidueChain, Model,
 };
 use rstar::{primitives::GeomWithData, RTree};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::BufRead;
 
-use crate::utils::{is_nucleic_residue, is_protein_residue, read_raw};
+use crate::basepair::{paired_nucleotides, BasePairCriteria};
+use crate::chem::{ModifiedResidues, PolymerType, ResidueClassifier};
+use crate::error::PskitError;
+use crate::residue::ResidueKey;
+use crate::selection::{read_selected, Selection};
+use crate::sequence::{map_deposited, Deposited, SequenceSource};
+use crate::utils::{three_to_one, ReadOptions};
 
-#[derive(Clone, Debug)]
-struct ResidueId {
-    chain_id: String,
-    resseq: isize,
-    resname: String,
+/// Which part of a nucleotide an atom belongs to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NucleicRegion {
+    /// Phosphate or sugar.
+    Backbone,
+    Base,
 }
 
-fn key_for_sort(s: &str) -> (&str, isize, &str, isize, &str) {
-    let front = s.split('_').next().unwrap_or("");
-    let mut parts = front.split('-');
+impl NucleicRegion {
+    pub fn of_atom(name: &str) -> Self {
+        // 糖环原子名带撇号（C1'、O4' …），其余磷酸基团原子按名称判断
+        if name.contains('\'')
+            || name.contains('*')
+            || matches!(name, "P" | "OP1" | "OP2" | "OP3" | "O1P" | "O2P" | "O3P")
+        {
+            NucleicRegion::Backbone
+        } else {
+            NucleicRegion::Base
+        }
+    }
 
-    let a_cid = parts.next().unwrap_or("\u{10FFFF}");
-    let a_resseq = parts
-        .next()
-        .and_then(|s| s.parse::<isize>().ok())
-        .unwrap_or(isize::MAX);
-    let _ = parts.next();
-    let b_cid = parts.next().unwrap_or("\u{10FFFF}");
-    let b_resseq = parts
-        .next()
-        .and_then(|s| s.parse::<isize>().ok())
-        .unwrap_or(isize::MAX);
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NucleicRegion::Backbone => "backbone",
+            NucleicRegion::Base => "base",
+        }
+    }
+}
 
-    (a_cid, a_resseq, b_cid, b_resseq, s)
+/// One protein–nucleic residue pair in contact, with its closest atom pair.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BindingPair {
+    pub protein: ResidueKey,
+    pub nucleic: ResidueKey,
+    pub protein_atom: String,
+    pub nucleic_atom: String,
+    /// Distance of the closest atom pair.
+    pub distance: f64,
+    /// Atom pairs under the cutoff.
+    pub contacts: usize,
+    pub backbone_contacts: usize,
+    pub base_contacts: usize,
+    /// Whether the nucleotide forms a base pair (any family) within the selection.
+    pub nucleic_paired: bool,
 }
 
-pub fn compute_binding_pairs<R: BufRead>(
-    reader: R,
-    cutoff: f64,
-    format: &str,
-) -> Result<Vec<(String, f64)>, String> {
-    let (pdb, _errors) = read_raw(reader, format)?;
-
-    let mut protein_has = false;
-    let mut nucleic_has = false;
-    'scan: for chain in pdb.chains() {
-        for residue in chain.residues() {
-            if let Some(name) = residue.name() {
-                protein_has |= is_protein_residue(name);
-                nucleic_has |= is_nucleic_residue(name);
-                if protein_has && nucleic_has {
-                    break 'scan;
-                }
-            }
-        }
+impl BindingPair {
+    /// Nucleotide region of the closest contact.
+    pub fn region(&self) -> NucleicRegion {
+        NucleicRegion::of_atom(&self.nucleic_atom)
     }
+}
 
-    if !(protein_has && nucleic_has) {
-        return Err("Not a protein-nucleic acid complex.".to_string());
+impl std::fmt::Display for BindingPair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}_{}", self.protein, self.nucleic)
     }
+}
 
-    // 只对核酸原子建 rtree
-    let mut nuc_points: Vec<GeomWithData<[f64; 3], ResidueId>> = Vec::new();
-    for atom in pdb.atoms_with_hierarchy() {
-        let resname = match atom.residue().name() {
-            Some(n) => n,
-            None => continue,
-        };
-        if !is_nucleic_residue(resname) {
-            continue;
+/// How often one protein–nucleic residue pair is in contact across the models.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BindingFrequency {
+    pub protein: ResidueKey,
+    pub nucleic: ResidueKey,
+    /// Fraction of models with the pair within the cutoff.
+    pub frequency: f64,
+}
+
+impl std::fmt::Display for BindingFrequency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}_{}", self.protein, self.nucleic)
+    }
+}
+
+type PairMap = HashMap<(ResidueKey, ResidueKey), BindingPair>;
+
+/// Every protein–nucleic residue pair of one model with an atom pair within `cutoff`.
+///
+/// `distance` holds the squared distance until the caller takes the root.
+fn model_pairs(model: &Model, classifier: &ResidueClassifier, cutoff: f64) -> PairMap {
+    // 只对核酸原子建 rtree，附带 (残基, 原子名)
+    let mut nuc_points: Vec<GeomWithData<[f64; 3], (ResidueKey, String)>> = Vec::new();
+    for atom in model.atoms_with_hierarchy() {
+        match atom.residue().name() {
+            Some(name) if classifier.is_nucleic(atom.chain().id(), name) => {}
+            _ => continue,
         }
         let (x, y, z) = atom.atom().pos();
         nuc_points.push(GeomWithData::new(
             [x, y, z],
-            ResidueId {
-                chain_id: atom.chain().id().to_string(),
-                resseq: atom.residue().id().0,
-                resname: resname.to_string(),
-            },
+            (
+                ResidueKey::new(atom.chain().id(), atom.residue()),
+                atom.atom().name().to_string(),
+            ),
         ));
     }
-    let nuc_tree: RTree<GeomWithData<[f64; 3], ResidueId>> = RTree::bulk_load(nuc_points);
+    let nuc_tree = RTree::bulk_load(nuc_points);
 
-    let mut pairs: HashMap<String, f64> = HashMap::new();
-    for atom_a in pdb.atoms_with_hierarchy() {
-        let a_resname = match atom_a.residue().name() {
-            Some(n) => n,
-            None => continue,
-        };
-        if !is_protein_residue(a_resname) {
-            continue;
+    let mut pairs: PairMap = HashMap::new();
+    for atom_a in model.atoms_with_hierarchy() {
+        match atom_a.residue().name() {
+            Some(name) if classifier.is_protein(atom_a.chain().id(), name) => {}
+            _ => continue,
         }
 
         let ap = atom_a.atom().pos();
-        let a_name = format!(
-            "{}-{}-{}",
-            atom_a.chain().id(),
-            atom_a.residue().id().0,
-            a_resname
-        );
+        let a_key = ResidueKey::new(atom_a.chain().id(), atom_a.residue());
 
         for b in nuc_tree.locate_within_distance([ap.0, ap.1, ap.2], cutoff * cutoff) {
             let bp = b.geom();
@@ -104,39 +127,475 @@ pub fn compute_binding_pairs<R: BufRead>(
             let dy = ap.1 - bp[1];
             let dz = ap.2 - bp[2];
             let d = dx * dx + dy * dy + dz * dz;
+            let (b_key, b_atom) = &b.data;
+
+            let pair = pairs
+                .entry((a_key.clone(), b_key.clone()))
+                .or_insert_with(|| BindingPair {
+                    protein: a_key.clone(),
+                    nucleic: b_key.clone(),
+                    protein_atom: atom_a.atom().name().to_string(),
+                    nucleic_atom: b_atom.to_string(),
+                    distance: d,
+                    contacts: 0,
+                    backbone_contacts: 0,
+                    base_contacts: 0,
+                    nucleic_paired: false,
+                });
+            if d < pair.distance {
+                pair.distance = d;
+                pair.protein_atom = atom_a.atom().name().to_string();
+                pair.nucleic_atom = b_atom.to_string();
+            }
+            pair.contacts += 1;
+            match NucleicRegion::of_atom(b_atom) {
+                NucleicRegion::Backbone => pair.backbone_contacts += 1,
+                NucleicRegion::Base => pair.base_contacts += 1,
+            }
+        }
+    }
+
+    pairs
+}
+
+/// Protein–nucleic residue pairs of the first model with any atom pair within `cutoff`,
+/// sorted by protein then nucleic residue. `nucleic_paired` is filled by testing only the
+/// binding nucleotides for base pairs, with the default [`BasePairCriteria`].
+pub fn compute_binding_pairs<R: BufRead>(
+    reader: R,
+    cutoff: f64,
+    format: &str,
+    modified: ModifiedResidues,
+    selection: &Selection,
+) -> Result<Vec<BindingPair>, PskitError> {
+    let (pdb, classifier) =
+        read_selected(reader, format, ReadOptions::default(), modified, selection)?;
+
+    if !classifier.is_complex(&pdb) {
+        return Err(PskitError::NotComplex);
+    }
+
+    let Some(model) = pdb.models().next() else {
+        return Ok(Vec::new());
+    };
+    let mut pairs: Vec<_> = model_pairs(model, &classifier, cutoff)
+        .into_values()
+        .collect();
+    // 只对结合位点上的核苷酸判断碱基配对
+    let binding: HashSet<&ResidueKey> = pairs.iter().map(|pair| &pair.nucleic).collect();
+    let paired = paired_nucleotides(model, &classifier, &BasePairCriteria::default(), &binding);
+    for pair in &mut pairs {
+        pair.distance = pair.distance.sqrt();
+        pair.nucleic_paired = paired.contains(&pair.nucleic);
+    }
+    pairs.sort_by(|a, b| (&a.protein, &a.nucleic).cmp(&(&b.protein, &b.nucleic)));
+
+    Ok(pairs)
+}
+
+/// Fraction of models in which each protein–nucleic residue pair is within `cutoff`.
+///
+/// All models of the file are read; a single-model file gives frequencies of 1.0. Pairs are
+/// sorted by protein then nucleic residue.
+pub fn compute_binding_frequency<R: BufRead>(
+    reader: R,
+    cutoff: f64,
+    format: &str,
+    modified: ModifiedResidues,
+    selection: &Selection,
+) -> Result<Vec<BindingFrequency>, PskitError> {
+    let (pdb, classifier) = read_selected(
+        reader,
+        format,
+        ReadOptions::all_models(),
+        modified,
+        selection,
+    )?;
+
+    if !classifier.is_complex(&pdb) {
+        return Err(PskitError::NotComplex);
+    }
+
+    let mut counts: HashMap<(ResidueKey, ResidueKey), usize> = HashMap::new();
+    for model in pdb.models() {
+        for pair in model_pairs(model, &classifier, cutoff).into_keys() {
+            *counts.entry(pair).or_default() += 1;
+        }
+    }
+
+    let n_models = pdb.model_count().max(1) as f64;
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|a, b| a.0.cmp(&b.0));
+
+    Ok(counts
+        .into_iter()
+        .map(|((protein, nucleic), c)| BindingFrequency {
+            protein,
+            nucleic,
+            frequency: c as f64 / n_models,
+        })
+        .collect())
+}
+
+/// Nucleic-acid partner a binding-site label refers to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LabelTarget {
+    Dna,
+    Rna,
+    /// DNA or RNA.
+    #[default]
+    Nucleic,
+}
+
+impl std::str::FromStr for LabelTarget {
+    type Err = PskitError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "dna" => Ok(LabelTarget::Dna),
+            "rna" => Ok(LabelTarget::Rna),
+            "nucleic" | "na" => Ok(LabelTarget::Nucleic),
+            _ => Err(PskitError::InvalidArgument(format!(
+                "Unknown label target {s:?}, expected dna, rna or nucleic"
+            ))),
+        }
+    }
+}
+
+impl LabelTarget {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LabelTarget::Dna => "dna",
+            LabelTarget::Rna => "rna",
+            LabelTarget::Nucleic => "nucleic",
+        }
+    }
+}
 
-            let b_res = &b.data;
-            let pair = format!(
-                "{a_name}_{}-{}-{}",
-                b_res.chain_id, b_res.resseq, b_res.resname
-            );
-
-            pairs
-                .entry(pair)
-                .and_modify(|v| {
-                    if d < *v {
-                        *v = d;
-                    }
+/// How a per-residue binding-site label is valued.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LabelValue {
+    /// 1 for residues within the cutoff, 0 otherwise.
+    #[default]
+    Binary,
+    /// Closest atom distance, capped at the cutoff for non-binding residues.
+    Distance,
+}
+
+impl std::str::FromStr for LabelValue {
+    type Err = PskitError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "binary" => Ok(LabelValue::Binary),
+            "distance" => Ok(LabelValue::Distance),
+            _ => Err(PskitError::InvalidArgument(format!(
+                "Unknown label value {s:?}, expected binary or distance"
+            ))),
+        }
+    }
+}
+
+/// Per-residue binding-site labels of one protein chain, aligned to its sequence.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChainLabels {
+    pub chain_id: String,
+    /// Sequence the labels are aligned to.
+    pub source: SequenceSource,
+    /// Observed residue at each sequence position (None if unmodeled).
+    pub residues: Vec<Option<ResidueKey>>,
+    pub sequence: String,
+    pub cutoff: f64,
+    /// Closest DNA atom distance of each residue, if within the cutoff.
+    pub dna: Vec<Option<f64>>,
+    pub rna: Vec<Option<f64>>,
+}
+
+impl ChainLabels {
+    pub fn distances(&self, target: LabelTarget) -> Vec<Option<f64>> {
+        match target {
+            LabelTarget::Dna => self.dna.clone(),
+            LabelTarget::Rna => self.rna.clone(),
+            LabelTarget::Nucleic => self
+                .dna
+                .iter()
+                .zip(&self.rna)
+                .map(|(d, r)| match (d, r) {
+                    (Some(d), Some(r)) => Some(d.min(*r)),
+                    _ => d.or(*r),
                 })
-                .or_insert(d);
+                .collect(),
         }
     }
 
-    for v in pairs.values_mut() {
-        *v = v.sqrt();
+    /// Label vector for `target`, one value per sequence position; unmodeled residues are NaN.
+    pub fn values(&self, target: LabelTarget, value: LabelValue) -> Vec<f64> {
+        self.distances(target)
+            .into_iter()
+            .zip(&self.residues)
+            .map(|(d, residue)| match (residue, value) {
+                (None, _) => f64::NAN,
+                (_, LabelValue::Binary) => d.map_or(0.0, |_| 1.0),
+                (_, LabelValue::Distance) => d.unwrap_or(self.cutoff),
+            })
+            .collect()
     }
+}
 
-    let mut pairs: Vec<_> = pairs.into_iter().collect();
-    pairs.sort_by(|ka, kb| {
-        let a = key_for_sort(&ka.0);
-        let b = key_for_sort(&kb.0);
+/// DNA/RNA binding-site labels for every protein chain of the first model.
+///
+/// A residue binds when one of its atoms lies within `cutoff` of a nucleotide, the same
+/// criterion as [`compute_binding_pairs`]. Structures without nucleic acid give all-zero
+/// labels rather than an error, so they can serve as negatives.
+///
+/// With [`SequenceSource::Seqres`] the labels follow the deposited sequence, as placed by
+/// [`map_sequences`](crate::sequence::map_sequences), so unmodeled residues keep their
+/// position; chains without a deposited sequence fall back on the observed residues.
+pub fn binding_labels<R: BufRead>(
+    reader: R,
+    cutoff: f64,
+    format: &str,
+    modified: ModifiedResidues,
+    selection: &Selection,
+    source: SequenceSource,
+) -> Result<Vec<ChainLabels>, PskitError> {
+    let deposited = Deposited::read(reader, format, modified, selection)?;
+    let (pdb, classifier) = (&deposited.pdb, &deposited.classifier);
+    let Some(model) = pdb.models().next() else {
+        return Ok(Vec::new());
+    };
 
-        a.0.cmp(&b.0)
-            .then_with(|| a.1.cmp(&b.1))
-            .then_with(|| a.2.cmp(&b.2))
-            .then_with(|| a.3.cmp(&b.3))
-            .then_with(|| a.4.cmp(&b.4))
-    });
+    let mut closest: HashMap<(ResidueKey, PolymerType), f64> = HashMap::new();
+    for ((protein, nucleic), pair) in model_pairs(model, classifier, cutoff) {
+        let Some(ty) = classifier.classify(&nucleic.chain_id, &nucleic.name) else {
+            continue;
+        };
+        let d = closest.entry((protein, ty)).or_insert(f64::INFINITY);
+        *d = d.min(pair.distance.sqrt());
+    }
+    let label = |chain_id: String,
+                 source: SequenceSource,
+                 residues: Vec<Option<ResidueKey>>,
+                 sequence: String| {
+        let distance = |key: &Option<ResidueKey>, ty| {
+            key.as_ref()
+                .and_then(|key| closest.get(&(key.clone(), ty)).copied())
+        };
+        ChainLabels {
+            dna: residues
+                .iter()
+                .map(|key| distance(key, PolymerType::Dna))
+                .collect(),
+            rna: residues
+                .iter()
+                .map(|key| distance(key, PolymerType::Rna))
+                .collect(),
+            chain_id,
+            source,
+            residues,
+            sequence,
+            cutoff,
+        }
+    };
 
-    Ok(pairs)
+    let mut labels = Vec::new();
+    if source == SequenceSource::Seqres {
+        for mapping in map_deposited(&deposited) {
+            if mapping.polymer_type == Some(PolymerType::Protein) {
+                labels.push(label(
+                    mapping.chain_id,
+                    mapping.source,
+                    mapping.residues,
+                    mapping.sequence,
+                ));
+            }
+        }
+        return Ok(labels);
+    }
+
+    for chain in model.chains() {
+        let mut residues = Vec::new();
+        let mut sequence = String::new();
+        for residue in chain.residues().filter(|r| r.atom_count() > 0) {
+            match residue.name() {
+                Some(name) if classifier.is_protein(chain.id(), name) => {
+                    sequence.push(three_to_one(name));
+                }
+                _ => continue,
+            }
+            residues.push(Some(ResidueKey::new(chain.id(), residue)));
+        }
+        if !residues.is_empty() {
+            labels.push(label(
+                chain.id().to_string(),
+                SequenceSource::Atom,
+                residues,
+                sequence,
+            ));
+        }
+    }
+
+    Ok(labels)
+}
+
+/// Closest atom pair between a residue of selection A and a residue of selection B.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InterfaceContact {
+    pub a: ResidueKey,
+    pub b: ResidueKey,
+    pub a_atom: String,
+    pub b_atom: String,
+    pub distance: f64,
+    /// Atom pairs under the cutoff.
+    pub contacts: usize,
+}
+
+/// Side of an interface a residue was selected on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InterfaceSide {
+    A,
+    B,
+}
+
+/// Interface label of one residue of either selection.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InterfaceResidue {
+    pub key: ResidueKey,
+    pub side: InterfaceSide,
+    /// Distance to the closest atom of the other selection, if within the cutoff.
+    pub distance: Option<f64>,
+    /// Residues of the other selection in contact.
+    pub partners: usize,
+}
+
+impl InterfaceResidue {
+    pub fn is_interface(&self) -> bool {
+        self.distance.is_some()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Interface {
+    pub contacts: Vec<InterfaceContact>,
+    /// Every residue of A then every residue of B, in structure order.
+    pub residues: Vec<InterfaceResidue>,
+}
+
+/// Interface between two arbitrary selections of the first model, e.g. `chain H or chain L`
+/// against `chain A`, `protein` against `ligand`, or `nucleic` against `ligand`.
+///
+/// Atoms matched by both selections only pair with atoms of other residues. Two residues that
+/// are both in A and in B (e.g. `protein` against `chain A`) are one contact, with the lower
+/// [`ResidueKey`] as `a`, so overlapping selections do not count it twice.
+pub fn annotate_interface<R: BufRead>(
+    reader: R,
+    a: &Selection,
+    b: &Selection,
+    cutoff: f64,
+    format: &str,
+    modified: ModifiedResidues,
+) -> Result<Interface, PskitError> {
+    let (pdb, classifier) = read_selected(
+        reader,
+        format,
+        ReadOptions::default(),
+        modified,
+        &Selection::all(),
+    )?;
+    a.check_chains(&pdb)?;
+    b.check_chains(&pdb)?;
+
+    let Some(model) = pdb.models().next() else {
+        return Ok(Interface::default());
+    };
+    let in_a = a.mask(model, &classifier);
+    let in_b = b.mask(model, &classifier);
+
+    let mut b_points: Vec<GeomWithData<[f64; 3], (ResidueKey, String)>> = Vec::new();
+    let mut a_order: Vec<ResidueKey> = Vec::new();
+    let mut b_order: Vec<ResidueKey> = Vec::new();
+    for (i, atom) in model.atoms_with_hierarchy().enumerate() {
+        let key = ResidueKey::new(atom.chain().id(), atom.residue());
+        if in_a[i] && a_order.last() != Some(&key) {
+            a_order.push(key.clone());
+        }
+        if in_b[i] {
+            if b_order.last() != Some(&key) {
+                b_order.push(key.clone());
+            }
+            let (x, y, z) = atom.atom().pos();
+            b_points.push(GeomWithData::new(
+                [x, y, z],
+                (key, atom.atom().name().to_string()),
+            ));
+        }
+    }
+    let tree = RTree::bulk_load(b_points);
+
+    let mut pairs: HashMap<(ResidueKey, ResidueKey), InterfaceContact> = HashMap::new();
+    for (i, atom) in model.atoms_with_hierarchy().enumerate() {
+        if !in_a[i] {
+            continue;
+        }
+        let a_key = ResidueKey::new(atom.chain().id(), atom.residue());
+        let (x, y, z) = atom.atom().pos();
+        for hit in tree.locate_within_distance([x, y, z], cutoff * cutoff) {
+            let (b_key, b_atom) = &hit.data;
+            if *b_key == a_key {
+                continue;
+            }
+            let p = hit.geom();
+            let d = ((x - p[0]).powi(2) + (y - p[1]).powi(2) + (z - p[2]).powi(2)).sqrt();
+            let contact = pairs
+                .entry((a_key.clone(), b_key.clone()))
+                .or_insert_with(|| InterfaceContact {
+                    a: a_key.clone(),
+                    b: b_key.clone(),
+                    a_atom: atom.atom().name().to_string(),
+                    b_atom: b_atom.clone(),
+                    distance: d,
+                    contacts: 0,
+                });
+            if d < contact.distance {
+                contact.distance = d;
+                contact.a_atom = atom.atom().name().to_string();
+                contact.b_atom = b_atom.clone();
+            }
+            contact.contacts += 1;
+        }
+    }
+
+    // 两侧都选中的残基之间只保留一个方向
+    let a_keys: HashSet<&ResidueKey> = a_order.iter().collect();
+    let shared: HashSet<&ResidueKey> = b_order.iter().filter(|k| a_keys.contains(k)).collect();
+    let mut contacts: Vec<_> = pairs
+        .into_values()
+        .filter(|c| c.a < c.b || !(shared.contains(&c.a) && shared.contains(&c.b)))
+        .collect();
+    contacts.sort_by(|x, y| (&x.a, &x.b).cmp(&(&y.a, &y.b)));
+
+    let mut best: HashMap<(InterfaceSide, &ResidueKey), (f64, usize)> = HashMap::new();
+    for c in &contacts {
+        for key in [(InterfaceSide::A, &c.a), (InterfaceSide::B, &c.b)] {
+            let entry = best.entry(key).or_insert((f64::INFINITY, 0));
+            entry.0 = entry.0.min(c.distance);
+            entry.1 += 1;
+        }
+    }
+    let label = |side, key: &ResidueKey| {
+        let hit = best.get(&(side, key));
+        InterfaceResidue {
+            key: key.clone(),
+            side,
+            distance: hit.map(|h| h.0),
+            partners: hit.map_or(0, |h| h.1),
+        }
+    };
+    let residues = a_order
+        .iter()
+        .map(|key| label(InterfaceSide::A, key))
+        .chain(b_order.iter().map(|key| label(InterfaceSide::B, key)))
+        .collect();
+
+    Ok(Interface { contacts, residues })
 }
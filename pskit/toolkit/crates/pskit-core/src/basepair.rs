@@ -0,0 +1,561 @@
+use pdbtbx::Model;
+use rstar::{primitives::GeomWithData, RTree};
+use std::collections::HashSet;
+use std::io::BufRead;
+
+use crate::chem::{ModifiedResidues, ResidueClassifier};
+use crate::error::PskitError;
+use crate::interaction::{
+    cross, dist, dot, line_angle, lookup, model_residues, norm, sub, Res, Vec3, BASE_ACCEPTORS,
+    BASE_DONORS, SIX_RING,
+};
+use crate::residue::ResidueKey;
+use crate::selection::{read_selected, Selection};
+use crate::utils::ReadOptions;
+
+/// Base edge of the Leontis–Westhof classification.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum BaseEdge {
+    WatsonCrick,
+    Hoogsteen,
+    Sugar,
+}
+
+impl BaseEdge {
+    pub fn code(&self) -> char {
+        match self {
+            BaseEdge::WatsonCrick => 'W',
+            BaseEdge::Hoogsteen => 'H',
+            BaseEdge::Sugar => 'S',
+        }
+    }
+}
+
+/// Relative orientation of the two glycosidic bonds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Orientation {
+    Cis,
+    Trans,
+}
+
+impl Orientation {
+    pub fn code(&self) -> char {
+        match self {
+            Orientation::Cis => 'c',
+            Orientation::Trans => 't',
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PairClass {
+    /// cWW A–U, A–T or G–C.
+    WatsonCrick,
+    /// cWW G–U or G–T.
+    Wobble,
+    NonCanonical,
+}
+
+impl PairClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PairClass::WatsonCrick => "watson_crick",
+            PairClass::Wobble => "wobble",
+            PairClass::NonCanonical => "non_canonical",
+        }
+    }
+
+    /// Pairs written as brackets in dot-bracket notation.
+    pub fn is_canonical(&self) -> bool {
+        !matches!(self, PairClass::NonCanonical)
+    }
+}
+
+/// Geometric criteria of base pairs and stacking; distances in Å, angles in degrees.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BasePairCriteria {
+    /// Donor–acceptor distance of base–base H-bonds.
+    pub hbond_distance: f64,
+    /// Maximum angle between the two base planes of a pair.
+    pub pair_angle: f64,
+    /// Maximum distance of one base centroid from the mean base plane of a pair.
+    pub pair_offset: f64,
+    /// Ring centroid distance of stacked bases.
+    pub stacking_distance: f64,
+    /// Maximum angle between the two base planes of a stack.
+    pub stacking_angle: f64,
+    /// Minimum rise between stacked bases, which keeps coplanar neighbours out.
+    pub stacking_rise: f64,
+}
+
+impl Default for BasePairCriteria {
+    fn default() -> Self {
+        BasePairCriteria {
+            hbond_distance: 3.5,
+            pair_angle: 65.0,
+            pair_offset: 2.5,
+            stacking_distance: 5.5,
+            stacking_angle: 30.0,
+            stacking_rise: 2.5,
+        }
+    }
+}
+
+/// One base pair; `a` precedes `b` in the file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BasePair {
+    pub a: ResidueKey,
+    pub b: ResidueKey,
+    pub edge_a: BaseEdge,
+    pub edge_b: BaseEdge,
+    pub orientation: Orientation,
+    pub class: PairClass,
+    /// H-bonds as `(atom of a, atom of b, distance)`.
+    pub hbonds: Vec<(String, String, f64)>,
+}
+
+impl BasePair {
+    /// Leontis–Westhof family, e.g. "cWW" or "tHS".
+    pub fn family(&self) -> String {
+        format!(
+            "{}{}{}",
+            self.orientation.code(),
+            self.edge_a.code(),
+            self.edge_b.code()
+        )
+    }
+}
+
+/// Two stacked bases; `a` precedes `b` in the file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BaseStack {
+    pub a: ResidueKey,
+    pub b: ResidueKey,
+    /// Closest ring centroid distance.
+    pub distance: f64,
+    /// Angle between the two rings.
+    pub angle: f64,
+}
+
+/// Secondary structure of one nucleic-acid chain.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ChainDotBracket {
+    pub chain_id: String,
+    pub residues: Vec<ResidueKey>,
+    pub sequence: String,
+    /// One character per residue: `.` unpaired, `()` nested pairs, then `[]`, `{}`, `<>` and
+    /// `Aa`, `Bb`, … for each pseudoknot level.
+    pub dot_bracket: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NucleicStructure {
+    /// Sorted by the file order of `a`, then `b`.
+    pub pairs: Vec<BasePair>,
+    pub stacks: Vec<BaseStack>,
+    pub chains: Vec<ChainDotBracket>,
+}
+
+impl NucleicStructure {
+    /// Whether the nucleotide forms any base pair, canonical or not.
+    pub fn is_paired(&self, key: &ResidueKey) -> bool {
+        self.pairs.iter().any(|p| &p.a == key || &p.b == key)
+    }
+}
+
+/// Polar base atoms with the edges they belong to.
+type EdgeTable = &'static [(&'static str, &'static [BaseEdge])];
+
+/// A base pair with the positions of both nucleotides in the model.
+type IndexedPair = (usize, usize, BasePair);
+
+// 各原子所在的边；N6/O6/O4/N4 位于 W 与 H 之间，N2/O2 位于 W 与 S 之间
+const EDGE_ATOMS: &[(char, EdgeTable)] = {
+    use BaseEdge::{Hoogsteen as H, Sugar as S, WatsonCrick as W};
+    &[
+        (
+            'A',
+            &[("N1", &[W]), ("N6", &[W, H]), ("N7", &[H]), ("N3", &[S])],
+        ),
+        (
+            'G',
+            &[
+                ("N1", &[W]),
+                ("N2", &[W, S]),
+                ("O6", &[W, H]),
+                ("N7", &[H]),
+                ("N3", &[S]),
+            ],
+        ),
+        ('C', &[("N3", &[W]), ("N4", &[W, H]), ("O2", &[W, S])]),
+        ('U', &[("N3", &[W]), ("O4", &[W, H]), ("O2", &[W, S])]),
+        ('T', &[("N3", &[W]), ("O4", &[W, H]), ("O2", &[W, S])]),
+    ]
+};
+
+const BRACKETS: &[(char, char)] = &[('(', ')'), ('[', ']'), ('{', '}'), ('<', '>')];
+
+fn unit(a: Vec3) -> Vec3 {
+    let n = norm(a);
+    a.map(|v| v / n)
+}
+
+fn edges_of(base: char, atom: &str) -> &'static [BaseEdge] {
+    if atom == "O2'" {
+        return &[BaseEdge::Sugar];
+    }
+    EDGE_ATOMS
+        .iter()
+        .find(|(b, _)| *b == base)
+        .and_then(|(_, atoms)| atoms.iter().find(|(name, _)| *name == atom))
+        .map_or(&[], |(_, edges)| edges)
+}
+
+/// A nucleotide with a known base and a complete six-membered ring.
+struct Base<'a> {
+    res: &'a Res,
+    letter: char,
+    centroid: Vec3,
+    normal: Vec3,
+    c1: Option<Vec3>,
+}
+
+impl Base<'_> {
+    fn is_donor(&self, atom: &str) -> bool {
+        atom == "O2'" || lookup(BASE_DONORS, self.letter).contains(&atom)
+    }
+
+    fn is_acceptor(&self, atom: &str) -> bool {
+        atom == "O2'" || lookup(BASE_ACCEPTORS, self.letter).contains(&atom)
+    }
+}
+
+/// Edge with the most H-bonds; ties go to W, then H, then S.
+fn dominant_edge(votes: &[BaseEdge]) -> BaseEdge {
+    [BaseEdge::WatsonCrick, BaseEdge::Hoogsteen, BaseEdge::Sugar]
+        .into_iter()
+        .rev()
+        .max_by_key(|edge| votes.iter().filter(|v| *v == edge).count())
+        .unwrap_or(BaseEdge::WatsonCrick)
+}
+
+fn classify(a: &Base, b: &Base, pair: &BasePair) -> PairClass {
+    if pair.family() != "cWW" {
+        return PairClass::NonCanonical;
+    }
+    let mut letters = [a.letter, b.letter];
+    letters.sort();
+    match letters {
+        ['A', 'U'] | ['A', 'T'] | ['C', 'G'] => PairClass::WatsonCrick,
+        // 摆动配对需要 O6–N3 与 N1–O2 两个 H-bond
+        ['G', 'U'] | ['G', 'T'] if pair.hbonds.len() >= 2 => PairClass::Wobble,
+        _ => PairClass::NonCanonical,
+    }
+}
+
+fn pair_of(a: &Base, b: &Base, criteria: &BasePairCriteria) -> Option<BasePair> {
+    if line_angle(a.normal, b.normal) > criteria.pair_angle {
+        return None;
+    }
+    // 两个法向量取同向后平均
+    let nb = if dot(a.normal, b.normal) < 0.0 {
+        b.normal.map(|v| -v)
+    } else {
+        b.normal
+    };
+    let normal = unit([
+        a.normal[0] + nb[0],
+        a.normal[1] + nb[1],
+        a.normal[2] + nb[2],
+    ]);
+    if dot(sub(b.centroid, a.centroid), normal).abs() > criteria.pair_offset {
+        return None;
+    }
+
+    let mut hbonds = Vec::new();
+    let (mut votes_a, mut votes_b) = (Vec::new(), Vec::new());
+    let mut direction = [0.0; 3];
+    for atom_a in &a.res.atoms {
+        let edges_a = edges_of(a.letter, &atom_a.name);
+        if edges_a.is_empty() {
+            continue;
+        }
+        for atom_b in &b.res.atoms {
+            let edges_b = edges_of(b.letter, &atom_b.name);
+            if edges_b.is_empty() || atom_a.name == "O2'" && atom_b.name == "O2'" {
+                continue;
+            }
+            let d = dist(atom_a.pos, atom_b.pos);
+            let polar = a.is_donor(&atom_a.name) && b.is_acceptor(&atom_b.name)
+                || b.is_donor(&atom_b.name) && a.is_acceptor(&atom_a.name);
+            if d <= criteria.hbond_distance && polar {
+                hbonds.push((atom_a.name.clone(), atom_b.name.clone(), d));
+                votes_a.extend_from_slice(edges_a);
+                votes_b.extend_from_slice(edges_b);
+                let step = unit(sub(atom_b.pos, atom_a.pos));
+                direction = std::array::from_fn(|k| direction[k] + step[k]);
+            }
+        }
+    }
+    // 只有 2'-OH 参与的接触不算碱基对
+    if hbonds.iter().all(|(x, y, _)| x == "O2'" || y == "O2'") {
+        return None;
+    }
+
+    // cis：两个 C1' 位于轴线同侧；轴线沿 H-bond 平均方向，穿过两碱基质心的中点
+    let middle: Vec3 = std::array::from_fn(|k| (a.centroid[k] + b.centroid[k]) / 2.0);
+    let side = |c1: Option<Vec3>| c1.map(|p| dot(cross(direction, sub(p, middle)), normal));
+    let orientation = match (side(a.c1), side(b.c1)) {
+        (Some(sa), Some(sb)) if sa * sb < 0.0 => Orientation::Trans,
+        _ => Orientation::Cis,
+    };
+    let mut pair = BasePair {
+        a: a.res.key.clone(),
+        b: b.res.key.clone(),
+        edge_a: dominant_edge(&votes_a),
+        edge_b: dominant_edge(&votes_b),
+        orientation,
+        class: PairClass::NonCanonical,
+        hbonds,
+    };
+    pair.class = classify(a, b, &pair);
+    Some(pair)
+}
+
+fn stack_of(a: &Base, b: &Base, criteria: &BasePairCriteria) -> Option<BaseStack> {
+    let mut best: Option<BaseStack> = None;
+    for (ca, na, _) in a.res.base_rings() {
+        for (cb, nb, _) in b.res.base_rings() {
+            let d = dist(ca, cb);
+            let angle = line_angle(na, nb);
+            let rise = dot(sub(cb, ca), unit(na)).abs();
+            let stacked = d <= criteria.stacking_distance
+                && angle <= criteria.stacking_angle
+                && rise >= criteria.stacking_rise;
+            if stacked && best.as_ref().is_none_or(|s| d < s.distance) {
+                best = Some(BaseStack {
+                    a: a.res.key.clone(),
+                    b: b.res.key.clone(),
+                    distance: d,
+                    angle,
+                });
+            }
+        }
+    }
+    best
+}
+
+/// The pairable bases of the nucleotides, with an R-tree of their centroids.
+fn bases_of(nucleic: &[Res]) -> (Vec<Option<Base<'_>>>, RTree<GeomWithData<Vec3, usize>>) {
+    let bases: Vec<Option<Base>> = nucleic
+        .iter()
+        .map(|res| {
+            let letter = res.base_letter();
+            let (centroid, normal, _) = res.ring(SIX_RING)?;
+            matches!(letter, 'A' | 'C' | 'G' | 'U' | 'T').then(|| Base {
+                res,
+                letter,
+                centroid,
+                normal: unit(normal),
+                c1: res.atom("C1'").map(|a| a.pos),
+            })
+        })
+        .collect();
+    let tree = RTree::bulk_load(
+        bases
+            .iter()
+            .enumerate()
+            .filter_map(|(i, base)| base.as_ref().map(|b| GeomWithData::new(b.centroid, i)))
+            .collect(),
+    );
+    (bases, tree)
+}
+
+/// Centroid distance within which two bases may pair or stack.
+fn reach(criteria: &BasePairCriteria) -> f64 {
+    criteria
+        .stacking_distance
+        .max(criteria.hbond_distance + 9.0)
+}
+
+/// Base pairs and stacks of one model, as indices into the nucleotides of the model.
+pub(crate) fn model_base_pairs(
+    model: &Model,
+    classifier: &ResidueClassifier,
+    criteria: &BasePairCriteria,
+) -> (Vec<Res>, Vec<IndexedPair>, Vec<BaseStack>) {
+    let (_, nucleic) = model_residues(model, classifier);
+    let (bases, tree) = bases_of(&nucleic);
+
+    // 碱基对与堆积的质心距离都在 reach 以内
+    let reach = reach(criteria);
+    let mut pairs = Vec::new();
+    let mut stacks = Vec::new();
+    for (i, a) in bases.iter().enumerate() {
+        let Some(a) = a else { continue };
+        let mut near: Vec<usize> = tree
+            .locate_within_distance(a.centroid, reach * reach)
+            .map(|hit| hit.data)
+            .filter(|&j| j > i)
+            .collect();
+        near.sort_unstable();
+        for j in near {
+            let Some(b) = &bases[j] else { continue };
+            if let Some(pair) = pair_of(a, b, criteria) {
+                pairs.push((i, j, pair));
+            } else if let Some(stack) = stack_of(a, b, criteria) {
+                stacks.push(stack);
+            }
+        }
+    }
+    (nucleic, pairs, stacks)
+}
+
+/// Which of the `targets` nucleotides of the model form any base pair.
+///
+/// Only the targets are tested against their neighbours, so this is much cheaper than
+/// [`model_base_pairs`] when few nucleotides are of interest.
+pub(crate) fn paired_nucleotides(
+    model: &Model,
+    classifier: &ResidueClassifier,
+    criteria: &BasePairCriteria,
+    targets: &HashSet<&ResidueKey>,
+) -> HashSet<ResidueKey> {
+    let (_, nucleic) = model_residues(model, classifier);
+    let (bases, tree) = bases_of(&nucleic);
+
+    let reach = reach(criteria);
+    let mut paired = HashSet::new();
+    for (i, a) in bases.iter().enumerate() {
+        let Some(a) = a.as_ref().filter(|a| targets.contains(&a.res.key)) else {
+            continue;
+        };
+        let found = tree
+            .locate_within_distance(a.centroid, reach * reach)
+            .filter(|hit| hit.data != i)
+            .any(|hit| {
+                let Some(b) = &bases[hit.data] else {
+                    return false;
+                };
+                // 与 model_base_pairs 一致，总以文件顺序靠前的核苷酸为 a
+                let pair = if i < hit.data {
+                    pair_of(a, b, criteria)
+                } else {
+                    pair_of(b, a, criteria)
+                };
+                pair.is_some()
+            });
+        if found {
+            paired.insert(a.res.key.clone());
+        }
+    }
+    paired
+}
+
+/// Canonical pairs kept for the dot-bracket, one per nucleotide: the pair with most H-bonds
+/// wins in base triples.
+fn bracket_pairs(pairs: &[IndexedPair]) -> Vec<(usize, usize)> {
+    let mut candidates: Vec<&IndexedPair> = pairs
+        .iter()
+        .filter(|(_, _, p)| p.class.is_canonical())
+        .collect();
+    candidates.sort_by_key(|(i, j, p)| (std::cmp::Reverse(p.hbonds.len()), *i, *j));
+    let mut used = HashSet::new();
+    let mut kept: Vec<(usize, usize)> = Vec::new();
+    for &(i, j, _) in candidates {
+        if !used.contains(&i) && !used.contains(&j) {
+            used.extend([i, j]);
+            kept.push((i, j));
+        }
+    }
+    kept.sort_unstable();
+    kept
+}
+
+fn bracket(level: usize) -> (char, char) {
+    match BRACKETS.get(level) {
+        Some(&pair) => pair,
+        None => {
+            let letter = (b'A' + ((level - BRACKETS.len()) % 26) as u8) as char;
+            (letter, letter.to_ascii_lowercase())
+        }
+    }
+}
+
+/// Dot-bracket of each chain from its intra-chain canonical pairs.
+fn dot_brackets(nucleic: &[Res], pairs: &[IndexedPair]) -> Vec<ChainDotBracket> {
+    // 链按首次出现的顺序排列
+    let mut chains: Vec<ChainDotBracket> = Vec::new();
+    let mut position = Vec::with_capacity(nucleic.len());
+    for res in nucleic {
+        let c = match chains.iter().position(|c| c.chain_id == res.key.chain_id) {
+            Some(c) => c,
+            None => {
+                chains.push(ChainDotBracket {
+                    chain_id: res.key.chain_id.clone(),
+                    ..Default::default()
+                });
+                chains.len() - 1
+            }
+        };
+        let chain = &mut chains[c];
+        position.push((c, chain.residues.len()));
+        chain.residues.push(res.key.clone());
+        chain.sequence.push(res.base_letter());
+    }
+
+    let mut symbols: Vec<Vec<char>> = chains
+        .iter()
+        .map(|chain| vec!['.'; chain.residues.len()])
+        .collect();
+    // 按左端顺序逐层放置，每层内的配对互不交叉
+    let mut levels: Vec<Vec<(usize, usize)>> = Vec::new();
+    for (i, j) in bracket_pairs(pairs) {
+        let ((ci, pi), (cj, pj)) = (position[i], position[j]);
+        if ci != cj {
+            continue;
+        }
+        let level = levels
+            .iter()
+            .position(|pairs| pairs.iter().all(|&(_, l)| !(i < l && l < j)))
+            .unwrap_or_else(|| {
+                levels.push(Vec::new());
+                levels.len() - 1
+            });
+        levels[level].push((i, j));
+        let (open, close) = bracket(level);
+        symbols[ci][pi] = open;
+        symbols[cj][pj] = close;
+    }
+    for (chain, symbols) in chains.iter_mut().zip(symbols) {
+        chain.dot_bracket = symbols.into_iter().collect();
+    }
+    chains
+}
+
+/// Base pairs, base stacking and per-chain dot-bracket of the first model.
+///
+/// Pairs need a base–base H-bond (2'-OH counts for the sugar edge) between roughly coplanar
+/// bases. Each base takes the edge holding most of its H-bonds, and the pair is cis when both
+/// C1' atoms lie on the same side of the line along the H-bonds through the midpoint of the
+/// base centroids. The dot-bracket holds Watson–Crick and wobble pairs within a chain; pairs
+/// across chains are only listed in `pairs`.
+pub fn compute_base_pairs<R: BufRead>(
+    reader: R,
+    format: &str,
+    modified: ModifiedResidues,
+    selection: &Selection,
+    criteria: &BasePairCriteria,
+) -> Result<NucleicStructure, PskitError> {
+    let (pdb, classifier) =
+        read_selected(reader, format, ReadOptions::default(), modified, selection)?;
+    let Some(model) = pdb.models().next() else {
+        return Ok(NucleicStructure::default());
+    };
+    let (nucleic, pairs, stacks) = model_base_pairs(model, &classifier, criteria);
+    Ok(NucleicStructure {
+        chains: dot_brackets(&nucleic, &pairs),
+        pairs: pairs.into_iter().map(|(_, _, pair)| pair).collect(),
+        stacks,
+    })
+}
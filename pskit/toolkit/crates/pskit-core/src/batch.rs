@@ -0,0 +1,169 @@
+use std::borrow::Cow;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+
+use crate::error::PskitError;
+use crate::utils::{detect_format, read_error};
+
+/// One structure of a batch.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BatchSource {
+    File(PathBuf),
+    /// Already loaded data, e.g. stdin or an upload; `name` is used for format detection.
+    Bytes {
+        name: String,
+        data: Vec<u8>,
+    },
+}
+
+impl BatchSource {
+    pub fn name(&self) -> String {
+        match self {
+            BatchSource::File(path) => path.display().to_string(),
+            BatchSource::Bytes { name, .. } => name.clone(),
+        }
+    }
+
+    fn load(&self) -> Result<Cow<'_, [u8]>, PskitError> {
+        match self {
+            BatchSource::File(path) => std::fs::read(path).map(Cow::Owned).map_err(read_error),
+            BatchSource::Bytes { data, .. } => Ok(Cow::Borrowed(data)),
+        }
+    }
+}
+
+impl From<PathBuf> for BatchSource {
+    fn from(path: PathBuf) -> Self {
+        BatchSource::File(path)
+    }
+}
+
+/// What the batch operation is given for each source.
+#[derive(Clone, Copy, Debug)]
+pub struct BatchInput<'a> {
+    pub source: &'a BatchSource,
+    pub data: &'a [u8],
+    /// `BatchOptions::format`, or detected from the name and content.
+    pub format: &'a str,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BatchOptions {
+    /// Worker threads; 0 uses one per core. Ignored on wasm, which runs sequentially.
+    pub threads: usize,
+    /// Format of every source instead of detecting it per file.
+    pub format: Option<String>,
+}
+
+/// Outcome of one source, streamed as soon as it is done.
+#[derive(Debug)]
+pub struct BatchResult<T> {
+    /// Position of the source in the batch; results arrive in completion order.
+    pub index: usize,
+    pub result: Result<T, PskitError>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BatchProgress {
+    pub done: usize,
+    pub failed: usize,
+    pub total: usize,
+}
+
+/// Run the operation on one source; a panic (e.g. inside pdbtbx) becomes that source's error.
+fn run_one<T, F>(
+    source: &BatchSource,
+    options: &BatchOptions,
+    operation: &F,
+) -> Result<T, PskitError>
+where
+    F: Fn(BatchInput<'_>) -> Result<T, PskitError>,
+{
+    let data = source.load()?;
+    let format = match &options.format {
+        Some(format) => format.as_str(),
+        None => detect_format(&source.name(), &data),
+    };
+    let input = BatchInput {
+        source,
+        data: &data,
+        format,
+    };
+    panic::catch_unwind(AssertUnwindSafe(|| operation(input))).unwrap_or_else(|payload| {
+        let detail = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        Err(PskitError::Internal(detail))
+    })
+}
+
+/// Run `operation` on every source, in parallel on native targets.
+///
+/// Each result, including per-source errors, is handed to `on_result` on the calling thread as
+/// soon as it is ready, followed by `on_progress`; a failing or panicking source never stops
+/// the batch.
+/// Returns the final progress.
+pub fn run_batch<T, F, R, P>(
+    sources: &[BatchSource],
+    options: &BatchOptions,
+    operation: F,
+    mut on_result: R,
+    mut on_progress: P,
+) -> BatchProgress
+where
+    T: Send,
+    F: Fn(BatchInput<'_>) -> Result<T, PskitError> + Sync,
+    R: FnMut(BatchResult<T>),
+    P: FnMut(BatchProgress),
+{
+    let mut progress = BatchProgress {
+        done: 0,
+        failed: 0,
+        total: sources.len(),
+    };
+    let mut report = |item: BatchResult<T>| {
+        progress.done += 1;
+        progress.failed += item.result.is_err() as usize;
+        on_result(item);
+        on_progress(progress);
+    };
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use rayon::prelude::*;
+        use std::sync::mpsc;
+
+        if let Ok(pool) = rayon::ThreadPoolBuilder::new()
+            .num_threads(options.threads)
+            .build()
+        {
+            let (sender, receiver) = mpsc::channel();
+            std::thread::scope(|scope| {
+                scope.spawn(|| {
+                    pool.install(|| {
+                        sources.par_iter().enumerate().for_each_with(
+                            sender,
+                            |sender, (index, source)| {
+                                let result = run_one(source, options, &operation);
+                                let _ = sender.send(BatchResult { index, result });
+                            },
+                        )
+                    })
+                });
+                // 所有工作线程结束后 sender 被释放，接收循环随之退出
+                for item in receiver {
+                    report(item);
+                }
+            });
+            return progress;
+        }
+    }
+
+    for (index, source) in sources.iter().enumerate() {
+        let result = run_one(source, options, &operation);
+        report(BatchResult { index, result });
+    }
+    progress
+}
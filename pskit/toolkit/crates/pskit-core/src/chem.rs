@@ -0,0 +1,382 @@
+use crate::cif::{is_null, CifIndex};
+use crate::utils::{NUCLEIC_RESIDUES, PROTEIN_RESIDUES};
+use pdbtbx::PDB;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PolymerType {
+    Protein,
+    Dna,
+    Rna,
+}
+
+impl PolymerType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PolymerType::Protein => "protein",
+            PolymerType::Dna => "dna",
+            PolymerType::Rna => "rna",
+        }
+    }
+
+    pub fn is_nucleic(self) -> bool {
+        matches!(self, PolymerType::Dna | PolymerType::Rna)
+    }
+
+    /// Map an mmCIF `_entity_poly.type` value.
+    pub fn from_entity_poly_type(ty: &str) -> Option<Self> {
+        match ty.trim().to_lowercase().as_str() {
+            "polypeptide(l)" | "polypeptide(d)" => Some(PolymerType::Protein),
+            "polydeoxyribonucleotide" => Some(PolymerType::Dna),
+            "polyribonucleotide" | "polydeoxyribonucleotide/polyribonucleotide hybrid" => {
+                Some(PolymerType::Rna)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Whether modified residues (MSE, SEP, PSU, ...) are treated as part of the polymer or as ligands.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ModifiedResidues {
+    #[default]
+    Polymer,
+    Ligand,
+}
+
+impl std::str::FromStr for ModifiedResidues {
+    type Err = crate::PskitError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "polymer" => Ok(ModifiedResidues::Polymer),
+            "ligand" => Ok(ModifiedResidues::Ligand),
+            _ => Err(crate::PskitError::InvalidArgument(format!(
+                "Unknown modified residue mode {s:?}, expected \"polymer\" or \"ligand\""
+            ))),
+        }
+    }
+}
+
+/// Common modified residues and their standard parent, sorted by name for binary search.
+const MODIFIED_RESIDUES: &[(&str, &str)] = &[
+    ("0A", "A"),
+    ("1AP", "A"),
+    ("1MA", "A"),
+    ("1MG", "G"),
+    ("2MA", "A"),
+    ("2MG", "G"),
+    ("2MU", "U"),
+    ("4OC", "C"),
+    ("4SU", "U"),
+    ("5BU", "U"),
+    ("5CM", "DC"),
+    ("5FU", "U"),
+    ("5HC", "DC"),
+    ("5IU", "U"),
+    ("5MC", "C"),
+    ("5MU", "U"),
+    ("6MA", "DA"),
+    ("6OG", "DG"),
+    ("7MG", "G"),
+    ("8OG", "DG"),
+    ("A2M", "A"),
+    ("AAR", "ARG"),
+    ("ABA", "ALA"),
+    ("AGM", "ARG"),
+    ("AIB", "ALA"),
+    ("ALY", "LYS"),
+    ("BRU", "DU"),
+    ("CAS", "CYS"),
+    ("CBR", "DC"),
+    ("CCC", "C"),
+    ("CME", "CYS"),
+    ("CSD", "CYS"),
+    ("CSO", "CYS"),
+    ("CSS", "CYS"),
+    ("CSX", "CYS"),
+    ("CXM", "MET"),
+    ("DHA", "SER"),
+    ("DOC", "DC"),
+    ("FME", "MET"),
+    ("H2U", "U"),
+    ("HIC", "HIS"),
+    ("HYP", "PRO"),
+    ("IAS", "ASP"),
+    ("KCX", "LYS"),
+    ("LLP", "LYS"),
+    ("M2G", "G"),
+    ("M3L", "LYS"),
+    ("MA6", "A"),
+    ("MHO", "MET"),
+    ("MLY", "LYS"),
+    ("MLZ", "LYS"),
+    ("MSE", "MET"),
+    ("MVA", "VAL"),
+    ("NEP", "HIS"),
+    ("NLE", "LEU"),
+    ("OCS", "CYS"),
+    ("OMC", "C"),
+    ("OMG", "G"),
+    ("OMU", "U"),
+    ("ORN", "ALA"),
+    ("PCA", "GLN"),
+    ("PSU", "U"),
+    ("PTR", "TYR"),
+    ("SAC", "SER"),
+    ("SEC", "CYS"),
+    ("SEP", "SER"),
+    ("SMC", "CYS"),
+    ("TPO", "THR"),
+    ("TPQ", "TYR"),
+    ("TYS", "TYR"),
+    ("UR3", "U"),
+    ("YCM", "CYS"),
+    ("YYG", "G"),
+];
+
+/// Standard parent of a known modified residue, e.g. `MSE -> MET`, `PSU -> U`.
+pub fn parent_residue(name: &str) -> Option<&'static str> {
+    MODIFIED_RESIDUES
+        .binary_search_by(|(modified, _)| (*modified).cmp(name))
+        .ok()
+        .map(|i| MODIFIED_RESIDUES[i].1)
+}
+
+/// The standard residue a name stands for: itself if standard, its parent if a known modification.
+pub fn standard_residue(name: &str) -> Option<&str> {
+    if PROTEIN_RESIDUES.contains(&name) || NUCLEIC_RESIDUES.contains(&name) {
+        Some(name)
+    } else {
+        parent_residue(name)
+    }
+}
+
+fn standard_polymer_type(name: &str) -> Option<PolymerType> {
+    if PROTEIN_RESIDUES.contains(&name) {
+        Some(PolymerType::Protein)
+    } else if matches!(name, "DA" | "DC" | "DG" | "DT" | "DU" | "DI") {
+        Some(PolymerType::Dna)
+    } else if matches!(name, "A" | "C" | "G" | "U" | "I") {
+        Some(PolymerType::Rna)
+    } else {
+        None
+    }
+}
+
+/// Polymer type implied by the residue name alone (standard residues and the modified-residue table).
+pub fn residue_polymer_type(name: &str) -> Option<PolymerType> {
+    standard_polymer_type(name).or_else(|| parent_residue(name).and_then(standard_polymer_type))
+}
+
+/// One polymer entity as declared by the deposited file (`_entity_poly` / SEQRES).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EntityPoly {
+    pub entity_id: String,
+    pub polymer_type: Option<PolymerType>,
+    /// Author chain IDs carrying this entity.
+    pub strand_ids: Vec<String>,
+    /// Residue names of the full deposited sequence, in order.
+    pub monomers: Vec<String>,
+}
+
+impl EntityPoly {
+    /// Read `_entity_poly` and `_entity_poly_seq` from mmCIF text.
+    pub fn from_mmcif(text: &str) -> Vec<EntityPoly> {
+        Self::from_cif(&CifIndex::new(text))
+    }
+
+    /// Like [`EntityPoly::from_mmcif`], from an already tokenized file.
+    pub(crate) fn from_cif(index: &CifIndex) -> Vec<EntityPoly> {
+        let mut entities: Vec<EntityPoly> = index
+            .category("_entity_poly")
+            .into_iter()
+            .filter_map(|row| {
+                let entity_id = row.get("entity_id")?.clone();
+                Some(EntityPoly {
+                    entity_id,
+                    polymer_type: row
+                        .get("type")
+                        .and_then(|ty| PolymerType::from_entity_poly_type(ty)),
+                    strand_ids: row
+                        .get("pdbx_strand_id")
+                        .filter(|v| !is_null(v))
+                        .map(|v| v.split(',').map(|s| s.trim().to_string()).collect())
+                        .unwrap_or_default(),
+                    monomers: Vec::new(),
+                })
+            })
+            .collect();
+
+        // 微不均一位点同一 num 有多行，只取第一行
+        let mut seen = HashSet::new();
+        for row in index.category("_entity_poly_seq") {
+            let (Some(entity_id), Some(mon_id)) = (row.get("entity_id"), row.get("mon_id")) else {
+                continue;
+            };
+            if let Some(num) = row.get("num") {
+                if !seen.insert((entity_id.clone(), num.clone())) {
+                    continue;
+                }
+            }
+            if let Some(entity) = entities.iter_mut().find(|e| &e.entity_id == entity_id) {
+                entity.monomers.push(mon_id.clone());
+            }
+        }
+        entities
+    }
+
+    /// Build one entity per chain from PDB `SEQRES` records; `MODRES` teaches the parents of
+    /// modified residues that are not in the built-in table.
+    pub fn from_pdb(text: &str) -> (Vec<EntityPoly>, HashMap<String, String>) {
+        let mut entities: Vec<EntityPoly> = Vec::new();
+        let mut modres = HashMap::new();
+        for line in text.lines() {
+            if line.starts_with("SEQRES") && line.len() > 19 {
+                let chain_id = line.get(11..12).unwrap_or("").trim().to_string();
+                let names = line.get(19..).unwrap_or("").split_whitespace();
+                let entity = match entities
+                    .iter_mut()
+                    .position(|e| e.strand_ids[0] == chain_id)
+                {
+                    Some(i) => &mut entities[i],
+                    None => {
+                        entities.push(EntityPoly {
+                            entity_id: chain_id.clone(),
+                            strand_ids: vec![chain_id],
+                            ..Default::default()
+                        });
+                        entities.last_mut().unwrap()
+                    }
+                };
+                entity.monomers.extend(names.map(String::from));
+            } else if line.starts_with("MODRES") {
+                let name = line.get(12..15).unwrap_or("").trim();
+                let parent = line.get(24..27).unwrap_or("").trim();
+                if !name.is_empty() && !parent.is_empty() {
+                    modres.insert(name.to_string(), parent.to_string());
+                }
+            }
+        }
+
+        for entity in &mut entities {
+            // SEQRES has no polymer type; take the most common type among the residues.
+            let mut counts: HashMap<PolymerType, usize> = HashMap::new();
+            for name in &entity.monomers {
+                let ty = residue_polymer_type(name).or_else(|| {
+                    modres
+                        .get(name)
+                        .and_then(|parent| standard_polymer_type(parent))
+                });
+                if let Some(ty) = ty {
+                    *counts.entry(ty).or_default() += 1;
+                }
+            }
+            entity.polymer_type = counts.into_iter().max_by_key(|(_, n)| *n).map(|(ty, _)| ty);
+        }
+        (entities, modres)
+    }
+}
+
+/// Decides whether a residue belongs to a protein or nucleic-acid polymer.
+///
+/// Standard residue names are always polymer residues. Other names count when the deposited
+/// polymer sequence of their chain lists them, or when they are known modifications, unless
+/// `modified` is [`ModifiedResidues::Ligand`].
+#[derive(Clone, Debug, Default)]
+pub struct ResidueClassifier {
+    chain_types: HashMap<String, PolymerType>,
+    chain_monomers: HashMap<String, HashSet<String>>,
+    modres: HashMap<String, String>,
+    modified: ModifiedResidues,
+}
+
+impl ResidueClassifier {
+    pub fn new(modified: ModifiedResidues) -> Self {
+        ResidueClassifier {
+            modified,
+            ..Default::default()
+        }
+    }
+
+    /// Classifier using the entity information of the given file text, if it has any.
+    pub fn from_text(text: &str, modified: ModifiedResidues) -> Self {
+        let (entities, modres) = if text.contains("_entity_poly") {
+            (EntityPoly::from_mmcif(text), HashMap::new())
+        } else {
+            EntityPoly::from_pdb(text)
+        };
+        Self::from_entities(&entities, modres, modified)
+    }
+
+    /// Classifier for entities already read from the file, with its `MODRES` parents.
+    pub(crate) fn from_entities(
+        entities: &[EntityPoly],
+        modres: HashMap<String, String>,
+        modified: ModifiedResidues,
+    ) -> Self {
+        let mut classifier = Self::new(modified);
+        classifier.modres = modres;
+        for entity in entities {
+            for chain_id in &entity.strand_ids {
+                if let Some(ty) = entity.polymer_type {
+                    classifier.chain_types.insert(chain_id.clone(), ty);
+                }
+                classifier
+                    .chain_monomers
+                    .entry(chain_id.clone())
+                    .or_default()
+                    .extend(entity.monomers.iter().cloned());
+            }
+        }
+        classifier
+    }
+
+    pub fn classify(&self, chain_id: &str, name: &str) -> Option<PolymerType> {
+        if let Some(ty) = standard_polymer_type(name) {
+            return Some(ty);
+        }
+        if self.modified == ModifiedResidues::Ligand {
+            return None;
+        }
+        if let Some(ty) = residue_polymer_type(name) {
+            return Some(ty);
+        }
+        if let Some(parent) = self.modres.get(name) {
+            if let Some(ty) = standard_polymer_type(parent) {
+                return Some(ty);
+            }
+        }
+        match self.chain_monomers.get(chain_id) {
+            Some(monomers) if monomers.contains(name) => self.chain_types.get(chain_id).copied(),
+            _ => None,
+        }
+    }
+
+    pub fn is_protein(&self, chain_id: &str, name: &str) -> bool {
+        self.classify(chain_id, name) == Some(PolymerType::Protein)
+    }
+
+    pub fn is_nucleic(&self, chain_id: &str, name: &str) -> bool {
+        self.classify(chain_id, name)
+            .is_some_and(PolymerType::is_nucleic)
+    }
+
+    /// True when the structure has both protein and nucleic-acid residues.
+    pub fn is_complex(&self, pdb: &PDB) -> bool {
+        let mut protein_has = false;
+        let mut nucleic_has = false;
+        for chain in pdb.chains() {
+            for residue in chain.residues() {
+                if let Some(ty) = residue.name().and_then(|n| self.classify(chain.id(), n)) {
+                    protein_has |= ty == PolymerType::Protein;
+                    nucleic_has |= ty.is_nucleic();
+                    if protein_has && nucleic_has {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+// Minimal mmCIF reader for the categories pdbtbx does not keep (`_entity_poly`, `_entity_poly_seq`, ...).
+
+struct Token<'a> {
+    text: std::borrow::Cow<'a, str>,
+    quoted: bool,
+}
+
+fn tokenize(text: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut lines = text.lines();
+    while let Some(line) = lines.next() {
+        if let Some(first) = line.strip_prefix(';') {
+            // Semicolon text field: runs until the next line starting with ';'.
+            let mut field = first.to_string();
+            for next in lines.by_ref() {
+                if next.starts_with(';') {
+                    break;
+                }
+                field.push('\n');
+                field.push_str(next);
+            }
+            tokens.push(Token {
+                text: field.trim().to_string().into(),
+                quoted: true,
+            });
+            continue;
+        }
+
+        let bytes = line.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            let c = bytes[i];
+            if c.is_ascii_whitespace() {
+                i += 1;
+            } else if c == b'#' {
+                break;
+            } else if c == b'\'' || c == b'"' {
+                // A quote only closes when followed by whitespace or the end of the line.
+                let start = i + 1;
+                let mut j = start;
+                while j < bytes.len()
+                    && !(bytes[j] == c
+                        && (j + 1 == bytes.len() || bytes[j + 1].is_ascii_whitespace()))
+                {
+                    j += 1;
+                }
+                tokens.push(Token {
+                    text: line[start..j.min(bytes.len())].into(),
+                    quoted: true,
+                });
+                i = j + 1;
+            } else {
+                let start = i;
+                while i < bytes.len() && !bytes[i].is_ascii_whitespace() {
+                    i += 1;
+                }
+                tokens.push(Token {
+                    text: line[start..i].into(),
+                    quoted: false,
+                });
+            }
+        }
+    }
+    tokens
+}
+
+fn is_tag(token: &Token) -> bool {
+    !token.quoted && token.text.starts_with('_')
+}
+
+fn is_keyword(token: &Token) -> bool {
+    !token.quoted
+        && (token.text.eq_ignore_ascii_case("loop_")
+            || token.text.starts_with("data_")
+            || token.text.starts_with("save_"))
+}
+
+/// Category of a tag, e.g. `_entity_poly` for `_entity_poly.type`.
+fn category_of(token: &Token) -> Option<String> {
+    let (category, _) = token.text.split_once('.')?;
+    Some(category.to_string())
+}
+
+/// Where the items of one category sit in the token stream.
+enum Block {
+    /// A `loop_`: its header tags and the values that follow them.
+    Loop {
+        header: Range<usize>,
+        values: Range<usize>,
+    },
+    /// A single `_tag value` pair.
+    Pair { tag: usize, value: usize },
+}
+
+/// mmCIF text tokenized once, with the blocks of every category indexed so several categories
+/// can be read without scanning the file again.
+pub struct CifIndex<'a> {
+    tokens: Vec<Token<'a>>,
+    categories: HashMap<String, Vec<Block>>,
+}
+
+impl<'a> CifIndex<'a> {
+    pub fn new(text: &'a str) -> Self {
+        let tokens = tokenize(text);
+        let mut categories: HashMap<String, Vec<Block>> = HashMap::new();
+        let mut i = 0;
+        while i < tokens.len() {
+            let token = &tokens[i];
+            if !token.quoted && token.text.eq_ignore_ascii_case("loop_") {
+                i += 1;
+                let header_start = i;
+                while i < tokens.len() && is_tag(&tokens[i]) {
+                    i += 1;
+                }
+                let header = header_start..i;
+                while i < tokens.len() && !is_tag(&tokens[i]) && !is_keyword(&tokens[i]) {
+                    i += 1;
+                }
+                if let Some(category) = tokens[header.clone()].first().and_then(category_of) {
+                    let values = header.end..i;
+                    categories
+                        .entry(category)
+                        .or_default()
+                        .push(Block::Loop { header, values });
+                }
+            } else if is_tag(token) {
+                let value = Some(i + 1).filter(|&v| {
+                    v < tokens.len() && !is_tag(&tokens[v]) && !is_keyword(&tokens[v])
+                });
+                if let (Some(category), Some(value)) = (category_of(token), value) {
+                    categories
+                        .entry(category)
+                        .or_default()
+                        .push(Block::Pair { tag: i, value });
+                }
+                i += if value.is_some() { 2 } else { 1 };
+            } else {
+                i += 1;
+            }
+        }
+        CifIndex { tokens, categories }
+    }
+
+    /// Rows of one category (e.g. `_entity_poly`), keyed by item name without the category
+    /// prefix.
+    ///
+    /// A category written as plain key-value pairs yields a single row.
+    pub fn category(&self, category: &str) -> Vec<HashMap<String, String>> {
+        let item = |tag: &Token| {
+            tag.text
+                .get(category.len() + 1..)
+                .unwrap_or_default()
+                .to_string()
+        };
+        let mut rows = Vec::new();
+        let mut single: HashMap<String, String> = HashMap::new();
+        for block in self.categories.get(category).into_iter().flatten() {
+            match block {
+                Block::Loop { header, values } => {
+                    let header = &self.tokens[header.clone()];
+                    for chunk in self.tokens[values.clone()].chunks(header.len()) {
+                        if chunk.len() != header.len() {
+                            break;
+                        }
+                        let row = header
+                            .iter()
+                            .zip(chunk)
+                            .map(|(tag, value)| (item(tag), value.text.to_string()))
+                            .collect();
+                        rows.push(row);
+                    }
+                }
+                Block::Pair { tag, value } => {
+                    single.insert(
+                        item(&self.tokens[*tag]),
+                        self.tokens[*value].text.to_string(),
+                    );
+                }
+            }
+        }
+
+        if !single.is_empty() {
+            rows.push(single);
+        }
+        rows
+    }
+}
+
+/// mmCIF uses `?` and `.` for unknown / not applicable values.
+pub fn is_null(value: &str) -> bool {
+    value == "?" || value == "."
+}
@@ -1,9 +1,17 @@
-use crate::utils::{read_raw, three_to_one};
-use pdbtbx::Residue;
+use crate::chem::ModifiedResidues;
+use crate::error::PskitError;
+use crate::residue::ResidueKey;
+use crate::selection::{read_selected, Selection};
+use crate::sequence::{map_deposited, ChainMapping, Deposited, SequenceSource};
+use crate::utils::{three_to_one, ReadOptions};
+use pdbtbx::{Atom, Model, Residue, PDB};
+use rstar::{primitives::GeomWithData, PointDistance, RTree};
+use std::collections::HashMap;
 use std::io::BufRead;
+use std::str::FromStr;
 use wide::f64x4;
 
-fn d2_batch_wide(x: &[f64], y: &[f64], z: &[f64], p: [f64; 3]) -> Vec<f64> {
+pub(crate) fn d2_batch_wide(x: &[f64], y: &[f64], z: &[f64], p: [f64; 3]) -> Vec<f64> {
     assert!(x.len() == y.len() && y.len() == z.len());
     let n = x.len();
     let mut out = vec![0.0; n];
@@ -35,6 +43,21 @@ fn d2_batch_wide(x: &[f64], y: &[f64], z: &[f64], p: [f64; 3]) -> Vec<f64> {
     out
 }
 
+/// Upper-triangle distance map of `points`; row `i` holds the distances to `i+1..n`.
+pub(crate) fn upper_distances(points: &[[f64; 3]]) -> Vec<Vec<f64>> {
+    let x: Vec<f64> = points.iter().map(|p| p[0]).collect();
+    let y: Vec<f64> = points.iter().map(|p| p[1]).collect();
+    let z: Vec<f64> = points.iter().map(|p| p[2]).collect();
+    let n = points.len();
+    (0..n)
+        .map(|i| {
+            let mut row = d2_batch_wide(&x[i + 1..], &y[i + 1..], &z[i + 1..], points[i]);
+            row.iter_mut().for_each(|d| *d = d.sqrt());
+            row
+        })
+        .collect()
+}
+
 fn get_residue_pos(residue: &Residue) -> [f64; 3] {
     for atom in residue.atoms() {
         if atom.name() == "CA" {
@@ -42,94 +65,409 @@ fn get_residue_pos(residue: &Residue) -> [f64; 3] {
         }
     }
 
-    let n = residue.atom_count();
+    centroid(residue.atoms())
+}
+
+fn centroid<'a>(atoms: impl Iterator<Item = &'a Atom>) -> [f64; 3] {
+    let mut n = 0;
+    let sum =
+        atoms
+            .map(|atom| atom.pos())
+            .fold([0.0, 0.0, 0.0], |[acc_x, acc_y, acc_z], (x, y, z)| {
+                n += 1;
+                [acc_x + x, acc_y + y, acc_z + z]
+            });
+    sum.map(|e| e / n as f64)
+}
+
+/// Which point(s) of a residue distances are measured from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Representative {
+    /// CA when present, otherwise the centroid of all atoms.
+    #[default]
+    Auto,
+    Ca,
+    /// CB, with a virtual CB built from N/CA/C for glycine.
+    Cb,
+    /// C1' of nucleotides.
+    C1,
+    /// Phosphorus of nucleotides.
+    P,
+    /// Centroid of the heavy side-chain (or nucleobase) atoms; CA for glycine.
+    SideChain,
+    /// Minimum distance over every heavy-atom pair of the two residues.
+    MinHeavy,
+    /// Centroid of all atoms.
+    Centroid,
+}
+
+impl FromStr for Representative {
+    type Err = PskitError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(Representative::Auto),
+            "ca" => Ok(Representative::Ca),
+            "cb" => Ok(Representative::Cb),
+            "c1'" | "c1" => Ok(Representative::C1),
+            "p" => Ok(Representative::P),
+            "sidechain" | "side_chain" => Ok(Representative::SideChain),
+            "min" | "min_heavy" => Ok(Representative::MinHeavy),
+            "centroid" => Ok(Representative::Centroid),
+            _ => Err(PskitError::InvalidArgument(format!(
+                "Unknown representative atom {s:?}, expected auto, ca, cb, c1', p, sidechain, min_heavy or centroid"
+            ))),
+        }
+    }
+}
+
+const BACKBONE_ATOMS: &[&str] = &["N", "CA", "C", "O", "OXT", "P", "OP1", "OP2", "OP3"];
+
+fn is_heavy(atom: &Atom) -> bool {
+    match atom.element() {
+        Some(e) => !matches!(e.symbol(), "H" | "D"),
+        None => !atom.name().starts_with('H'),
+    }
+}
+
+fn find_atom(residue: &Residue, name: &str) -> Option<[f64; 3]> {
     residue
         .atoms()
-        .map(|atom| atom.pos())
-        .fold([0.0, 0.0, 0.0], |[acc_x, acc_y, acc_z], (x, y, z)| {
-            [acc_x + x, acc_y + y, acc_z + z]
-        })
-        .map(|e| e / n as f64)
+        .find(|atom| atom.name() == name)
+        .map(|atom| atom.pos().into())
+}
+
+/// Ideal CB position from the backbone N, CA and C atoms.
+fn virtual_cb(residue: &Residue) -> Option<[f64; 3]> {
+    let n = find_atom(residue, "N")?;
+    let ca = find_atom(residue, "CA")?;
+    let c = find_atom(residue, "C")?;
+    let b: [f64; 3] = std::array::from_fn(|i| ca[i] - n[i]);
+    let c: [f64; 3] = std::array::from_fn(|i| c[i] - ca[i]);
+    let a = [
+        b[1] * c[2] - b[2] * c[1],
+        b[2] * c[0] - b[0] * c[2],
+        b[0] * c[1] - b[1] * c[0],
+    ];
+    Some(std::array::from_fn(|i| {
+        -0.58273431 * a[i] + 0.56802827 * b[i] - 0.54067466 * c[i] + ca[i]
+    }))
+}
+
+/// Point(s) representing `residue`; residues lacking the requested atom fall back to
+/// the centroid of all their atoms. Only `MinHeavy` yields more than one point.
+pub(crate) fn representative_points(residue: &Residue, repr: Representative) -> Vec<[f64; 3]> {
+    let point = match repr {
+        Representative::Auto => Some(get_residue_pos(residue)),
+        Representative::Ca => find_atom(residue, "CA"),
+        Representative::Cb => find_atom(residue, "CB").or_else(|| virtual_cb(residue)),
+        Representative::C1 => find_atom(residue, "C1'"),
+        Representative::P => find_atom(residue, "P"),
+        Representative::Centroid => None,
+        Representative::SideChain => {
+            let mut side = residue
+                .atoms()
+                .filter(|atom| {
+                    is_heavy(atom)
+                        && !BACKBONE_ATOMS.contains(&atom.name())
+                        && !atom.name().contains('\'')
+                })
+                .peekable();
+            if side.peek().is_some() {
+                Some(centroid(side))
+            } else {
+                find_atom(residue, "CA")
+            }
+        }
+        Representative::MinHeavy => {
+            let heavy: Vec<[f64; 3]> = residue
+                .atoms()
+                .filter(|atom| is_heavy(atom))
+                .map(|atom| atom.pos().into())
+                .collect();
+            if !heavy.is_empty() {
+                return heavy;
+            }
+            None
+        }
+    };
+    vec![point.unwrap_or_else(|| centroid(residue.atoms()))]
+}
+
+pub(crate) fn check_chain(pdb: &PDB, chain_id: Option<&str>) -> Result<(), PskitError> {
+    if let Some(cid) = chain_id {
+        let chain_ids: Vec<_> = pdb.chains().map(|chain| chain.id()).collect();
+        if !chain_ids.contains(&cid) {
+            return Err(PskitError::ChainNotFound {
+                chain_id: cid.to_string(),
+                valid: chain_ids.into_iter().map(String::from).collect(),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn residue_positions(model: &Model, chain_id: Option<&str>) -> Vec<(ResidueKey, [f64; 3])> {
+    let mut out = Vec::new();
+    for chain in model.chains() {
+        if let Some(cid) = chain_id {
+            if cid != chain.id() {
+                continue;
+            }
+        }
+        for residue in chain.residues() {
+            out.push((
+                ResidueKey::new(chain.id(), residue),
+                get_residue_pos(residue),
+            ));
+        }
+    }
+    out
+}
+
+pub(crate) fn axis_label(key: &ResidueKey) -> String {
+    format!(
+        "{}-{}-{}",
+        key.chain_id,
+        key.number,
+        three_to_one(&key.name)
+    )
 }
 
 pub fn d2_map<R: BufRead>(
     reader: R,
     chain_id: Option<String>,
+    repr: Representative,
     format: &str,
-) -> Result<(Vec<String>, Vec<Vec<f64>>), String> {
-    let (pdb, _errors) = read_raw(reader, format)?;
+    selection: &Selection,
+) -> Result<(Vec<String>, Vec<Vec<f64>>), PskitError> {
+    let (pdb, _classifier) = read_selected(
+        reader,
+        format,
+        ReadOptions::default(),
+        ModifiedResidues::default(),
+        selection,
+    )?;
+    pdb_d2_map(&pdb, chain_id.as_deref(), repr)
+}
+
+/// [`d2_map`] of an already read structure.
+fn pdb_d2_map(
+    pdb: &PDB,
+    chain_id: Option<&str>,
+    repr: Representative,
+) -> Result<(Vec<String>, Vec<Vec<f64>>), PskitError> {
+    check_chain(pdb, chain_id)?;
 
     let mut axis = Vec::new();
     let mut values = vec![];
 
+    // 每个残基的代表点连续存放，offsets[i]..offsets[i+1] 为第 i 个残基的点
+    let mut offsets = vec![0];
     let mut x = Vec::new();
     let mut y = Vec::new();
     let mut z = Vec::new();
 
-    if let Some(cid) = chain_id.as_deref() {
-        let chain_ids: Vec<_> = pdb.chains().map(|chain| chain.id()).collect();
-        if !chain_ids.contains(&cid) {
-            return Err(format!(
-                "Chain {cid} not exists. Valid chain IDs are: {:?}",
-                chain_ids
-            ));
+    for model in pdb.models() {
+        for chain in model.chains() {
+            if chain_id.is_some_and(|cid| cid != chain.id()) {
+                continue;
+            }
+            for residue in chain.residues() {
+                axis.push(axis_label(&ResidueKey::new(chain.id(), residue)));
+                for pos in representative_points(residue, repr) {
+                    x.push(pos[0]);
+                    y.push(pos[1]);
+                    z.push(pos[2]);
+                }
+                offsets.push(x.len());
+            }
         }
     }
 
-    for chain in pdb.chains() {
-        if let Some(cid) = &chain_id {
-            if cid != chain.id() {
-                continue;
+    let n = axis.len();
+
+    if repr != Representative::MinHeavy {
+        for i in 0..n {
+            let d2 = d2_batch_wide(&x[i + 1..n], &y[i + 1..n], &z[i + 1..n], [x[i], y[i], z[i]]);
+            values.push(d2);
+        }
+        return Ok((axis, values));
+    }
+
+    let m = x.len();
+    for i in 0..n {
+        let rest = offsets[i + 1];
+        let mut row = vec![f64::INFINITY; n - i - 1];
+        for a in offsets[i]..rest {
+            let d2 = d2_batch_wide(&x[rest..m], &y[rest..m], &z[rest..m], [x[a], y[a], z[a]]);
+            for (k, v) in row.iter_mut().enumerate() {
+                let (lo, hi) = (offsets[i + 1 + k] - rest, offsets[i + 2 + k] - rest);
+                *v = d2[lo..hi].iter().fold(*v, |acc, &d| acc.min(d));
             }
         }
-        for residue in chain.residues() {
-            axis.push(format!(
-                "{}-{}-{}",
-                chain.id(),
-                residue.id().0,
-                three_to_one(residue.name().unwrap_or("UNK"))
-            ));
-            let pos = get_residue_pos(residue);
-            x.push(pos[0]);
-            y.push(pos[1]);
-            z.push(pos[2]);
+        values.push(row);
+    }
+
+    Ok((axis, values))
+}
+
+/// How per-model distances are combined into one map for multi-model files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModelAggregate {
+    Mean,
+    Min,
+    Max,
+    Std,
+}
+
+impl FromStr for ModelAggregate {
+    type Err = PskitError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "mean" => Ok(ModelAggregate::Mean),
+            "min" => Ok(ModelAggregate::Min),
+            "max" => Ok(ModelAggregate::Max),
+            "std" => Ok(ModelAggregate::Std),
+            _ => Err(PskitError::InvalidArgument(format!(
+                "Unknown model aggregate {s:?}, expected mean, min, max or std"
+            ))),
         }
     }
+}
 
-    let n = axis.len();
+/// Distance map over every model of an ensemble, combined with `aggregate`.
+///
+/// The axis comes from the first model; residues of later models are matched by residue key,
+/// and a pair missing from a model is left out of that model's contribution.
+pub fn d_map_models<R: BufRead>(
+    reader: R,
+    chain_id: Option<String>,
+    aggregate: ModelAggregate,
+    format: &str,
+    selection: &Selection,
+) -> Result<(Vec<String>, Vec<Vec<f64>>), PskitError> {
+    let (pdb, _classifier) = read_selected(
+        reader,
+        format,
+        ReadOptions::all_models(),
+        ModifiedResidues::default(),
+        selection,
+    )?;
+    check_chain(&pdb, chain_id.as_deref())?;
+
+    let Some(first) = pdb.models().next() else {
+        return Ok((Vec::new(), Vec::new()));
+    };
+    let keys: Vec<ResidueKey> = residue_positions(first, chain_id.as_deref())
+        .into_iter()
+        .map(|(key, _)| key)
+        .collect();
+    let index: HashMap<&ResidueKey, usize> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+    let n = keys.len();
+
+    let init = match aggregate {
+        ModelAggregate::Min => f64::INFINITY,
+        ModelAggregate::Max => f64::NEG_INFINITY,
+        ModelAggregate::Mean | ModelAggregate::Std => 0.0,
+    };
+    let mut acc: Vec<Vec<f64>> = (0..n).map(|i| vec![init; n - i - 1]).collect();
+    let mut acc_sq: Vec<Vec<f64>> = (0..n).map(|i| vec![0.0; n - i - 1]).collect();
+    let mut counts: Vec<Vec<u32>> = (0..n).map(|i| vec![0; n - i - 1]).collect();
+
+    for model in pdb.models() {
+        let mut pos: Vec<Option<[f64; 3]>> = vec![None; n];
+        for (key, p) in residue_positions(model, chain_id.as_deref()) {
+            if let Some(&i) = index.get(&key) {
+                pos[i] = Some(p);
+            }
+        }
+        for i in 0..n {
+            let Some(a) = pos[i] else { continue };
+            for j in i + 1..n {
+                let Some(b) = pos[j] else { continue };
+                let d =
+                    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt();
+                let v = &mut acc[i][j - i - 1];
+                match aggregate {
+                    ModelAggregate::Min => *v = v.min(d),
+                    ModelAggregate::Max => *v = v.max(d),
+                    ModelAggregate::Mean | ModelAggregate::Std => {
+                        *v += d;
+                        acc_sq[i][j - i - 1] += d * d;
+                    }
+                }
+                counts[i][j - i - 1] += 1;
+            }
+        }
+    }
 
     for i in 0..n {
-        let d2 = d2_batch_wide(&x[i + 1..n], &y[i + 1..n], &z[i + 1..n], [x[i], y[i], z[i]]);
-        values.push(d2);
+        for (k, v) in acc[i].iter_mut().enumerate() {
+            let c = counts[i][k] as f64;
+            if c == 0.0 {
+                *v = f64::NAN;
+                continue;
+            }
+            match aggregate {
+                ModelAggregate::Mean => *v /= c,
+                ModelAggregate::Std => {
+                    let mean = *v / c;
+                    *v = (acc_sq[i][k] / c - mean * mean).max(0.0).sqrt();
+                }
+                ModelAggregate::Min | ModelAggregate::Max => {}
+            }
+        }
     }
 
-    Ok((axis, values))
+    Ok((keys.iter().map(axis_label).collect(), acc))
+}
+
+/// Indices of the `k` smallest values of `line`, in index order; ties are broken by index, so
+/// exactly `min(k, line.len())` indices are returned.
+pub(crate) fn nearest(line: &[f64], k: usize) -> Vec<usize> {
+    if k == 0 {
+        return Vec::new();
+    }
+    let mut order: Vec<usize> = (0..line.len()).collect();
+    if line.len() > k {
+        order.select_nth_unstable_by(k - 1, |&a, &b| line[a].total_cmp(&line[b]).then(a.cmp(&b)));
+        order.truncate(k);
+        order.sort_unstable();
+    }
+    order
 }
 
+/// Replace the `k` smallest squared distances of `line` (see [`nearest`]) by their square
+/// roots and every other entry by 0.
+pub(crate) fn keep_nearest(line: &mut [f64], k: usize) {
+    let kept = nearest(line, k);
+    let mut next = kept.iter().peekable();
+    for (j, e) in line.iter_mut().enumerate() {
+        *e = if next.next_if(|&&i| i == j).is_some() {
+            e.sqrt()
+        } else {
+            0.0
+        };
+    }
+}
+
+/// Upper-triangle map keeping, in each row, the distances to the `k` nearest of the
+/// following residues; every other entry is 0.
 pub fn knn_map<R: BufRead>(
     reader: R,
     chain_id: Option<String>,
     k: usize,
+    repr: Representative,
     format: &str,
-) -> Result<(Vec<String>, Vec<Vec<f64>>), String> {
-    let mut d2_map = d2_map(reader, chain_id, format)?;
+    selection: &Selection,
+) -> Result<(Vec<String>, Vec<Vec<f64>>), PskitError> {
+    let mut d2_map = d2_map(reader, chain_id, repr, format, selection)?;
     let (_, values) = &mut d2_map;
 
     for line in values {
-        let mut tmp = line.clone();
-        let (_, kth, _) = tmp.select_nth_unstable_by(k, |a, b| a.total_cmp(b));
-
-        let mut has_equal_v = false;
-        for e in line {
-            *e = if e < kth {
-                e.sqrt()
-            } else if e == kth && !has_equal_v {
-                has_equal_v = true;
-                e.sqrt()
-            } else {
-                0.0
-            }
-        }
+        keep_nearest(line, k);
     }
     let k_map = d2_map;
 
@@ -139,17 +477,245 @@ pub fn knn_map<R: BufRead>(
 pub fn d_map<R: BufRead>(
     reader: R,
     chain_id: Option<String>,
+    repr: Representative,
     format: &str,
-) -> Result<(Vec<String>, Vec<Vec<f64>>), String> {
-    let mut d2_map = d2_map(reader, chain_id, format)?;
-    let (_, values) = &mut d2_map;
+    selection: &Selection,
+) -> Result<(Vec<String>, Vec<Vec<f64>>), PskitError> {
+    let (axis, mut values) = d2_map(reader, chain_id, repr, format, selection)?;
+    take_sqrt(&mut values);
+    Ok((axis, values))
+}
 
+fn take_sqrt(values: &mut [Vec<f64>]) {
     for line in values {
         for e in line {
             *e = e.sqrt();
         }
     }
-    let d_map = d2_map;
+}
+
+/// Spread a single-chain [`d_map`] (upper-triangle rows) over the chain's full deposited
+/// sequence, so unmodeled residues show as NaN instead of being compressed away. Missing residues are
+/// labelled with their inferred author number; map residues outside the chain are dropped.
+pub fn insert_gaps(
+    axis: &[String],
+    values: &[Vec<f64>],
+    chain: &ChainMapping,
+) -> (Vec<String>, Vec<Vec<f64>>) {
+    let rows: HashMap<&str, usize> = axis
+        .iter()
+        .enumerate()
+        .map(|(i, label)| (label.as_str(), i))
+        .collect();
+    let numbering = chain.numbering();
+    let (labels, source): (Vec<String>, Vec<Option<usize>>) = chain
+        .sequence
+        .chars()
+        .zip(&chain.residues)
+        .zip(&numbering)
+        .map(|((code, residue), number)| match residue {
+            Some(key) => {
+                let label = axis_label(key);
+                let row = rows.get(label.as_str()).copied();
+                (label, row)
+            }
+            None => (format!("{}-{number}-{code}", chain.chain_id), None),
+        })
+        .unzip();
+    let gapped = source
+        .iter()
+        .enumerate()
+        .map(|(k, i)| {
+            source[k + 1..]
+                .iter()
+                .map(|j| match (*i, *j) {
+                    (Some(i), Some(j)) if i < j => values[i][j - i - 1],
+                    (Some(i), Some(j)) if j < i => values[j][i - j - 1],
+                    _ => f64::NAN,
+                })
+                .collect()
+        })
+        .collect();
+    (labels, gapped)
+}
+
+/// [`d_map`] of one chain spread over its deposited sequence with [`insert_gaps`], so the axis
+/// runs over every sequence position. Chains without a deposited sequence keep the plain map.
+pub fn d_map_gapped<R: BufRead>(
+    reader: R,
+    chain_id: String,
+    repr: Representative,
+    format: &str,
+    selection: &Selection,
+) -> Result<(Vec<String>, Vec<Vec<f64>>), PskitError> {
+    // 距离图与序列映射共用同一次读取
+    let deposited = Deposited::read(reader, format, ModifiedResidues::default(), selection)?;
+    let (axis, mut values) = pdb_d2_map(&deposited.pdb, Some(&chain_id), repr)?;
+    take_sqrt(&mut values);
+    match map_deposited(&deposited)
+        .iter()
+        .find(|m| m.chain_id == chain_id && m.source == SequenceSource::Seqres)
+    {
+        Some(mapping) => Ok(insert_gaps(&axis, &values, mapping)),
+        None => Ok((axis, values)),
+    }
+}
+
+/// Row axis, column axis and full row-major values of a map between two residue sets.
+pub type CrossMap = (Vec<String>, Vec<String>, Vec<Vec<f64>>);
+
+/// Squared distances between the residues of two selections, e.g. heavy chain vs antigen.
+///
+/// Returns the row axis, the column axis and the full `rows × cols` matrix. A residue's
+/// position is taken from its selected atoms only, so `chain A and name CB` maps CB atoms.
+pub fn d2_map_between<R: BufRead>(
+    reader: R,
+    rows: &Selection,
+    cols: &Selection,
+    format: &str,
+) -> Result<CrossMap, PskitError> {
+    let (pdb, classifier) = read_selected(
+        reader,
+        format,
+        ReadOptions::default(),
+        ModifiedResidues::default(),
+        &Selection::all(),
+    )?;
+
+    let positions = |selection: &Selection| -> Result<_, PskitError> {
+        let mut part = pdb.clone();
+        selection.apply(&mut part, &classifier)?;
+        let positions = part
+            .models()
+            .next()
+            .map(|model| residue_positions(model, None))
+            .unwrap_or_default();
+        Ok(positions)
+    };
+    let row_pos = positions(rows)?;
+    let col_pos = positions(cols)?;
+
+    let x: Vec<f64> = col_pos.iter().map(|(_, p)| p[0]).collect();
+    let y: Vec<f64> = col_pos.iter().map(|(_, p)| p[1]).collect();
+    let z: Vec<f64> = col_pos.iter().map(|(_, p)| p[2]).collect();
+    let values = row_pos
+        .iter()
+        .map(|(_, p)| d2_batch_wide(&x, &y, &z, *p))
+        .collect();
+
+    Ok((
+        row_pos.iter().map(|(key, _)| axis_label(key)).collect(),
+        col_pos.iter().map(|(key, _)| axis_label(key)).collect(),
+        values,
+    ))
+}
+
+pub fn d_map_between<R: BufRead>(
+    reader: R,
+    rows: &Selection,
+    cols: &Selection,
+    format: &str,
+) -> Result<CrossMap, PskitError> {
+    let (row_axis, col_axis, mut values) = d2_map_between(reader, rows, cols, format)?;
+
+    for line in &mut values {
+        for e in line {
+            *e = e.sqrt();
+        }
+    }
+
+    Ok((row_axis, col_axis, values))
+}
+
+/// Residue pairs within a cutoff, in coordinate (COO) form.
+///
+/// `rows[k] < cols[k]` index into `axis`, and pairs are sorted by row then column.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SparseContacts {
+    pub axis: Vec<String>,
+    pub rows: Vec<u32>,
+    pub cols: Vec<u32>,
+    pub distances: Vec<f64>,
+}
+
+impl SparseContacts {
+    /// Compressed sparse row form: `(indptr, indices, data)`, with `indptr.len() == axis.len() + 1`.
+    pub fn to_csr(&self) -> (Vec<u32>, Vec<u32>, Vec<f64>) {
+        let mut indptr = vec![0u32; self.axis.len() + 1];
+        for &r in &self.rows {
+            indptr[r as usize + 1] += 1;
+        }
+        for i in 1..indptr.len() {
+            indptr[i] += indptr[i - 1];
+        }
+        (indptr, self.cols.clone(), self.distances.clone())
+    }
+}
+
+/// Sparse contact list: only residue pairs closer than `cutoff` Å are kept.
+///
+/// Unlike [`d_map`] this never materialises the N×N matrix, so it scales to whole
+/// assemblies; neighbours are found through an R-tree over the representative points.
+pub fn contact_list<R: BufRead>(
+    reader: R,
+    chain_id: Option<String>,
+    cutoff: f64,
+    repr: Representative,
+    format: &str,
+    selection: &Selection,
+) -> Result<SparseContacts, PskitError> {
+    let (pdb, _classifier) = read_selected(
+        reader,
+        format,
+        ReadOptions::default(),
+        ModifiedResidues::default(),
+        selection,
+    )?;
+    check_chain(&pdb, chain_id.as_deref())?;
+
+    let mut axis = Vec::new();
+    let mut points: Vec<GeomWithData<[f64; 3], u32>> = Vec::new();
+    if let Some(model) = pdb.models().next() {
+        for chain in model.chains() {
+            if chain_id.as_deref().is_some_and(|cid| cid != chain.id()) {
+                continue;
+            }
+            for residue in chain.residues() {
+                let index = axis.len() as u32;
+                axis.push(axis_label(&ResidueKey::new(chain.id(), residue)));
+                for pos in representative_points(residue, repr) {
+                    points.push(GeomWithData::new(pos, index));
+                }
+            }
+        }
+    }
+    let tree = RTree::bulk_load(points.clone());
+
+    let mut pairs: HashMap<(u32, u32), f64> = HashMap::new();
+    for a in &points {
+        for b in tree.locate_within_distance(*a.geom(), cutoff * cutoff) {
+            if b.data <= a.data {
+                continue;
+            }
+            let d2 = b.distance_2(a.geom());
+            pairs
+                .entry((a.data, b.data))
+                .and_modify(|v| *v = v.min(d2))
+                .or_insert(d2);
+        }
+    }
 
-    Ok(d_map)
+    let mut pairs: Vec<_> = pairs.into_iter().collect();
+    pairs.sort_by_key(|&(key, _)| key);
+
+    let mut contacts = SparseContacts {
+        axis,
+        ..Default::default()
+    };
+    for ((i, j), d2) in pairs {
+        contacts.rows.push(i);
+        contacts.cols.push(j);
+        contacts.distances.push(d2.sqrt());
+    }
+    Ok(contacts)
 }
@@ -0,0 +1,812 @@
+use pdbtbx::{Residue, PDB};
+use rstar::{primitives::GeomWithData, RTree};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::io::BufRead;
+
+use crate::chem::{ModifiedResidues, ResidueClassifier};
+use crate::error::PskitError;
+use crate::interaction::{angle, cross, dist, dot, sub, Vec3};
+use crate::residue::ResidueKey;
+use crate::sasa::{model_sasa, SasaOptions};
+use crate::selection::{read_selected, Selection};
+use crate::split::complex_parts;
+use crate::utils::{three_to_one, write_raw, ReadOptions};
+
+/// DSSP secondary-structure class; [`code`](Self::code) gives the one-letter DSSP symbol.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SecondaryStructure {
+    /// α-helix (H).
+    AlphaHelix,
+    /// Isolated β-bridge (B).
+    Bridge,
+    /// Extended strand in a ladder (E).
+    Strand,
+    /// 3₁₀-helix (G).
+    Helix310,
+    /// π-helix (I).
+    PiHelix,
+    /// H-bonded turn (T).
+    Turn,
+    /// Bend (S).
+    Bend,
+    #[default]
+    Loop,
+}
+
+impl SecondaryStructure {
+    pub fn code(&self) -> char {
+        match self {
+            SecondaryStructure::AlphaHelix => 'H',
+            SecondaryStructure::Bridge => 'B',
+            SecondaryStructure::Strand => 'E',
+            SecondaryStructure::Helix310 => 'G',
+            SecondaryStructure::PiHelix => 'I',
+            SecondaryStructure::Turn => 'T',
+            SecondaryStructure::Bend => 'S',
+            SecondaryStructure::Loop => ' ',
+        }
+    }
+}
+
+/// One backbone H-bond partner; `energy` is in kcal/mol.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct HBond {
+    /// Index of the partner in [`Dssp::residues`].
+    pub partner: Option<usize>,
+    pub energy: f64,
+}
+
+/// β-bridge partner with the label of its ladder (lowercase parallel, uppercase antiparallel).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BridgePartner {
+    pub partner: usize,
+    pub ladder: char,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DsspResidue {
+    pub key: ResidueKey,
+    pub amino_acid: char,
+    pub structure: SecondaryStructure,
+    /// Flags of 3-, 4- and 5-turns: `>` start, `<` end, `X` both, the stride digit inside.
+    pub helix_flags: [char; 3],
+    pub bend: bool,
+    pub bridge_partners: [Option<BridgePartner>; 2],
+    pub sheet: Option<char>,
+    /// Accessible surface area in Å² of the residue within the protein.
+    pub accessibility: f64,
+    /// Best two H-bonds with this residue's N-H as donor.
+    pub nh_o: [HBond; 2],
+    /// Best two H-bonds with this residue's C=O as acceptor.
+    pub o_hn: [HBond; 2],
+    pub tco: Option<f64>,
+    pub kappa: Option<f64>,
+    pub alpha: Option<f64>,
+    pub phi: Option<f64>,
+    pub psi: Option<f64>,
+    pub ca: [f64; 3],
+    /// True when the backbone is interrupted before this residue (gap or new chain).
+    pub break_before: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Dssp {
+    pub residues: Vec<DsspResidue>,
+}
+
+impl Dssp {
+    /// One DSSP symbol per residue, loops as `-`.
+    pub fn summary(&self) -> String {
+        self.residues
+            .iter()
+            .map(|r| match r.structure.code() {
+                ' ' => '-',
+                c => c,
+            })
+            .collect()
+    }
+}
+
+/// Dihedral angle p1-p2-p3-p4 in degrees.
+fn dihedral(p1: Vec3, p2: Vec3, p3: Vec3, p4: Vec3) -> f64 {
+    let v12 = sub(p1, p2);
+    let v43 = sub(p4, p3);
+    let z = sub(p2, p3);
+    let p = cross(z, v12);
+    let x = cross(z, v43);
+    let y = cross(z, x);
+    let u = dot(x, x);
+    let v = dot(y, y);
+    if u <= 0.0 || v <= 0.0 {
+        return 360.0;
+    }
+    let u = dot(p, x) / u.sqrt();
+    let v = dot(p, y) / v.sqrt();
+    if u == 0.0 && v == 0.0 {
+        360.0
+    } else {
+        v.atan2(u).to_degrees()
+    }
+}
+
+fn find_atom(residue: &Residue, name: &str) -> Option<Vec3> {
+    residue
+        .atoms()
+        .find(|atom| atom.name() == name)
+        .map(|atom| atom.pos().into())
+}
+
+struct Backbone {
+    key: ResidueKey,
+    letter: char,
+    is_proline: bool,
+    n: Vec3,
+    ca: Vec3,
+    c: Vec3,
+    o: Vec3,
+    h: Vec3,
+    break_before: bool,
+}
+
+/// Protein residues of the first model with a complete N, CA, C, O backbone.
+fn backbone(pdb: &PDB, classifier: &ResidueClassifier) -> Vec<Backbone> {
+    let mut out: Vec<Backbone> = Vec::new();
+    let Some(model) = pdb.models().next() else {
+        return out;
+    };
+    for chain in model.chains() {
+        let mut new_chain = true;
+        for residue in chain.residues() {
+            let Some(name) = residue.name() else {
+                continue;
+            };
+            if !classifier.is_protein(chain.id(), name) {
+                continue;
+            }
+            let atoms = (
+                find_atom(residue, "N"),
+                find_atom(residue, "CA"),
+                find_atom(residue, "C"),
+                find_atom(residue, "O"),
+            );
+            let (Some(n), Some(ca), Some(c), Some(o)) = atoms else {
+                // 主链不完整的残基视为断点
+                new_chain = true;
+                continue;
+            };
+            let previous = out.last().filter(|_| !new_chain);
+            // 肽键 C–N 超过 2.5 Å 视为链断裂
+            let break_before = previous.is_none_or(|p| dist(p.c, n) > 2.5);
+            // 酰胺氢沿上一残基 O→C 方向、距 N 1 Å 放置（DSSP 约定）
+            let h = match previous {
+                Some(p) if !break_before => {
+                    let co = sub(p.c, p.o);
+                    let len = dot(co, co).sqrt();
+                    std::array::from_fn(|i| n[i] + co[i] / len)
+                }
+                _ => n,
+            };
+            out.push(Backbone {
+                key: ResidueKey::new(chain.id(), residue),
+                letter: three_to_one(name),
+                is_proline: name == "PRO",
+                n,
+                ca,
+                c,
+                o,
+                h,
+                break_before,
+            });
+            new_chain = false;
+        }
+    }
+    out
+}
+
+const COUPLING: f64 = -27.888;
+const MIN_HBOND_ENERGY: f64 = -9.9;
+const MAX_HBOND_ENERGY: f64 = -0.5;
+const MIN_CA_DISTANCE: f64 = 9.0;
+
+/// Kabsch–Sander electrostatic energy of the N-H(donor)···O=C(acceptor) bond.
+fn hbond_energy(donor: &Backbone, acceptor: &Backbone) -> f64 {
+    if donor.is_proline {
+        return 0.0;
+    }
+    let ho = dist(donor.h, acceptor.o);
+    let hc = dist(donor.h, acceptor.c);
+    let nc = dist(donor.n, acceptor.c);
+    let no = dist(donor.n, acceptor.o);
+    let energy = if ho < 0.5 || hc < 0.5 || nc < 0.5 || no < 0.5 {
+        MIN_HBOND_ENERGY
+    } else {
+        COUPLING / ho - COUPLING / hc + COUPLING / nc - COUPLING / no
+    };
+    ((energy * 1000.0).round() / 1000.0).max(MIN_HBOND_ENERGY)
+}
+
+fn keep_best(slots: &mut [HBond; 2], partner: usize, energy: f64) {
+    if energy < slots[0].energy {
+        slots[1] = slots[0];
+        slots[0] = HBond {
+            partner: Some(partner),
+            energy,
+        };
+    } else if energy < slots[1].energy {
+        slots[1] = HBond {
+            partner: Some(partner),
+            energy,
+        };
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BridgeType {
+    Parallel,
+    Antiparallel,
+}
+
+struct Ladder {
+    kind: BridgeType,
+    i: Vec<usize>,
+    j: Vec<usize>,
+}
+
+struct Assigner<'a> {
+    bb: &'a [Backbone],
+    nh_o: Vec<[HBond; 2]>,
+    o_hn: Vec<[HBond; 2]>,
+}
+
+impl Assigner<'_> {
+    /// N-H of `donor` bonds to C=O of `acceptor`.
+    fn test_bond(&self, donor: usize, acceptor: usize) -> bool {
+        self.nh_o[donor]
+            .iter()
+            .any(|b| b.partner == Some(acceptor) && b.energy < MAX_HBOND_ENERGY)
+    }
+
+    /// No backbone break between residues `from` and `to` (inclusive).
+    fn no_break(&self, from: usize, to: usize) -> bool {
+        to < self.bb.len() && (from + 1..=to).all(|k| !self.bb[k].break_before)
+    }
+
+    fn bridge_type(&self, i: usize, j: usize) -> Option<BridgeType> {
+        let (a, b, c) = (i - 1, i, i + 1);
+        let (d, e, f) = (j - 1, j, j + 1);
+        if !self.no_break(a, c) || !self.no_break(d, f) {
+            return None;
+        }
+        if (self.test_bond(e, a) && self.test_bond(c, e))
+            || (self.test_bond(b, d) && self.test_bond(f, b))
+        {
+            Some(BridgeType::Parallel)
+        } else if (self.test_bond(e, b) && self.test_bond(b, e))
+            || (self.test_bond(f, a) && self.test_bond(c, d))
+        {
+            Some(BridgeType::Antiparallel)
+        } else {
+            None
+        }
+    }
+
+    fn ladders(&self) -> Vec<Ladder> {
+        let n = self.bb.len();
+        let mut ladders: Vec<Ladder> = Vec::new();
+        for i in 1..n.saturating_sub(4) {
+            for j in i + 3..n - 1 {
+                let Some(kind) = self.bridge_type(i, j) else {
+                    continue;
+                };
+                let extends = ladders.iter_mut().find(|l| {
+                    l.kind == kind
+                        && l.i.last() == Some(&(i - 1))
+                        && match kind {
+                            BridgeType::Parallel => l.j.last() == Some(&(j - 1)),
+                            BridgeType::Antiparallel => l.j.first() == Some(&(j + 1)),
+                        }
+                });
+                match extends {
+                    Some(ladder) => {
+                        ladder.i.push(i);
+                        match kind {
+                            BridgeType::Parallel => ladder.j.push(j),
+                            BridgeType::Antiparallel => ladder.j.insert(0, j),
+                        }
+                    }
+                    None => ladders.push(Ladder {
+                        kind,
+                        i: vec![i],
+                        j: vec![j],
+                    }),
+                }
+            }
+        }
+
+        // 允许 β-bulge：相邻同型梯子间隔足够小时合并
+        ladders.sort_by_key(|l| (l.i[0], l.j[0]));
+        let mut a = 0;
+        while a < ladders.len() {
+            let mut b = a + 1;
+            while b < ladders.len() {
+                let (x, y) = (&ladders[a], &ladders[b]);
+                let (ibi, iei) = (x.i[0], *x.i.last().unwrap());
+                let (jbi, jei) = (x.j[0], *x.j.last().unwrap());
+                let (ibj, iej) = (y.i[0], *y.i.last().unwrap());
+                let (jbj, jej) = (y.j[0], *y.j.last().unwrap());
+                let candidate = x.kind == y.kind
+                    && self.no_break(ibi.min(ibj), iei.max(iej))
+                    && self.no_break(jbi.min(jbj), jei.max(jej))
+                    && ibj < iei + 6
+                    && !(iei >= ibj && ibi <= iej);
+                let gap = |from: usize, to: usize| to.checked_sub(from);
+                let bulge = candidate
+                    && match x.kind {
+                        BridgeType::Parallel => {
+                            gap(jei, jbj).is_some_and(|g| (g < 6 && ibj - iei < 3) || g < 3)
+                        }
+                        BridgeType::Antiparallel => {
+                            gap(jej, jbi).is_some_and(|g| (g < 6 && ibj - iei < 3) || g < 3)
+                        }
+                    };
+                if bulge {
+                    let y = ladders.remove(b);
+                    let x = &mut ladders[a];
+                    x.i.extend(y.i);
+                    match x.kind {
+                        BridgeType::Parallel => x.j.extend(y.j),
+                        BridgeType::Antiparallel => {
+                            let mut j = y.j;
+                            j.append(&mut x.j);
+                            x.j = j;
+                        }
+                    }
+                } else {
+                    b += 1;
+                }
+            }
+            a += 1;
+        }
+        ladders
+    }
+}
+
+/// Sheets as groups of ladders that share residues, in ladder order.
+fn sheets(ladders: &[Ladder]) -> Vec<usize> {
+    let sets: Vec<BTreeSet<usize>> = ladders
+        .iter()
+        .map(|l| {
+            (l.i[0]..=*l.i.last().unwrap())
+                .chain(l.j[0]..=*l.j.last().unwrap())
+                .collect()
+        })
+        .collect();
+    let mut sheet = vec![usize::MAX; ladders.len()];
+    let mut next = 0;
+    for start in 0..ladders.len() {
+        if sheet[start] != usize::MAX {
+            continue;
+        }
+        sheet[start] = next;
+        let mut stack = vec![start];
+        while let Some(a) = stack.pop() {
+            for b in 0..ladders.len() {
+                if sheet[b] == usize::MAX && !sets[a].is_disjoint(&sets[b]) {
+                    sheet[b] = next;
+                    stack.push(b);
+                }
+            }
+        }
+        next += 1;
+    }
+    sheet
+}
+
+fn assign(pdb: &PDB, classifier: &ResidueClassifier) -> Dssp {
+    let bb = backbone(pdb, classifier);
+    let n = bb.len();
+
+    let mut assigner = Assigner {
+        bb: &bb,
+        nh_o: vec![[HBond::default(); 2]; n],
+        o_hn: vec![[HBond::default(); 2]; n],
+    };
+    let tree = RTree::bulk_load(
+        bb.iter()
+            .enumerate()
+            .map(|(i, r)| GeomWithData::new(r.ca, i))
+            .collect(),
+    );
+    for (i, donor) in bb.iter().enumerate() {
+        for hit in tree.locate_within_distance(donor.ca, MIN_CA_DISTANCE * MIN_CA_DISTANCE) {
+            let j = hit.data;
+            // 相邻残基 i-1 的羰基不与 i 的 N-H 配对
+            if j == i || j + 1 == i {
+                continue;
+            }
+            let energy = hbond_energy(donor, &bb[j]);
+            keep_best(&mut assigner.nh_o[i], j, energy);
+            keep_best(&mut assigner.o_hn[j], i, energy);
+        }
+    }
+
+    let mut structure = vec![SecondaryStructure::Loop; n];
+    let mut partners: Vec<[Option<BridgePartner>; 2]> = vec![[None; 2]; n];
+    let mut sheet_of: Vec<Option<char>> = vec![None; n];
+
+    let ladders = assigner.ladders();
+    let sheet_ids = sheets(&ladders);
+    for (index, ladder) in ladders.iter().enumerate() {
+        let letter = (b'A' + (index % 26) as u8) as char;
+        let label = match ladder.kind {
+            BridgeType::Parallel => letter.to_ascii_lowercase(),
+            BridgeType::Antiparallel => letter,
+        };
+        let sheet = (b'A' + (sheet_ids[index] % 26) as u8) as char;
+        let ss = if ladder.i.len() > 1 {
+            SecondaryStructure::Strand
+        } else {
+            SecondaryStructure::Bridge
+        };
+        let pairs: Vec<(usize, usize)> = match ladder.kind {
+            BridgeType::Parallel => ladder
+                .i
+                .iter()
+                .copied()
+                .zip(ladder.j.iter().copied())
+                .collect(),
+            BridgeType::Antiparallel => ladder
+                .i
+                .iter()
+                .copied()
+                .zip(ladder.j.iter().rev().copied())
+                .collect(),
+        };
+        for (i, j) in pairs {
+            for (a, b) in [(i, j), (j, i)] {
+                let slot = if partners[a][0].is_none() { 0 } else { 1 };
+                partners[a][slot] = Some(BridgePartner {
+                    partner: b,
+                    ladder: label,
+                });
+            }
+        }
+        let ranges = [
+            ladder.i[0]..=*ladder.i.last().unwrap(),
+            ladder.j[0]..=*ladder.j.last().unwrap(),
+        ];
+        for k in ranges.into_iter().flatten() {
+            sheet_of[k] = Some(sheet);
+            if structure[k] != SecondaryStructure::Strand {
+                structure[k] = ss;
+            }
+        }
+    }
+
+    // n-turn 标记：3/4/5 转角
+    let mut flags = vec![[' '; 3]; n];
+    for (slot, stride) in (3..=5).enumerate() {
+        for i in 0..n.saturating_sub(stride) {
+            if assigner.no_break(i, i + stride) && assigner.test_bond(i + stride, i) {
+                flags[i + stride][slot] = '<';
+                for flag in &mut flags[i + 1..i + stride] {
+                    if flag[slot] == ' ' {
+                        flag[slot] = char::from_digit(stride as u32, 10).unwrap();
+                    }
+                }
+                flags[i][slot] = if flags[i][slot] == '<' { 'X' } else { '>' };
+            }
+        }
+    }
+    let is_start = |i: usize, slot: usize| matches!(flags[i][slot], '>' | 'X');
+
+    for i in 1..n.saturating_sub(4) {
+        if is_start(i, 1) && is_start(i - 1, 1) {
+            structure[i..=i + 3].fill(SecondaryStructure::AlphaHelix);
+        }
+    }
+    for (slot, stride, ss) in [
+        (0, 3, SecondaryStructure::Helix310),
+        (2, 5, SecondaryStructure::PiHelix),
+    ] {
+        for i in 1..n.saturating_sub(stride) {
+            if is_start(i, slot)
+                && is_start(i - 1, slot)
+                && structure[i..i + stride]
+                    .iter()
+                    .all(|s| matches!(s, SecondaryStructure::Loop) || *s == ss)
+            {
+                structure[i..i + stride].fill(ss);
+            }
+        }
+    }
+
+    let kappa: Vec<Option<f64>> = (0..n)
+        .map(|i| {
+            (i >= 2 && assigner.no_break(i - 2, i + 2))
+                .then(|| angle(sub(bb[i].ca, bb[i - 2].ca), sub(bb[i + 2].ca, bb[i].ca)))
+        })
+        .collect();
+    for i in 1..n.saturating_sub(1) {
+        if structure[i] != SecondaryStructure::Loop {
+            continue;
+        }
+        let turn = (3..=5)
+            .enumerate()
+            .any(|(slot, stride)| (1..stride).any(|k| i >= k && is_start(i - k, slot)));
+        if turn {
+            structure[i] = SecondaryStructure::Turn;
+        } else if kappa[i].is_some_and(|k| k > 70.0) {
+            structure[i] = SecondaryStructure::Bend;
+        }
+    }
+
+    // 溶剂可及面积按仅含蛋白的结构计算
+    let (protein, _) = complex_parts(pdb, classifier);
+    let areas: HashMap<ResidueKey, f64> = model_sasa(&protein, &SasaOptions::default())
+        .residues
+        .into_iter()
+        .map(|r| (r.key, r.area))
+        .collect();
+
+    let residues = bb
+        .iter()
+        .enumerate()
+        .map(|(i, r)| {
+            let previous = (i > 0 && !r.break_before).then(|| &bb[i - 1]);
+            let next = bb.get(i + 1).filter(|next| !next.break_before);
+            DsspResidue {
+                key: r.key.clone(),
+                amino_acid: r.letter,
+                structure: structure[i],
+                helix_flags: flags[i],
+                bend: kappa[i].is_some_and(|k| k > 70.0),
+                bridge_partners: partners[i],
+                sheet: sheet_of[i],
+                accessibility: areas.get(&r.key).copied().unwrap_or_default(),
+                nh_o: assigner.nh_o[i],
+                o_hn: assigner.o_hn[i],
+                tco: previous.map(|p| {
+                    let a = sub(r.c, r.o);
+                    let b = sub(p.c, p.o);
+                    dot(a, b) / (dot(a, a) * dot(b, b)).sqrt()
+                }),
+                kappa: kappa[i],
+                alpha: (i >= 1 && assigner.no_break(i - 1, i + 2))
+                    .then(|| dihedral(bb[i - 1].ca, r.ca, bb[i + 1].ca, bb[i + 2].ca)),
+                phi: previous.map(|p| dihedral(p.c, r.n, r.ca, r.c)),
+                psi: next.map(|next| dihedral(r.n, r.ca, r.c, next.n)),
+                ca: r.ca,
+                break_before: r.break_before && i > 0,
+            }
+        })
+        .collect();
+
+    Dssp { residues }
+}
+
+/// DSSP secondary structure of the protein residues of the first model.
+///
+/// Follows Kabsch & Sander: backbone H-bonds from the electrostatic energy model
+/// (bond below -0.5 kcal/mol), n-turns, β-bridges and ladders with bulges, then helices,
+/// with priority H > B/E > G > I > T > S. Amide hydrogens are always placed from the
+/// backbone, so input hydrogens are not required.
+pub fn compute_dssp<R: BufRead>(
+    reader: R,
+    format: &str,
+    modified: ModifiedResidues,
+    selection: &Selection,
+) -> Result<Dssp, PskitError> {
+    let (pdb, classifier) =
+        read_selected(reader, format, ReadOptions::default(), modified, selection)?;
+    Ok(assign(&pdb, &classifier))
+}
+
+/// Serialize an assignment in the classic fixed-column `.dssp` format.
+pub fn write_dssp(dssp: &Dssp) -> Vec<u8> {
+    let residues = &dssp.residues;
+    // DSSP 序号在断点处额外占一行 '!'
+    let mut numbers = Vec::with_capacity(residues.len());
+    let mut number = 0;
+    for r in residues {
+        number += if r.break_before { 2 } else { 1 };
+        numbers.push(number);
+    }
+
+    let mut out = String::from(
+        "==== Secondary Structure Definition by the program DSSP, pskit implementation ==== .\n\
+         REFERENCE W. KABSCH AND C.SANDER, BIOPOLYMERS 22 (1983) 2577-2637                  .\n",
+    );
+    let chains: BTreeSet<&str> = residues.iter().map(|r| r.key.chain_id.as_str()).collect();
+    let _ = writeln!(
+        out,
+        "{:5}{:3}{:3}{:3}{:3} TOTAL NUMBER OF RESIDUES, NUMBER OF CHAINS, NUMBER OF SS-BRIDGES(TOTAL,INTRACHAIN,INTERCHAIN)                .",
+        residues.len(),
+        chains.len(),
+        0,
+        0,
+        0
+    );
+    let total: f64 = residues.iter().map(|r| r.accessibility).sum();
+    let _ = writeln!(
+        out,
+        "{total:8.1}   ACCESSIBLE SURFACE OF PROTEIN (ANGSTROM**2)                                                         ."
+    );
+    out.push_str(
+        "  #  RESIDUE AA STRUCTURE BP1 BP2  ACC     N-H-->O    O-->H-N    N-H-->O    O-->H-N    TCO  KAPPA ALPHA  PHI   PSI    X-CA   Y-CA   Z-CA\n",
+    );
+
+    let hbond = |i: usize, b: &HBond| {
+        let (offset, energy) = match b.partner {
+            Some(p) => (numbers[p] as isize - numbers[i] as isize, b.energy),
+            None => (0, 0.0),
+        };
+        format!("{:>11}", format!("{offset},{energy:4.1}"))
+    };
+    for (i, r) in residues.iter().enumerate() {
+        if r.break_before {
+            let chain_change = i > 0 && residues[i - 1].key.chain_id != r.key.chain_id;
+            let _ = writeln!(
+                out,
+                "{:5}        !{}             0   0    0      0, 0.0     0, 0.0     0, 0.0     0, 0.0   0.000 360.0 360.0 360.0 360.0    0.0    0.0    0.0",
+                numbers[i] - 1,
+                if chain_change { '*' } else { ' ' }
+            );
+        }
+        let chain_id = r.key.chain_id.chars().next().unwrap_or(' ');
+        let icode = r
+            .key
+            .number
+            .insertion_code
+            .as_deref()
+            .and_then(|c| c.chars().next())
+            .unwrap_or(' ');
+        let chirality = match r.alpha {
+            Some(a) if a < 0.0 => '-',
+            Some(_) => '+',
+            None => ' ',
+        };
+        let bridge = |k: usize| match r.bridge_partners[k] {
+            Some(p) => (p.ladder, numbers[p.partner]),
+            None => (' ', 0),
+        };
+        let (label1, bp1) = bridge(0);
+        let (label2, bp2) = bridge(1);
+        let _ = writeln!(
+            out,
+            "{:5}{:5}{}{} {}  {} {}{}{}{}{}{}{}{:4}{:4}{}{:4} {}{}{}{}  {:6.3}{:6.1}{:6.1}{:6.1}{:6.1} {:6.1} {:6.1} {:6.1}",
+            numbers[i],
+            r.key.number.serial,
+            icode,
+            chain_id,
+            r.amino_acid,
+            r.structure.code(),
+            r.helix_flags[0],
+            r.helix_flags[1],
+            r.helix_flags[2],
+            if r.bend { 'S' } else { ' ' },
+            chirality,
+            label1,
+            label2,
+            bp1,
+            bp2,
+            r.sheet.unwrap_or(' '),
+            r.accessibility.round() as i64,
+            hbond(i, &r.nh_o[0]),
+            hbond(i, &r.o_hn[0]),
+            hbond(i, &r.nh_o[1]),
+            hbond(i, &r.o_hn[1]),
+            r.tco.unwrap_or(0.0),
+            r.kappa.unwrap_or(360.0),
+            r.alpha.unwrap_or(360.0),
+            r.phi.unwrap_or(360.0),
+            r.psi.unwrap_or(360.0),
+            r.ca[0],
+            r.ca[1],
+            r.ca[2],
+        );
+    }
+    out.into_bytes()
+}
+
+const CONF_TYPES: &[(SecondaryStructure, &str, &str)] = &[
+    (
+        SecondaryStructure::AlphaHelix,
+        "HELX_RH_AL_P",
+        "right-handed alpha helix",
+    ),
+    (
+        SecondaryStructure::Helix310,
+        "HELX_RH_3T_P",
+        "right-handed 3-10 helix",
+    ),
+    (
+        SecondaryStructure::PiHelix,
+        "HELX_RH_PI_P",
+        "right-handed pi helix",
+    ),
+    (SecondaryStructure::Strand, "STRN", "beta strand"),
+    (SecondaryStructure::Bridge, "STRN", "beta bridge"),
+    (
+        SecondaryStructure::Turn,
+        "TURN_TY1_P",
+        "hydrogen-bonded turn",
+    ),
+    (SecondaryStructure::Bend, "BEND", "bend"),
+];
+
+/// The structure as mmCIF with its DSSP assignment appended as `_struct_conf` ranges.
+pub fn write_dssp_mmcif<R: BufRead>(
+    reader: R,
+    format: &str,
+    modified: ModifiedResidues,
+    selection: &Selection,
+) -> Result<Vec<u8>, PskitError> {
+    let (pdb, classifier) =
+        read_selected(reader, format, ReadOptions::default(), modified, selection)?;
+    let dssp = assign(&pdb, &classifier);
+
+    let mut out = String::from_utf8_lossy(&write_raw(pdb, "cif")).into_owned();
+    if !out.ends_with('\n') {
+        out.push('\n');
+    }
+
+    // 连续且同类型的残基合并为一个区段
+    let residues = &dssp.residues;
+    let mut segments: Vec<(SecondaryStructure, usize, usize)> = Vec::new();
+    for (i, r) in residues.iter().enumerate() {
+        if r.structure == SecondaryStructure::Loop {
+            continue;
+        }
+        match segments.last_mut() {
+            Some((ss, _, end)) if *ss == r.structure && *end + 1 == i && !r.break_before => {
+                *end = i
+            }
+            _ => segments.push((r.structure, i, i)),
+        }
+    }
+
+    if segments.is_empty() {
+        return Ok(out.into_bytes());
+    }
+
+    out.push_str("#\nloop_\n_struct_conf_type.id\n_struct_conf_type.criteria\n");
+    let mut used: Vec<&str> = Vec::new();
+    for (ss, id, _) in CONF_TYPES {
+        if !used.contains(id) && segments.iter().any(|s| s.0 == *ss) {
+            used.push(id);
+            let _ = writeln!(out, "{id} DSSP");
+        }
+    }
+    out.push_str(
+        "#\nloop_\n_struct_conf.conf_type_id\n_struct_conf.id\n_struct_conf.beg_auth_asym_id\n\
+         _struct_conf.beg_auth_seq_id\n_struct_conf.pdbx_beg_PDB_ins_code\n_struct_conf.beg_auth_comp_id\n\
+         _struct_conf.end_auth_asym_id\n_struct_conf.end_auth_seq_id\n_struct_conf.pdbx_end_PDB_ins_code\n\
+         _struct_conf.end_auth_comp_id\n_struct_conf.details\n",
+    );
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for (ss, start, end) in &segments {
+        let Some((_, id, details)) = CONF_TYPES.iter().find(|(s, _, _)| s == ss) else {
+            continue;
+        };
+        let count = counts.entry(id).or_default();
+        *count += 1;
+        let (a, b) = (&residues[*start].key, &residues[*end].key);
+        let icode = |key: &ResidueKey| key.number.insertion_code.clone().unwrap_or("?".into());
+        let _ = writeln!(
+            out,
+            "{id} {id}{count} {} {} {} {} {} {} {} {} '{details}'",
+            a.chain_id,
+            a.number.serial,
+            icode(a),
+            a.name,
+            b.chain_id,
+            b.number.serial,
+            icode(b),
+            b.name,
+        );
+    }
+    out.push_str("#\n");
+    Ok(out.into_bytes())
+}
@@ -0,0 +1,114 @@
+use crate::residue::ResidueNumber;
+use pdbtbx::{Context, PDBError};
+use std::fmt;
+
+/// One parser diagnostic, flattened so callers don't need pdbtbx to inspect it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseIssue {
+    pub level: String,
+    pub message: String,
+    pub detail: String,
+    /// First line of the input the diagnostic points at, if known.
+    pub line: Option<usize>,
+    /// Rendered source excerpt, empty when pdbtbx gave no context.
+    pub context: String,
+}
+
+impl From<&PDBError> for ParseIssue {
+    fn from(err: &PDBError) -> Self {
+        let line = match err.context() {
+            Context::FullLine { linenumber, .. } | Context::Line { linenumber, .. } => {
+                Some(*linenumber)
+            }
+            Context::Range {
+                start_linenumber, ..
+            }
+            | Context::RangeHighlights {
+                start_linenumber, ..
+            } => Some(*start_linenumber),
+            _ => None,
+        };
+        ParseIssue {
+            level: err.level().descriptor().to_string(),
+            message: err.short_description().to_string(),
+            detail: err.long_description().to_string(),
+            line,
+            context: err.context().to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PskitError {
+    /// The input could not be read as PDB/mmCIF; holds every breaking diagnostic.
+    Parse(Vec<ParseIssue>),
+    ChainNotFound {
+        chain_id: String,
+        valid: Vec<String>,
+    },
+    InvalidRange {
+        chain_id: String,
+        min: ResidueNumber,
+        max: ResidueNumber,
+    },
+    /// A user-supplied parameter could not be interpreted.
+    InvalidArgument(String),
+    /// The structure lacks the protein or the nucleic acid part of a complex.
+    NotComplex,
+    /// The input could not be read, e.g. a missing file.
+    Io(String),
+    /// Processing failed unexpectedly (a caught panic); not caused by the input format itself.
+    Internal(String),
+}
+
+impl PskitError {
+    /// Stable machine-readable name of the variant.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PskitError::Parse(_) => "parse",
+            PskitError::ChainNotFound { .. } => "chain_not_found",
+            PskitError::InvalidRange { .. } => "invalid_range",
+            PskitError::InvalidArgument(_) => "invalid_argument",
+            PskitError::NotComplex => "not_complex",
+            PskitError::Io(_) => "io",
+            PskitError::Internal(_) => "internal",
+        }
+    }
+}
+
+impl fmt::Display for PskitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PskitError::Parse(issues) => {
+                write!(f, "File parsing failed due to:")?;
+                for issue in issues {
+                    write!(f, "\n{}: {}", issue.level, issue.message)?;
+                    if let Some(line) = issue.line {
+                        write!(f, " (line {line})")?;
+                    }
+                    if !issue.detail.is_empty() {
+                        write!(f, "\n{}", issue.detail)?;
+                    }
+                }
+                Ok(())
+            }
+            PskitError::ChainNotFound { chain_id, valid } => write!(
+                f,
+                "Chain {chain_id} not exists. Valid chain IDs are: {:?}",
+                valid
+            ),
+            PskitError::InvalidRange { chain_id, min, max } => write!(
+                f,
+                "Invalid range. For Chain {chain_id}, please enter values between {min} and {max}."
+            ),
+            PskitError::InvalidArgument(msg) => write!(f, "{msg}"),
+            PskitError::NotComplex => write!(f, "Not a protein-nucleic acid complex."),
+            PskitError::Io(msg) => write!(f, "Could not read input: {msg}"),
+            PskitError::Internal(msg) => write!(f, "Processing failed unexpectedly: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for PskitError {}
+
+pub type Result<T> = std::result::Result<T, PskitError>;
@@ -0,0 +1,479 @@
+use crate::annotate::{BindingPair, ChainLabels, LabelTarget, LabelValue};
+use crate::basepair::ChainDotBracket;
+use crate::error::PskitError;
+use crate::sequence::{ChainMapping, ChainSequence, SequenceSource, GAP};
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// File formats a distance or contact map can be written to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MapFormat {
+    /// NumPy `.npy` (version 1.0, little-endian `f8`, C order).
+    Npy,
+    /// CSV with the column axis as header and the row axis as first column.
+    Csv,
+    /// CASP RR contact list (`i j 0 cutoff probability`).
+    CaspRr,
+}
+
+impl FromStr for MapFormat {
+    type Err = PskitError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "npy" => Ok(MapFormat::Npy),
+            "csv" => Ok(MapFormat::Csv),
+            "rr" | "casp" | "casp_rr" => Ok(MapFormat::CaspRr),
+            _ => Err(PskitError::InvalidArgument(format!(
+                "Unknown map format {s:?}, expected npy, csv or rr"
+            ))),
+        }
+    }
+}
+
+/// Expand an upper-triangle map (row `i` holds residues `i+1..n`, as returned by `d_map`
+/// and `knn_map`) into a symmetric `n × n` matrix with a zero diagonal.
+pub fn square_from_upper(upper: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let n = upper.len();
+    let mut out = vec![vec![0.0; n]; n];
+    for (i, row) in upper.iter().enumerate() {
+        for (k, &v) in row.iter().enumerate() {
+            let j = i + 1 + k;
+            out[i][j] = v;
+            out[j][i] = v;
+        }
+    }
+    out
+}
+
+/// `.npy` header and data for a `rows × cols` array of `descr` elements.
+fn npy(descr: &str, rows: usize, cols: usize, data: &[u8]) -> Vec<u8> {
+    let mut header =
+        format!("{{'descr': '{descr}', 'fortran_order': False, 'shape': ({rows}, {cols}), }}");
+    // 魔数(6) + 版本(2) + 头长度(2) + 头部，总长度需按 64 字节对齐，头部以换行结尾
+    let unpadded = 10 + header.len() + 1;
+    header.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+    header.push('\n');
+
+    let mut out = Vec::with_capacity(10 + header.len() + data.len());
+    out.extend_from_slice(b"\x93NUMPY\x01\x00");
+    out.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    out.extend_from_slice(header.as_bytes());
+    out.extend_from_slice(data);
+    out
+}
+
+/// Serialize a dense `rows × cols` matrix as a NumPy `.npy` file.
+pub fn write_npy(values: &[Vec<f64>]) -> Vec<u8> {
+    let cols = values.first().map_or(0, Vec::len);
+    let data: Vec<u8> = values
+        .iter()
+        .flatten()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    npy("<f8", values.len(), cols, &data)
+}
+
+/// Serialize a dense integer matrix as a NumPy `.npy` file of little-endian `i8` (int64).
+pub fn write_npy_i64(values: &[Vec<i64>]) -> Vec<u8> {
+    let cols = values.first().map_or(0, Vec::len);
+    let data: Vec<u8> = values
+        .iter()
+        .flatten()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    npy("<i8", values.len(), cols, &data)
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// Bundle `.npy` files into an uncompressed `.npz` (zip) archive; `numpy.load` exposes each
+/// entry under its name without the `.npy` suffix.
+pub fn write_npz(entries: &[(&str, Vec<u8>)]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut directory = Vec::new();
+    for (name, data) in entries {
+        let name = format!("{name}.npy");
+        let offset = out.len() as u32;
+        let crc = crc32(data);
+        let size = data.len() as u32;
+        // 本地文件头与中央目录项共用的字段：版本、标志、存储方式(0)、时间、日期、CRC、大小
+        let mut common = Vec::new();
+        common.extend_from_slice(&20u16.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+        common.extend_from_slice(&0x21u16.to_le_bytes());
+        common.extend_from_slice(&crc.to_le_bytes());
+        common.extend_from_slice(&size.to_le_bytes());
+        common.extend_from_slice(&size.to_le_bytes());
+        common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+
+        out.extend_from_slice(b"PK\x03\x04");
+        out.extend_from_slice(&common);
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(data);
+
+        directory.extend_from_slice(b"PK\x01\x02");
+        directory.extend_from_slice(&20u16.to_le_bytes());
+        directory.extend_from_slice(&common);
+        // 注释长度、磁盘号、内部属性(各 2 字节)，外部属性(4 字节)，随后是本地头偏移
+        directory.extend_from_slice(&[0; 6]);
+        directory.extend_from_slice(&0u32.to_le_bytes());
+        directory.extend_from_slice(&offset.to_le_bytes());
+        directory.extend_from_slice(name.as_bytes());
+    }
+    let directory_offset = out.len() as u32;
+    let count = entries.len() as u16;
+    out.extend_from_slice(&directory);
+    out.extend_from_slice(b"PK\x05\x06");
+    out.extend_from_slice(&[0; 4]);
+    out.extend_from_slice(&count.to_le_bytes());
+    out.extend_from_slice(&count.to_le_bytes());
+    out.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+    out.extend_from_slice(&directory_offset.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes());
+    out
+}
+
+/// Serialize a dense matrix as CSV; the header row holds `col_axis`, each line starts with its row label.
+pub fn write_csv(row_axis: &[String], col_axis: &[String], values: &[Vec<f64>]) -> Vec<u8> {
+    let mut out = String::new();
+    for label in col_axis {
+        out.push(',');
+        out.push_str(&csv_field(label));
+    }
+    out.push('\n');
+    for (label, row) in row_axis.iter().zip(values) {
+        out.push_str(&csv_field(label));
+        for v in row {
+            let _ = write!(out, ",{v}");
+        }
+        out.push('\n');
+    }
+    out.into_bytes()
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Serialize an upper-triangle map as a CASP RR contact list.
+///
+/// Residues are numbered by their 1-based position on the axis; pairs closer than `cutoff`
+/// and at least `min_separation` apart in sequence are listed with probability 1. Zero
+/// entries (non-neighbours of a kNN map) are skipped.
+pub fn write_casp_rr(
+    axis: &[String],
+    upper: &[Vec<f64>],
+    cutoff: f64,
+    min_separation: usize,
+) -> Vec<u8> {
+    let mut out = String::from("PFRMAT RR\nMODEL  1\n");
+    // 轴标签形如 "A-52A-S"，最后一段是单字母残基
+    let sequence: String = axis
+        .iter()
+        .map(|label| {
+            label
+                .rsplit('-')
+                .next()
+                .and_then(|s| s.chars().next())
+                .unwrap_or('X')
+        })
+        .collect();
+    for line in sequence.as_bytes().chunks(50) {
+        out.push_str(std::str::from_utf8(line).unwrap_or_default());
+        out.push('\n');
+    }
+    for (i, row) in upper.iter().enumerate() {
+        for (k, &d) in row.iter().enumerate() {
+            let j = i + 1 + k;
+            if j - i < min_separation || d <= 0.0 || d >= cutoff {
+                continue;
+            }
+            let _ = writeln!(out, "{} {} 0 {cutoff} 1.000", i + 1, j + 1);
+        }
+    }
+    out.push_str("END\n");
+    out.into_bytes()
+}
+
+/// Write an upper-triangle map from `d_map`/`knn_map` in `format`.
+///
+/// RR output uses the conventional 8 Å cutoff and a minimum sequence separation of 6.
+pub fn write_map(axis: &[String], upper: &[Vec<f64>], format: MapFormat) -> Vec<u8> {
+    match format {
+        MapFormat::Npy => write_npy(&square_from_upper(upper)),
+        MapFormat::Csv => write_csv(axis, axis, &square_from_upper(upper)),
+        MapFormat::CaspRr => write_casp_rr(axis, upper, 8.0, 6),
+    }
+}
+
+/// Write binding pairs as CSV with the columns of the web download (`prot_chain, prot_resi,
+/// prot_resn, na_chain, na_resi, na_resn, distance`), followed by the closest atoms, the
+/// nucleotide region, the contact counts and whether the nucleotide is base-paired (1/0).
+pub fn write_binding_pairs_csv(pairs: &[BindingPair]) -> Vec<u8> {
+    let mut out = String::from(
+        "prot_chain,prot_resi,prot_resn,na_chain,na_resi,na_resn,distance,\
+         prot_atom,na_atom,region,contacts,backbone_contacts,base_contacts,na_paired\n",
+    );
+    for pair in pairs {
+        let (p, n) = (&pair.protein, &pair.nucleic);
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{},{:.3},{},{},{},{},{},{},{}",
+            csv_field(&p.chain_id),
+            p.number,
+            csv_field(&p.name),
+            csv_field(&n.chain_id),
+            n.number,
+            csv_field(&n.name),
+            pair.distance,
+            csv_field(&pair.protein_atom),
+            csv_field(&pair.nucleic_atom),
+            pair.region().as_str(),
+            pair.contacts,
+            pair.backbone_contacts,
+            pair.base_contacts,
+            pair.nucleic_paired as u8,
+        );
+    }
+    out.into_bytes()
+}
+
+fn label_token(v: f64, value: LabelValue) -> String {
+    match value {
+        _ if v.is_nan() => GAP.to_string(),
+        LabelValue::Binary => format!("{}", v as u8),
+        LabelValue::Distance => format!("{v:.2}"),
+    }
+}
+
+/// Write binding-site labels as a FASTA-like file: per chain a `>id_chain target` header,
+/// the sequence, and the labels on one line. Unmodeled residues are labelled [`GAP`].
+///
+/// Binary labels are written as a `0`/`1` string aligned character by character with the
+/// sequence; distance labels are space-separated with two decimals.
+pub fn write_label_fasta(
+    labels: &[ChainLabels],
+    id: &str,
+    target: LabelTarget,
+    value: LabelValue,
+) -> Vec<u8> {
+    let mut out = String::new();
+    for chain in labels {
+        let name = if id.is_empty() {
+            chain.chain_id.clone()
+        } else {
+            format!("{id}_{}", chain.chain_id)
+        };
+        let _ = writeln!(out, ">{name} {}", target.as_str());
+        out.push_str(&chain.sequence);
+        out.push('\n');
+        let tokens: Vec<_> = chain
+            .values(target, value)
+            .into_iter()
+            .map(|v| label_token(v, value))
+            .collect();
+        let sep = if value == LabelValue::Binary { "" } else { " " };
+        out.push_str(&tokens.join(sep));
+        out.push('\n');
+    }
+    out.into_bytes()
+}
+
+/// Write dot-bracket secondary structure in the `.dbn` layout: a `>id_chain` header, the
+/// sequence and the structure on one line each.
+pub fn write_dot_bracket(chains: &[ChainDotBracket], id: &str) -> Vec<u8> {
+    let mut out = String::new();
+    for chain in chains {
+        if id.is_empty() {
+            let _ = writeln!(out, ">{}", chain.chain_id);
+        } else {
+            let _ = writeln!(out, ">{id}_{}", chain.chain_id);
+        }
+        let _ = writeln!(out, "{}\n{}", chain.sequence, chain.dot_bracket);
+    }
+    out.into_bytes()
+}
+
+/// Write chain sequences as FASTA, one `>id_chain type` record per chain. Sequence lines are
+/// wrapped at `width` characters; 0 keeps each sequence on one line.
+pub fn write_fasta(sequences: &[ChainSequence], id: &str, width: usize) -> Vec<u8> {
+    let mut out = String::new();
+    for chain in sequences {
+        let name = if id.is_empty() {
+            chain.chain_id.clone()
+        } else {
+            format!("{id}_{}", chain.chain_id)
+        };
+        match chain.polymer_type {
+            Some(ty) => {
+                let _ = writeln!(out, ">{name} {}", ty.as_str());
+            }
+            None => {
+                let _ = writeln!(out, ">{name}");
+            }
+        }
+        let chars: Vec<char> = chain.sequence.chars().collect();
+        let width = if width == 0 {
+            chars.len().max(1)
+        } else {
+            width
+        };
+        for line in chars.chunks(width) {
+            out.extend(line);
+            out.push('\n');
+        }
+    }
+    out.into_bytes()
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Write binding-site labels as JSON: `{"id", "cutoff", "chains": [{"chain", "residues",
+/// "sequence", "dna", "rna"}]}`, with residue numbers as strings ("52A"). Unmodeled residues
+/// have a null number and null labels.
+pub fn write_label_json(labels: &[ChainLabels], id: &str, value: LabelValue) -> Vec<u8> {
+    let cutoff = labels.first().map_or(0.0, |l| l.cutoff);
+    let mut out = format!(
+        "{{\"id\":{},\"cutoff\":{cutoff},\"chains\":[",
+        json_string(id)
+    );
+    for (i, chain) in labels.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        let residues: Vec<_> = chain
+            .residues
+            .iter()
+            .map(|r| match r {
+                Some(r) => json_string(&r.number.to_string()),
+                None => "null".to_string(),
+            })
+            .collect();
+        let values = |target| {
+            chain
+                .values(target, value)
+                .into_iter()
+                .map(|v| {
+                    if v.is_nan() {
+                        "null".to_string()
+                    } else {
+                        label_token(v, value)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        let _ = write!(
+            out,
+            "{{\"chain\":{},\"residues\":[{}],\"sequence\":{},\"dna\":[{}],\"rna\":[{}]}}",
+            json_string(&chain.chain_id),
+            residues.join(","),
+            json_string(&chain.sequence),
+            values(LabelTarget::Dna),
+            values(LabelTarget::Rna),
+        );
+    }
+    out.push_str("]}\n");
+    out.into_bytes()
+}
+
+/// Write SEQRES-to-structure mappings as JSON: `{"id", "chains": [{"chain", "type", "source",
+/// "sequence", "residues", "unaligned", "missing": [{"start", "end", "sequence"}], "breaks":
+/// [{"before", "after", "distance"}]}]}`. `residues` holds the author number at each sequence
+/// position (null when unmodeled); `start`/`end` are 0-based inclusive sequence indices.
+pub fn write_sequence_mapping_json(mappings: &[ChainMapping], id: &str) -> Vec<u8> {
+    let mut out = format!("{{\"id\":{},\"chains\":[", json_string(id));
+    for (i, chain) in mappings.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        let residues: Vec<_> = chain
+            .residues
+            .iter()
+            .map(|r| {
+                r.as_ref()
+                    .map_or("null".to_string(), |r| json_string(&r.number.to_string()))
+            })
+            .collect();
+        let unaligned: Vec<_> = chain
+            .unaligned
+            .iter()
+            .map(|r| json_string(&r.number.to_string()))
+            .collect();
+        let missing: Vec<_> = chain
+            .missing
+            .iter()
+            .map(|m| {
+                format!(
+                    "{{\"start\":{},\"end\":{},\"sequence\":{}}}",
+                    m.start,
+                    m.end,
+                    json_string(&m.sequence)
+                )
+            })
+            .collect();
+        let breaks: Vec<_> = chain
+            .breaks
+            .iter()
+            .map(|b| {
+                format!(
+                    "{{\"before\":{},\"after\":{},\"distance\":{:.3}}}",
+                    json_string(&b.before.number.to_string()),
+                    json_string(&b.after.number.to_string()),
+                    b.distance
+                )
+            })
+            .collect();
+        let source = match chain.source {
+            SequenceSource::Atom => "atom",
+            SequenceSource::Seqres => "seqres",
+        };
+        let _ = write!(
+            out,
+            "{{\"chain\":{},\"type\":{},\"source\":\"{source}\",\"sequence\":{},\"residues\":[{}],\
+             \"unaligned\":[{}],\"missing\":[{}],\"breaks\":[{}]}}",
+            json_string(&chain.chain_id),
+            chain
+                .polymer_type
+                .map_or("null".to_string(), |ty| json_string(ty.as_str())),
+            json_string(&chain.sequence),
+            residues.join(","),
+            unaligned.join(","),
+            missing.join(","),
+            breaks.join(","),
+        );
+    }
+    out.push_str("]}\n");
+    out.into_bytes()
+}
@@ -0,0 +1,231 @@
+use std::collections::BTreeSet;
+use std::io::BufRead;
+use std::str::FromStr;
+
+use rstar::{primitives::GeomWithData, RTree};
+
+use crate::chem::ModifiedResidues;
+use crate::contact::{
+    axis_label, check_chain, d2_batch_wide, nearest, representative_points, Representative,
+};
+use crate::error::PskitError;
+use crate::export::{write_npy, write_npy_i64, write_npz};
+use crate::residue::ResidueKey;
+use crate::selection::{read_selected, Selection};
+use crate::utils::ReadOptions;
+
+/// Columns of [`ResidueGraph::features`].
+pub const EDGE_FEATURES: [&str; 6] = ["distance", "dx", "dy", "dz", "separation", "weight"];
+
+/// How residues are connected.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EdgeRule {
+    /// Every pair of residues within the cutoff, in Å.
+    Radius(f64),
+    /// Each residue to its `k` nearest residues; the graph is symmetrised, so a residue can
+    /// end up with more than `k` neighbours.
+    Knn(usize),
+}
+
+impl Default for EdgeRule {
+    fn default() -> Self {
+        EdgeRule::Radius(12.0)
+    }
+}
+
+impl FromStr for EdgeRule {
+    type Err = PskitError;
+
+    /// `radius:12` or `knn:16`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            PskitError::InvalidArgument(format!(
+                "Unknown edge rule {s:?}, expected radius:<Å> or knn:<k>"
+            ))
+        };
+        let (kind, value) = s.split_once(':').ok_or_else(invalid)?;
+        match kind.trim().to_lowercase().as_str() {
+            "radius" => value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|r: &f64| *r > 0.0)
+                .map(EdgeRule::Radius)
+                .ok_or_else(invalid),
+            "knn" => value
+                .trim()
+                .parse()
+                .map(EdgeRule::Knn)
+                .map_err(|_| invalid()),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GraphOptions {
+    pub edges: EdgeRule,
+    /// Node position; `MinHeavy` is not allowed since a node needs a single point.
+    pub repr: Representative,
+}
+
+impl Default for GraphOptions {
+    /// The 12 Å centroid graph of the INAB model.
+    fn default() -> Self {
+        GraphOptions {
+            edges: EdgeRule::default(),
+            repr: Representative::Centroid,
+        }
+    }
+}
+
+/// Residue graph for GNN inputs.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResidueGraph {
+    /// Node labels, as on the `d_map` axis.
+    pub axis: Vec<String>,
+    pub coords: Vec<[f64; 3]>,
+    /// Directed edges `(source, target)`. Each residue pair appears twice, `(i, j)` directly
+    /// followed by `(j, i)`, with pairs sorted by `i < j`.
+    pub edges: Vec<(u32, u32)>,
+    /// One row per edge, columns as in [`EDGE_FEATURES`]: distance, unit vector from source to
+    /// target, sequence separation within the chain (0 across chains) and `ln(separation) /
+    /// distance` (0 across chains).
+    pub features: Vec<[f64; 6]>,
+}
+
+impl ResidueGraph {
+    /// `2 × E` edge index in the PyTorch Geometric layout.
+    pub fn edge_index(&self) -> Vec<Vec<i64>> {
+        vec![
+            self.edges.iter().map(|&(s, _)| s as i64).collect(),
+            self.edges.iter().map(|&(_, t)| t as i64).collect(),
+        ]
+    }
+
+    /// `.npz` archive with `edge_index` (`2 × E`, int64), `edge_attr` (`E × 6`) and `coords`
+    /// (`N × 3`).
+    pub fn to_npz(&self) -> Vec<u8> {
+        let features: Vec<Vec<f64>> = self.features.iter().map(|f| f.to_vec()).collect();
+        let coords: Vec<Vec<f64>> = self.coords.iter().map(|c| c.to_vec()).collect();
+        write_npz(&[
+            ("edge_index", write_npy_i64(&self.edge_index())),
+            ("edge_attr", write_npy(&features)),
+            ("coords", write_npy(&coords)),
+        ])
+    }
+}
+
+/// Undirected residue pairs `i < j` connected under `rule`.
+fn connect(points: &[[f64; 3]], rule: EdgeRule) -> BTreeSet<(u32, u32)> {
+    let mut pairs = BTreeSet::new();
+    match rule {
+        EdgeRule::Radius(cutoff) => {
+            let tree = RTree::bulk_load(
+                points
+                    .iter()
+                    .enumerate()
+                    .map(|(i, p)| GeomWithData::new(*p, i as u32))
+                    .collect(),
+            );
+            for (i, p) in points.iter().enumerate() {
+                for b in tree.locate_within_distance(*p, cutoff * cutoff) {
+                    if b.data > i as u32 {
+                        pairs.insert((i as u32, b.data));
+                    }
+                }
+            }
+        }
+        EdgeRule::Knn(k) => {
+            let x: Vec<f64> = points.iter().map(|p| p[0]).collect();
+            let y: Vec<f64> = points.iter().map(|p| p[1]).collect();
+            let z: Vec<f64> = points.iter().map(|p| p[2]).collect();
+            for (i, p) in points.iter().enumerate() {
+                let mut row = d2_batch_wide(&x, &y, &z, *p);
+                // 自身不算近邻；按下标建边，距离为 0 的重合点同样是近邻
+                row[i] = f64::INFINITY;
+                for j in nearest(&row, k) {
+                    if j != i {
+                        pairs.insert((i.min(j) as u32, i.max(j) as u32));
+                    }
+                }
+            }
+        }
+    }
+    pairs
+}
+
+/// Build a residue graph over the first model.
+///
+/// Nodes are the selected residues (optionally restricted to `chain_id`) in file order,
+/// placed at the `options.repr` point. For the INAB preprocessing pass the protein chain,
+/// e.g. selection `protein and chain A`, with the default options.
+pub fn residue_graph<R: BufRead>(
+    reader: R,
+    chain_id: Option<String>,
+    options: GraphOptions,
+    format: &str,
+    selection: &Selection,
+) -> Result<ResidueGraph, PskitError> {
+    if options.repr == Representative::MinHeavy {
+        return Err(PskitError::InvalidArgument(
+            "Residue graphs need one point per residue, min_heavy is not supported".to_string(),
+        ));
+    }
+    let (pdb, _classifier) = read_selected(
+        reader,
+        format,
+        ReadOptions::default(),
+        ModifiedResidues::default(),
+        selection,
+    )?;
+    check_chain(&pdb, chain_id.as_deref())?;
+
+    let mut graph = ResidueGraph::default();
+    // 每个节点所在链的序号及其在链内的位置，用于计算序列间隔
+    let mut positions: Vec<(usize, usize)> = Vec::new();
+    if let Some(model) = pdb.models().next() {
+        let chains = model
+            .chains()
+            .filter(|chain| chain_id.as_deref().is_none_or(|cid| cid == chain.id()));
+        for (c, chain) in chains.enumerate() {
+            for (r, residue) in chain.residues().enumerate() {
+                graph
+                    .axis
+                    .push(axis_label(&ResidueKey::new(chain.id(), residue)));
+                graph
+                    .coords
+                    .push(representative_points(residue, options.repr)[0]);
+                positions.push((c, r));
+            }
+        }
+    }
+
+    for (i, j) in connect(&graph.coords, options.edges) {
+        let (a, b) = (graph.coords[i as usize], graph.coords[j as usize]);
+        let delta: [f64; 3] = std::array::from_fn(|k| b[k] - a[k]);
+        let distance = delta.iter().map(|d| d * d).sum::<f64>().sqrt();
+        let unit = delta.map(|d| if distance > 0.0 { d / distance } else { 0.0 });
+        let ((chain_a, ra), (chain_b, rb)) = (positions[i as usize], positions[j as usize]);
+        let separation = if chain_a == chain_b {
+            ra.abs_diff(rb)
+        } else {
+            0
+        };
+        let weight = if separation > 0 && distance > 0.0 {
+            (separation as f64).ln() / distance
+        } else {
+            0.0
+        };
+        let separation = separation as f64;
+        graph.edges.push((i, j));
+        graph
+            .features
+            .push([distance, unit[0], unit[1], unit[2], separation, weight]);
+        graph.edges.push((j, i));
+        graph
+            .features
+            .push([distance, -unit[0], -unit[1], -unit[2], separation, weight]);
+    }
+    Ok(graph)
+}
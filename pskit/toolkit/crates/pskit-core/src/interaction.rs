@@ -0,0 +1,535 @@
+use pdbtbx::{Model, Residue};
+use rstar::{primitives::GeomWithData, RTree};
+use std::io::BufRead;
+
+use crate::annotate::NucleicRegion;
+use crate::chem::{standard_residue, ModifiedResidues, ResidueClassifier};
+use crate::error::PskitError;
+use crate::residue::ResidueKey;
+use crate::selection::{read_selected, Selection};
+use crate::utils::{three_to_one, ReadOptions};
+
+/// Kind of a protein–nucleic acid contact.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum InteractionType {
+    HydrogenBond,
+    SaltBridge,
+    PiStacking,
+    CationPi,
+    VanDerWaals,
+}
+
+impl InteractionType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InteractionType::HydrogenBond => "hbond",
+            InteractionType::SaltBridge => "salt_bridge",
+            InteractionType::PiStacking => "pi_stacking",
+            InteractionType::CationPi => "cation_pi",
+            InteractionType::VanDerWaals => "vdw",
+        }
+    }
+}
+
+/// Geometric criteria of every interaction class; distances in Å, angles in degrees.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InteractionCriteria {
+    /// Donor–acceptor heavy-atom distance.
+    pub hbond_distance: f64,
+    /// Minimum angle antecedent–donor–acceptor (hydrogens are not required).
+    pub hbond_angle: f64,
+    /// Arg/Lys nitrogen to phosphate oxygen distance.
+    pub salt_bridge_distance: f64,
+    /// Ring centroid distance.
+    pub stacking_distance: f64,
+    /// Maximum angle between the two ring normals.
+    pub stacking_angle: f64,
+    /// Cation to nucleobase ring centroid distance.
+    pub cation_pi_distance: f64,
+    /// Maximum angle between the ring normal and the centroid–cation vector.
+    pub cation_pi_angle: f64,
+    /// Allowed excess over the sum of van der Waals radii.
+    pub vdw_tolerance: f64,
+}
+
+impl Default for InteractionCriteria {
+    fn default() -> Self {
+        InteractionCriteria {
+            hbond_distance: 3.5,
+            hbond_angle: 90.0,
+            salt_bridge_distance: 4.0,
+            stacking_distance: 5.5,
+            stacking_angle: 30.0,
+            cation_pi_distance: 6.0,
+            cation_pi_angle: 30.0,
+            vdw_tolerance: 0.5,
+        }
+    }
+}
+
+/// One classified contact. Ring interactions name the ring atoms joined by `+`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Interaction {
+    pub kind: InteractionType,
+    pub protein: ResidueKey,
+    pub nucleic: ResidueKey,
+    pub protein_atom: String,
+    pub nucleic_atom: String,
+    /// Atom distance, or centroid distance for ring interactions.
+    pub distance: f64,
+    /// H-bond antecedent angle, inter-ring angle or cation–normal angle.
+    pub angle: Option<f64>,
+}
+
+pub(crate) type Vec3 = [f64; 3];
+/// Ring centroid, normal and `+`-joined atom names.
+pub(crate) type Ring = (Vec3, Vec3, String);
+
+pub(crate) fn sub(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub(crate) fn dot(a: Vec3, b: Vec3) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub(crate) fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+pub(crate) fn norm(a: Vec3) -> f64 {
+    dot(a, a).sqrt()
+}
+
+pub(crate) fn dist(a: Vec3, b: Vec3) -> f64 {
+    norm(sub(a, b))
+}
+
+/// Angle between two vectors in degrees.
+pub(crate) fn angle(a: Vec3, b: Vec3) -> f64 {
+    (dot(a, b) / (norm(a) * norm(b)))
+        .clamp(-1.0, 1.0)
+        .acos()
+        .to_degrees()
+}
+
+/// Acute angle between two lines (normals have no orientation).
+pub(crate) fn line_angle(a: Vec3, b: Vec3) -> f64 {
+    let t = angle(a, b);
+    t.min(180.0 - t)
+}
+
+const PROTEIN_DONORS: &[(&str, &[&str])] = &[
+    ("ARG", &["NE", "NH1", "NH2"]),
+    ("ASN", &["ND2"]),
+    ("CYS", &["SG"]),
+    ("GLN", &["NE2"]),
+    ("HIS", &["ND1", "NE2"]),
+    ("LYS", &["NZ"]),
+    ("SER", &["OG"]),
+    ("THR", &["OG1"]),
+    ("TRP", &["NE1"]),
+    ("TYR", &["OH"]),
+];
+
+const PROTEIN_ACCEPTORS: &[(&str, &[&str])] = &[
+    ("ASN", &["OD1"]),
+    ("ASP", &["OD1", "OD2"]),
+    ("GLN", &["OE1"]),
+    ("GLU", &["OE1", "OE2"]),
+    ("HIS", &["ND1", "NE2"]),
+    ("MET", &["SD"]),
+    ("SER", &["OG"]),
+    ("THR", &["OG1"]),
+    ("TYR", &["OH"]),
+];
+
+pub(crate) const BASE_DONORS: &[(char, &[&str])] = &[
+    ('A', &["N6"]),
+    ('C', &["N4"]),
+    ('G', &["N1", "N2"]),
+    ('U', &["N3"]),
+    ('T', &["N3"]),
+];
+
+pub(crate) const BASE_ACCEPTORS: &[(char, &[&str])] = &[
+    ('A', &["N1", "N3", "N7"]),
+    ('C', &["O2", "N3"]),
+    ('G', &["O6", "N3", "N7"]),
+    ('U', &["O2", "O4"]),
+    ('T', &["O2", "O4"]),
+];
+
+const PHOSPHATE_OXYGENS: &[&str] = &["OP1", "OP2", "OP3", "O1P", "O2P", "O3P"];
+const BACKBONE_ACCEPTORS: &[&str] = &[
+    "OP1", "OP2", "OP3", "O1P", "O2P", "O3P", "O5'", "O3'", "O4'",
+];
+
+// 环原子按成环顺序列出（法向量计算依赖顺序）
+const PROTEIN_RINGS: &[(&str, &[&str])] = &[
+    ("PHE", &["CG", "CD1", "CE1", "CZ", "CE2", "CD2"]),
+    ("TYR", &["CG", "CD1", "CE1", "CZ", "CE2", "CD2"]),
+    ("TRP", &["CD2", "CE2", "CZ2", "CH2", "CZ3", "CE3"]),
+    ("TRP", &["CG", "CD1", "NE1", "CE2", "CD2"]),
+    ("HIS", &["CG", "ND1", "CE1", "NE2", "CD2"]),
+];
+
+pub(crate) const SIX_RING: &[&str] = &["N1", "C2", "N3", "C4", "C5", "C6"];
+const PURINE_FIVE_RING: &[&str] = &["C4", "C5", "N7", "C8", "N9"];
+
+const CATIONS: &[(&str, &str)] = &[("ARG", "CZ"), ("LYS", "NZ")];
+
+pub(crate) fn lookup<'a, K: PartialEq>(table: &[(K, &'a [&'a str])], key: K) -> Vec<&'a str> {
+    table
+        .iter()
+        .filter(|(k, _)| *k == key)
+        .flat_map(|(_, names)| names.iter().copied())
+        .collect()
+}
+
+fn vdw_radius(element: &str) -> f64 {
+    match element {
+        "N" => 1.55,
+        "O" => 1.52,
+        "S" | "P" => 1.8,
+        "SE" | "Se" => 1.9,
+        _ => 1.7,
+    }
+}
+
+pub(crate) struct ResAtom {
+    pub(crate) name: String,
+    element: String,
+    pub(crate) pos: Vec3,
+}
+
+pub(crate) struct Res {
+    pub(crate) key: ResidueKey,
+    /// Standard three-letter name for proteins, base letter for nucleotides.
+    pub(crate) kind: String,
+    pub(crate) atoms: Vec<ResAtom>,
+}
+
+impl Res {
+    fn new(chain_id: &str, residue: &Residue, kind: String) -> Self {
+        let atoms = residue
+            .atoms()
+            .filter_map(|atom| {
+                let element = atom
+                    .element()
+                    .map(|e| e.symbol().to_string())
+                    .unwrap_or_else(|| atom.name().chars().take(1).collect());
+                if element == "H" || element == "D" {
+                    return None;
+                }
+                Some(ResAtom {
+                    name: atom.name().to_string(),
+                    element,
+                    pos: atom.pos().into(),
+                })
+            })
+            .collect();
+        Res {
+            key: ResidueKey::new(chain_id, residue),
+            kind,
+            atoms,
+        }
+    }
+
+    pub(crate) fn atom(&self, name: &str) -> Option<&ResAtom> {
+        self.atoms.iter().find(|a| a.name == name)
+    }
+
+    /// Closest other heavy atom of the residue, standing in for the atom a donor is bonded to.
+    pub(crate) fn antecedent(&self, donor: &ResAtom) -> Option<Vec3> {
+        self.atoms
+            .iter()
+            .filter(|a| a.name != donor.name)
+            .map(|a| (dist(a.pos, donor.pos), a.pos))
+            .filter(|(d, _)| *d < 1.9)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, p)| p)
+    }
+
+    /// Centroid, normal and atom names of a ring, if all its atoms are present.
+    pub(crate) fn ring(&self, names: &[&str]) -> Option<Ring> {
+        let points: Vec<Vec3> = names
+            .iter()
+            .map(|n| self.atom(n).map(|a| a.pos))
+            .collect::<Option<_>>()?;
+        let k = points.len() as f64;
+        let c = points
+            .iter()
+            .fold([0.0; 3], |acc, p| {
+                [acc[0] + p[0], acc[1] + p[1], acc[2] + p[2]]
+            })
+            .map(|v| v / k);
+        // Newell 法求平面法向量
+        let mut n = [0.0; 3];
+        for (i, p) in points.iter().enumerate() {
+            let q = points[(i + 1) % points.len()];
+            n[0] += (p[1] - q[1]) * (p[2] + q[2]);
+            n[1] += (p[2] - q[2]) * (p[0] + q[0]);
+            n[2] += (p[0] - q[0]) * (p[1] + q[1]);
+        }
+        Some((c, n, names.join("+")))
+    }
+
+    fn protein_rings(&self) -> Vec<Ring> {
+        PROTEIN_RINGS
+            .iter()
+            .filter(|(res, _)| *res == self.kind)
+            .filter_map(|(_, names)| self.ring(names))
+            .collect()
+    }
+
+    pub(crate) fn base_rings(&self) -> Vec<Ring> {
+        let mut rings = vec![SIX_RING];
+        if matches!(self.kind.as_str(), "A" | "G") {
+            rings.push(PURINE_FIVE_RING);
+        }
+        rings
+            .into_iter()
+            .filter_map(|names| self.ring(names))
+            .collect()
+    }
+
+    fn is_protein_donor(&self, atom: &str) -> bool {
+        atom == "N" && self.kind != "PRO"
+            || lookup(PROTEIN_DONORS, self.kind.as_str()).contains(&atom)
+    }
+
+    fn is_protein_acceptor(&self, atom: &str) -> bool {
+        matches!(atom, "O" | "OXT") || lookup(PROTEIN_ACCEPTORS, self.kind.as_str()).contains(&atom)
+    }
+
+    pub(crate) fn base_letter(&self) -> char {
+        self.kind.chars().next().unwrap_or('X')
+    }
+
+    fn is_nucleic_donor(&self, atom: &str) -> bool {
+        atom == "O2'" || lookup(BASE_DONORS, self.base_letter()).contains(&atom)
+    }
+
+    fn is_nucleic_acceptor(&self, atom: &str) -> bool {
+        atom == "O2'"
+            || BACKBONE_ACCEPTORS.contains(&atom)
+            || lookup(BASE_ACCEPTORS, self.base_letter()).contains(&atom)
+    }
+}
+
+pub(crate) fn model_residues(
+    model: &Model,
+    classifier: &ResidueClassifier,
+) -> (Vec<Res>, Vec<Res>) {
+    let mut protein = Vec::new();
+    let mut nucleic = Vec::new();
+    for chain in model.chains() {
+        for residue in chain.residues() {
+            let Some(name) = residue.name() else { continue };
+            if classifier.is_protein(chain.id(), name) {
+                let kind = standard_residue(name).unwrap_or(name).to_string();
+                protein.push(Res::new(chain.id(), residue, kind));
+            } else if classifier.is_nucleic(chain.id(), name) {
+                let kind = three_to_one(name).to_string();
+                nucleic.push(Res::new(chain.id(), residue, kind));
+            }
+        }
+    }
+    (protein, nucleic)
+}
+
+fn classify_model(
+    model: &Model,
+    classifier: &ResidueClassifier,
+    criteria: &InteractionCriteria,
+) -> Vec<Interaction> {
+    let (protein, nucleic) = model_residues(model, classifier);
+    let mut out = Vec::new();
+
+    // 原子级相互作用：核酸重原子建 rtree，(残基序号, 原子序号)
+    let reach = [
+        criteria.hbond_distance,
+        criteria.salt_bridge_distance,
+        2.0 * vdw_radius("S") + criteria.vdw_tolerance,
+    ]
+    .into_iter()
+    .fold(0.0, f64::max);
+    let points: Vec<GeomWithData<Vec3, (usize, usize)>> = nucleic
+        .iter()
+        .enumerate()
+        .flat_map(|(r, res)| {
+            res.atoms
+                .iter()
+                .enumerate()
+                .map(move |(a, atom)| GeomWithData::new(atom.pos, (r, a)))
+        })
+        .collect();
+    let tree = RTree::bulk_load(points);
+
+    for p_res in &protein {
+        for p_atom in &p_res.atoms {
+            for hit in tree.locate_within_distance(p_atom.pos, reach * reach) {
+                let (r, a) = hit.data;
+                let n_res = &nucleic[r];
+                let n_atom = &n_res.atoms[a];
+                let d = dist(p_atom.pos, n_atom.pos);
+                let contact = |kind, angle| Interaction {
+                    kind,
+                    protein: p_res.key.clone(),
+                    nucleic: n_res.key.clone(),
+                    protein_atom: p_atom.name.clone(),
+                    nucleic_atom: n_atom.name.clone(),
+                    distance: d,
+                    angle,
+                };
+
+                let salt_bridge = d <= criteria.salt_bridge_distance
+                    && matches!(
+                        (p_res.kind.as_str(), p_atom.name.as_str()),
+                        ("ARG", "NE" | "NH1" | "NH2") | ("LYS", "NZ")
+                    )
+                    && PHOSPHATE_OXYGENS.contains(&n_atom.name.as_str());
+                if salt_bridge {
+                    out.push(contact(InteractionType::SaltBridge, None));
+                }
+
+                let hbond = if d > criteria.hbond_distance {
+                    None
+                } else if p_res.is_protein_donor(&p_atom.name)
+                    && n_res.is_nucleic_acceptor(&n_atom.name)
+                {
+                    p_res
+                        .antecedent(p_atom)
+                        .map(|ante| angle(sub(ante, p_atom.pos), sub(n_atom.pos, p_atom.pos)))
+                } else if n_res.is_nucleic_donor(&n_atom.name)
+                    && p_res.is_protein_acceptor(&p_atom.name)
+                {
+                    n_res
+                        .antecedent(n_atom)
+                        .map(|ante| angle(sub(ante, n_atom.pos), sub(p_atom.pos, n_atom.pos)))
+                } else {
+                    None
+                };
+                let hbond = hbond.filter(|a| *a >= criteria.hbond_angle);
+                if let Some(a) = hbond {
+                    out.push(contact(InteractionType::HydrogenBond, Some(a)));
+                }
+
+                let vdw_limit = vdw_radius(&p_atom.element)
+                    + vdw_radius(&n_atom.element)
+                    + criteria.vdw_tolerance;
+                if !salt_bridge && hbond.is_none() && d <= vdw_limit {
+                    out.push(contact(InteractionType::VanDerWaals, None));
+                }
+            }
+        }
+    }
+
+    // 环相关：π 堆积与阳离子-π
+    let base_rings: Vec<(&Res, Vec<Ring>)> =
+        nucleic.iter().map(|res| (res, res.base_rings())).collect();
+    for p_res in &protein {
+        let rings = p_res.protein_rings();
+        let cation = CATIONS
+            .iter()
+            .find(|(res, _)| *res == p_res.kind)
+            .and_then(|(_, name)| p_res.atom(name));
+        if rings.is_empty() && cation.is_none() {
+            continue;
+        }
+        for (n_res, n_rings) in &base_rings {
+            for (nc, nn, n_names) in n_rings {
+                for (pc, pn, p_names) in &rings {
+                    let d = dist(*pc, *nc);
+                    let a = line_angle(*pn, *nn);
+                    if d <= criteria.stacking_distance && a <= criteria.stacking_angle {
+                        out.push(Interaction {
+                            kind: InteractionType::PiStacking,
+                            protein: p_res.key.clone(),
+                            nucleic: n_res.key.clone(),
+                            protein_atom: p_names.clone(),
+                            nucleic_atom: n_names.clone(),
+                            distance: d,
+                            angle: Some(a),
+                        });
+                    }
+                }
+                if let Some(cation) = cation {
+                    let d = dist(cation.pos, *nc);
+                    let a = line_angle(*nn, sub(cation.pos, *nc));
+                    if d <= criteria.cation_pi_distance && a <= criteria.cation_pi_angle {
+                        out.push(Interaction {
+                            kind: InteractionType::CationPi,
+                            protein: p_res.key.clone(),
+                            nucleic: n_res.key.clone(),
+                            protein_atom: cation.name.clone(),
+                            nucleic_atom: n_names.clone(),
+                            distance: d,
+                            angle: Some(a),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    out
+}
+
+/// Classify every protein–nucleic acid contact of the first model as H-bond, salt bridge,
+/// π-stacking, cation-π or van der Waals.
+///
+/// An atom pair forming an H-bond or salt bridge is not also reported as van der Waals.
+/// Results are sorted by protein residue, nucleotide, then interaction type.
+pub fn classify_interactions<R: BufRead>(
+    reader: R,
+    format: &str,
+    modified: ModifiedResidues,
+    selection: &Selection,
+    criteria: &InteractionCriteria,
+) -> Result<Vec<Interaction>, PskitError> {
+    let (pdb, classifier) =
+        read_selected(reader, format, ReadOptions::default(), modified, selection)?;
+
+    if !classifier.is_complex(&pdb) {
+        return Err(PskitError::NotComplex);
+    }
+
+    let mut out = pdb
+        .models()
+        .next()
+        .map(|model| classify_model(model, &classifier, criteria))
+        .unwrap_or_default();
+    out.sort_by(|a, b| {
+        (
+            &a.protein,
+            &a.nucleic,
+            a.kind,
+            &a.protein_atom,
+            &a.nucleic_atom,
+        )
+            .cmp(&(
+                &b.protein,
+                &b.nucleic,
+                b.kind,
+                &b.protein_atom,
+                &b.nucleic_atom,
+            ))
+    });
+    Ok(out)
+}
+
+impl Interaction {
+    /// Nucleotide region touched; ring interactions always involve the base.
+    pub fn region(&self) -> NucleicRegion {
+        match self.kind {
+            InteractionType::PiStacking | InteractionType::CationPi => NucleicRegion::Base,
+            _ => NucleicRegion::of_atom(&self.nucleic_atom),
+        }
+    }
+}
@@ -1,6 +1,26 @@
 // This file defines the core library's public interface and exports the functionality of the annotate, map, and split modules.
 
+pub mod align;
 pub mod annotate;
+pub mod basepair;
+pub mod batch;
+pub mod chem;
+mod cif;
 pub mod contact;
+pub mod dssp;
+pub mod error;
+pub mod export;
+pub mod graph;
+pub mod interaction;
+pub mod quality;
+pub mod residue;
+pub mod sasa;
+pub mod selection;
+pub mod sequence;
 pub mod split;
+pub mod superpose;
 pub mod utils;
+
+pub use error::PskitError;
+pub use residue::{ResidueKey, ResidueNumber, ResidueRange};
+pub use selection::Selection;
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::io::BufRead;
+
+use crate::chem::ModifiedResidues;
+use crate::contact::upper_distances;
+use crate::error::PskitError;
+use crate::residue::ResidueKey;
+use crate::selection::{read_selected, Selection};
+use crate::superpose::{kabsch, match_residues, selected_chains, ResidueMatch};
+use crate::utils::ReadOptions;
+
+/// lDDT inclusion radius on the reference, in Å.
+const LDDT_RADIUS: f64 = 15.0;
+const LDDT_THRESHOLDS: [f64; 4] = [0.5, 1.0, 2.0, 4.0];
+const GDT_CUTOFFS: [f64; 4] = [1.0, 2.0, 4.0, 8.0];
+
+/// Per-residue scores, one entry per reference residue.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResidueQuality {
+    pub reference: ResidueKey,
+    /// Matched model residue, if any.
+    pub model: Option<ResidueKey>,
+    /// None when no other reference residue lies within the inclusion radius.
+    pub lddt: Option<f64>,
+    /// Distance to the reference after the TM-score superposition.
+    pub deviation: Option<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StructureQuality {
+    /// TM-score normalised by the reference length.
+    pub tm_score: f64,
+    /// Superposition of the model onto the reference that maximises the TM-score.
+    pub rotation: [[f64; 3]; 3],
+    pub translation: [f64; 3],
+    /// GDT-TS as a fraction (0–1).
+    pub gdt_ts: f64,
+    pub lddt: f64,
+    /// Matched residue pairs.
+    pub aligned: usize,
+    /// Reference residues, the length every score is normalised by.
+    pub reference_length: usize,
+    pub residues: Vec<ResidueQuality>,
+}
+
+/// Distance scale of the TM-score for a reference of `length` residues.
+fn tm_d0(length: usize) -> f64 {
+    if length <= 21 {
+        0.5
+    } else {
+        (1.24 * (length as f64 - 15.0).cbrt() - 1.8).max(0.5)
+    }
+}
+
+fn transform(rotation: &[[f64; 3]; 3], translation: &[f64; 3], p: [f64; 3]) -> [f64; 3] {
+    std::array::from_fn(|i| {
+        rotation[i][0] * p[0] + rotation[i][1] * p[1] + rotation[i][2] * p[2] + translation[i]
+    })
+}
+
+fn distances(x: &[[f64; 3]], y: &[[f64; 3]], fit: &Fit) -> Vec<f64> {
+    x.iter()
+        .zip(y)
+        .map(|(p, q)| {
+            let p = transform(&fit.0, &fit.1, *p);
+            ((p[0] - q[0]).powi(2) + (p[1] - q[1]).powi(2) + (p[2] - q[2]).powi(2)).sqrt()
+        })
+        .collect()
+}
+
+type Fit = ([[f64; 3]; 3], [f64; 3]);
+
+/// TM-align style search for the superposition of `x` onto `y` maximising `score`.
+///
+/// Every fragment of length n, n/2, n/4, … (down to 4) seeds a fit that is refined by
+/// refitting on the pairs closer than `cutoff`, until that set stops changing.
+fn search(
+    x: &[[f64; 3]],
+    y: &[[f64; 3]],
+    cutoff: f64,
+    score: impl Fn(&[f64]) -> f64,
+) -> (f64, Fit) {
+    let n = x.len();
+    let mut best = (f64::NEG_INFINITY, kabsch(x, y));
+    let mut lengths = vec![n];
+    let mut length = n / 2;
+    while length >= 4 {
+        lengths.push(length);
+        length /= 2;
+    }
+    let fit_on = |indices: &[usize]| {
+        let xs: Vec<[f64; 3]> = indices.iter().map(|&i| x[i]).collect();
+        let ys: Vec<[f64; 3]> = indices.iter().map(|&i| y[i]).collect();
+        kabsch(&xs, &ys)
+    };
+    for length in lengths {
+        // 长片段的起点太多时跳着取，控制搜索量
+        let step = ((n - length) / 20).max(1);
+        for start in (0..=n - length).step_by(step) {
+            let mut indices: Vec<usize> = (start..start + length).collect();
+            for _ in 0..20 {
+                let fit = fit_on(&indices);
+                let d = distances(x, y, &fit);
+                let value = score(&d);
+                if value > best.0 {
+                    best = (value, fit);
+                }
+                let mut limit = cutoff;
+                let mut next: Vec<usize> = Vec::new();
+                while next.len() < 3.min(n) {
+                    next = (0..n).filter(|&i| d[i] < limit).collect();
+                    limit += 0.5;
+                }
+                if next == indices {
+                    break;
+                }
+                indices = next;
+            }
+        }
+    }
+    best
+}
+
+/// lDDT of each reference residue and over all pairs, from the reference distance map and
+/// the model positions of the matched residues (`None` for unmatched ones, which count as
+/// not preserved).
+fn lddt(reference: &[[f64; 3]], model: &[Option<[f64; 3]>]) -> (f64, Vec<Option<f64>>) {
+    let reference_map = upper_distances(reference);
+    let n = reference.len();
+    let mut preserved = vec![0.0; n];
+    let mut total = vec![0usize; n];
+    for (i, row) in reference_map.iter().enumerate() {
+        for (k, &d_ref) in row.iter().enumerate() {
+            let j = i + 1 + k;
+            if d_ref >= LDDT_RADIUS {
+                continue;
+            }
+            let fraction = match (model[i], model[j]) {
+                (Some(p), Some(q)) => {
+                    let d = ((p[0] - q[0]).powi(2) + (p[1] - q[1]).powi(2) + (p[2] - q[2]).powi(2))
+                        .sqrt();
+                    let diff = (d - d_ref).abs();
+                    LDDT_THRESHOLDS.iter().filter(|&&t| diff < t).count() as f64
+                        / LDDT_THRESHOLDS.len() as f64
+                }
+                _ => 0.0,
+            };
+            for r in [i, j] {
+                preserved[r] += fraction;
+                total[r] += 1;
+            }
+        }
+    }
+    let pairs: usize = total.iter().sum();
+    let global = if pairs == 0 {
+        0.0
+    } else {
+        preserved.iter().sum::<f64>() / pairs as f64
+    };
+    let residues = preserved
+        .iter()
+        .zip(&total)
+        .map(|(p, &t)| (t > 0).then(|| p / t as f64))
+        .collect();
+    (global, residues)
+}
+
+/// Compare a model against a reference structure: TM-score, GDT-TS and lDDT.
+///
+/// Each residue is represented by its CA (C3' for nucleotides, otherwise the centroid of its
+/// selected atoms); residues are paired by `matching`. Only the first model of each file is
+/// used, and all scores are normalised by the number of selected reference residues.
+pub fn compare_structures<R1: BufRead, R2: BufRead>(
+    model: R1,
+    model_format: &str,
+    model_selection: &Selection,
+    reference: R2,
+    reference_format: &str,
+    reference_selection: &Selection,
+    matching: ResidueMatch,
+) -> Result<StructureQuality, PskitError> {
+    let (model_pdb, model_classifier) = read_selected(
+        model,
+        model_format,
+        ReadOptions::default(),
+        ModifiedResidues::default(),
+        &Selection::all(),
+    )?;
+    let (reference_pdb, reference_classifier) = read_selected(
+        reference,
+        reference_format,
+        ReadOptions::default(),
+        ModifiedResidues::default(),
+        &Selection::all(),
+    )?;
+    let model_chains = selected_chains(&model_pdb, &model_classifier, model_selection)?;
+    let reference_chains =
+        selected_chains(&reference_pdb, &reference_classifier, reference_selection)?;
+
+    let index: HashMap<&ResidueKey, usize> = reference_chains
+        .iter()
+        .flatten()
+        .enumerate()
+        .map(|(i, r)| (&r.key, i))
+        .collect();
+    let reference_points: Vec<[f64; 3]> = reference_chains
+        .iter()
+        .flatten()
+        .map(|r| r.point())
+        .collect();
+    let length = reference_points.len();
+
+    let pairs = match_residues(&model_chains, &reference_chains, matching);
+    if pairs.len() < 3 {
+        return Err(PskitError::InvalidArgument(format!(
+            "Structure comparison needs at least 3 matched residues, found {}",
+            pairs.len()
+        )));
+    }
+    let x: Vec<[f64; 3]> = pairs.iter().map(|(a, _)| a.point()).collect();
+    let y: Vec<[f64; 3]> = pairs.iter().map(|(_, b)| b.point()).collect();
+
+    let d0 = tm_d0(length);
+    let (tm_score, fit) = search(&x, &y, d0.clamp(4.5, 8.0), |d| {
+        d.iter()
+            .map(|d| 1.0 / (1.0 + (d / d0).powi(2)))
+            .sum::<f64>()
+            / length as f64
+    });
+    let gdt_ts = GDT_CUTOFFS
+        .iter()
+        .map(|&cutoff| {
+            search(&x, &y, cutoff, |d| {
+                d.iter().filter(|&&d| d <= cutoff).count() as f64
+            })
+            .0 / length as f64
+        })
+        .sum::<f64>()
+        / GDT_CUTOFFS.len() as f64;
+
+    let mut model_points = vec![None; length];
+    let mut matched = vec![None; length];
+    let deviations = distances(&x, &y, &fit);
+    for ((a, b), deviation) in pairs.iter().zip(deviations) {
+        let i = index[&b.key];
+        model_points[i] = Some(a.point());
+        matched[i] = Some((a.key.clone(), deviation));
+    }
+    let (lddt, per_residue) = lddt(&reference_points, &model_points);
+
+    let residues = reference_chains
+        .iter()
+        .flatten()
+        .zip(matched)
+        .zip(per_residue)
+        .map(|((r, matched), lddt)| ResidueQuality {
+            reference: r.key.clone(),
+            model: matched.as_ref().map(|(key, _)| key.clone()),
+            lddt,
+            deviation: matched.map(|(_, d)| d),
+        })
+        .collect();
+
+    Ok(StructureQuality {
+        tm_score,
+        rotation: fit.0,
+        translation: fit.1,
+        gdt_ts,
+        lddt,
+        aligned: pairs.len(),
+        reference_length: length,
+        residues,
+    })
+}
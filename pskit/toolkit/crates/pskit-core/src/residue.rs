@@ -0,0 +1,136 @@
+use crate::error::PskitError;
+use pdbtbx::Residue;
+use std::fmt;
+use std::str::FromStr;
+
+/// Author residue number including the insertion code, e.g. `52`, `52A`, `-3`.
+///
+/// Orders by sequence number first and then by insertion code, so `52 < 52A < 52B < 53`
+/// as in Kabat/Chothia numbering.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ResidueNumber {
+    pub serial: isize,
+    pub insertion_code: Option<String>,
+}
+
+impl ResidueNumber {
+    pub fn new(serial: isize, insertion_code: Option<&str>) -> Self {
+        ResidueNumber {
+            serial,
+            insertion_code: insertion_code
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(String::from),
+        }
+    }
+
+    pub fn of(residue: &Residue) -> Self {
+        let (serial, icode) = residue.id();
+        Self::new(serial, icode)
+    }
+}
+
+impl From<isize> for ResidueNumber {
+    fn from(serial: isize) -> Self {
+        ResidueNumber {
+            serial,
+            insertion_code: None,
+        }
+    }
+}
+
+impl fmt::Display for ResidueNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.serial)?;
+        if let Some(icode) = &self.insertion_code {
+            write!(f, "{icode}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ResidueNumber {
+    type Err = PskitError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let digits_end = s
+            .char_indices()
+            .find(|&(i, c)| !(c.is_ascii_digit() || (i == 0 && c == '-')))
+            .map_or(s.len(), |(i, _)| i);
+        let (num, icode) = s.split_at(digits_end);
+        let serial = num
+            .parse::<isize>()
+            .map_err(|_| PskitError::InvalidArgument(format!("Invalid residue number: {s:?}")))?;
+        if !icode.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(PskitError::InvalidArgument(format!(
+                "Invalid insertion code in residue number: {s:?}"
+            )));
+        }
+        Ok(ResidueNumber::new(serial, Some(icode)))
+    }
+}
+
+/// Inclusive residue range such as `52A..100`; either side may be left open (`..100`, `52A..`).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ResidueRange {
+    pub start: Option<ResidueNumber>,
+    pub end: Option<ResidueNumber>,
+}
+
+impl ResidueRange {
+    pub fn new(start: Option<ResidueNumber>, end: Option<ResidueNumber>) -> Self {
+        ResidueRange { start, end }
+    }
+
+    pub fn contains(&self, number: &ResidueNumber) -> bool {
+        self.start.as_ref().is_none_or(|s| s <= number)
+            && self.end.as_ref().is_none_or(|e| number <= e)
+    }
+}
+
+impl FromStr for ResidueRange {
+    type Err = PskitError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_side = |side: &str| -> Result<Option<ResidueNumber>, PskitError> {
+            let side = side.trim();
+            if side.is_empty() {
+                Ok(None)
+            } else {
+                side.parse().map(Some)
+            }
+        };
+        match s.split_once("..") {
+            Some((start, end)) => Ok(ResidueRange::new(parse_side(start)?, parse_side(end)?)),
+            None => {
+                let single = parse_side(s)?;
+                Ok(ResidueRange::new(single.clone(), single))
+            }
+        }
+    }
+}
+
+/// Identity of a residue shared by split, contact and annotate.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ResidueKey {
+    pub chain_id: String,
+    pub number: ResidueNumber,
+    pub name: String,
+}
+
+impl ResidueKey {
+    pub fn new(chain_id: &str, residue: &Residue) -> Self {
+        ResidueKey {
+            chain_id: chain_id.to_string(),
+            number: ResidueNumber::of(residue),
+            name: residue.name().unwrap_or("UNK").to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ResidueKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}-{}", self.chain_id, self.number, self.name)
+    }
+}
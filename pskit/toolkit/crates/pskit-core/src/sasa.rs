@@ -0,0 +1,352 @@
+use pdbtbx::{
+    Atom, ContainsAtomConformer, ContainsAtomConformerResidue, ContainsAtomConformerResidueChain,
+    PDB,
+};
+use rstar::{primitives::GeomWithData, RTree};
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::io::BufRead;
+
+use crate::chem::{standard_residue, ModifiedResidues};
+use crate::error::PskitError;
+use crate::residue::ResidueKey;
+use crate::selection::{read_selected, Selection, WATER};
+use crate::split::complex_parts;
+use crate::utils::ReadOptions;
+
+/// Shrake–Rupley parameters.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SasaOptions {
+    pub probe_radius: f64,
+    /// Test points per atom sphere.
+    pub n_points: usize,
+    pub include_hydrogens: bool,
+}
+
+impl Default for SasaOptions {
+    fn default() -> Self {
+        SasaOptions {
+            probe_radius: 1.4,
+            n_points: 100,
+            include_hydrogens: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtomSasa {
+    pub residue: ResidueKey,
+    pub atom: String,
+    pub area: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResidueSasa {
+    pub key: ResidueKey,
+    pub area: f64,
+    /// Area relative to the residue's maximum in a Gly-X-Gly tripeptide; amino acids only.
+    pub relative: Option<f64>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Sasa {
+    pub total: f64,
+    pub atoms: Vec<AtomSasa>,
+    pub residues: Vec<ResidueSasa>,
+}
+
+/// Surface of one residue in the isolated part versus in the complex.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResidueBurial {
+    pub key: ResidueKey,
+    pub unbound: f64,
+    pub bound: f64,
+}
+
+impl ResidueBurial {
+    pub fn buried(&self) -> f64 {
+        self.unbound - self.bound
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BuriedSurface {
+    pub protein: f64,
+    pub nucleic: f64,
+    pub complex: f64,
+    /// Residues that lose surface on binding, protein then nucleic.
+    pub residues: Vec<ResidueBurial>,
+}
+
+impl BuriedSurface {
+    /// Total buried surface area, `protein + nucleic - complex`.
+    pub fn buried(&self) -> f64 {
+        self.protein + self.nucleic - self.complex
+    }
+}
+
+/// Van der Waals radius (Bondi) by element, falling back on the first letter of the atom name.
+fn vdw_radius(atom: &Atom) -> f64 {
+    let element = atom
+        .element()
+        .map(|e| e.symbol().to_uppercase())
+        .unwrap_or_else(|| atom.name().chars().take(1).collect());
+    match element.as_str() {
+        "H" | "D" => 1.10,
+        "C" => 1.70,
+        "N" => 1.55,
+        "O" => 1.52,
+        "S" => 1.80,
+        "P" => 1.80,
+        "SE" => 1.90,
+        "F" => 1.47,
+        "CL" => 1.75,
+        "BR" => 1.85,
+        "I" => 1.98,
+        "MG" => 1.73,
+        "NA" => 2.27,
+        "K" => 2.75,
+        "ZN" => 1.39,
+        _ => 1.80,
+    }
+}
+
+fn is_hydrogen(atom: &Atom) -> bool {
+    match atom.element() {
+        Some(e) => matches!(e.symbol(), "H" | "D"),
+        None => atom.name().starts_with('H'),
+    }
+}
+
+/// Maximum accessible area of amino acids (Tien et al. 2013, theoretical).
+fn max_asa(name: &str) -> Option<f64> {
+    let area = match standard_residue(name).unwrap_or(name) {
+        "ALA" => 129.0,
+        "ARG" => 274.0,
+        "ASN" => 195.0,
+        "ASP" => 193.0,
+        "CYS" => 167.0,
+        "GLN" => 225.0,
+        "GLU" => 223.0,
+        "GLY" => 104.0,
+        "HIS" => 224.0,
+        "ILE" => 197.0,
+        "LEU" => 201.0,
+        "LYS" => 236.0,
+        "MET" => 224.0,
+        "PHE" => 240.0,
+        "PRO" => 159.0,
+        "SER" => 155.0,
+        "THR" => 172.0,
+        "TRP" => 285.0,
+        "TYR" => 263.0,
+        "VAL" => 174.0,
+        _ => return None,
+    };
+    Some(area)
+}
+
+/// Evenly spread unit vectors on a golden-section spiral.
+fn sphere_points(n: usize) -> Vec<[f64; 3]> {
+    let golden_angle = PI * (3.0 - 5f64.sqrt());
+    (0..n)
+        .map(|k| {
+            let y = 1.0 - (2 * k + 1) as f64 / n as f64;
+            let r = (1.0 - y * y).sqrt();
+            let phi = k as f64 * golden_angle;
+            [phi.cos() * r, y, phi.sin() * r]
+        })
+        .collect()
+}
+
+struct SurfaceAtom {
+    residue: ResidueKey,
+    name: String,
+    pos: [f64; 3],
+    radius: f64,
+}
+
+/// Atoms of the first model taking part in the surface; water is never included.
+fn surface_atoms(pdb: &PDB, options: &SasaOptions) -> Vec<SurfaceAtom> {
+    let mut atoms = Vec::new();
+    let Some(model) = pdb.models().next() else {
+        return atoms;
+    };
+    for atom in model.atoms_with_hierarchy() {
+        if atom
+            .residue()
+            .name()
+            .is_some_and(|name| WATER.contains(&name))
+            || (!options.include_hydrogens && is_hydrogen(atom.atom()))
+        {
+            continue;
+        }
+        let (x, y, z) = atom.atom().pos();
+        atoms.push(SurfaceAtom {
+            residue: ResidueKey::new(atom.chain().id(), atom.residue()),
+            name: atom.atom().name().to_string(),
+            pos: [x, y, z],
+            radius: vdw_radius(atom.atom()) + options.probe_radius,
+        });
+    }
+    atoms
+}
+
+/// Shrake–Rupley accessible area of every atom.
+fn shrake_rupley(atoms: &[SurfaceAtom], n_points: usize) -> Vec<f64> {
+    let points = sphere_points(n_points.max(1));
+    let max_radius = atoms.iter().map(|a| a.radius).fold(0.0, f64::max);
+    let tree = RTree::bulk_load(
+        atoms
+            .iter()
+            .enumerate()
+            .map(|(i, a)| GeomWithData::new(a.pos, i))
+            .collect(),
+    );
+
+    let mut neighbours: Vec<usize> = Vec::new();
+    atoms
+        .iter()
+        .enumerate()
+        .map(|(i, atom)| {
+            neighbours.clear();
+            let reach = atom.radius + max_radius;
+            for hit in tree.locate_within_distance(atom.pos, reach * reach) {
+                let j = hit.data;
+                let other = &atoms[j];
+                let limit = atom.radius + other.radius;
+                if j != i && dist2(atom.pos, other.pos) < limit * limit {
+                    neighbours.push(j);
+                }
+            }
+
+            // 逐个测试球面点是否被邻近原子覆盖；上一次遮挡的原子最可能再次遮挡
+            let mut last = 0;
+            let mut exposed = 0;
+            for u in &points {
+                let p = [
+                    atom.pos[0] + atom.radius * u[0],
+                    atom.pos[1] + atom.radius * u[1],
+                    atom.pos[2] + atom.radius * u[2],
+                ];
+                let covered = |j: usize| {
+                    let other = &atoms[j];
+                    dist2(p, other.pos) < other.radius * other.radius
+                };
+                if neighbours.get(last).is_some_and(|&j| covered(j)) {
+                    continue;
+                }
+                match neighbours.iter().position(|&j| covered(j)) {
+                    Some(k) => last = k,
+                    None => exposed += 1,
+                }
+            }
+            4.0 * PI * atom.radius * atom.radius * exposed as f64 / points.len() as f64
+        })
+        .collect()
+}
+
+fn dist2(a: [f64; 3], b: [f64; 3]) -> f64 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)
+}
+
+fn sasa_of(atoms: Vec<SurfaceAtom>, options: &SasaOptions) -> Sasa {
+    let areas = shrake_rupley(&atoms, options.n_points);
+    let mut residues: Vec<ResidueSasa> = Vec::new();
+    let mut sasa = Sasa::default();
+    for (atom, area) in atoms.into_iter().zip(areas) {
+        sasa.total += area;
+        match residues.last_mut() {
+            Some(last) if last.key == atom.residue => last.area += area,
+            _ => residues.push(ResidueSasa {
+                key: atom.residue.clone(),
+                area,
+                relative: None,
+            }),
+        }
+        sasa.atoms.push(AtomSasa {
+            residue: atom.residue,
+            atom: atom.name,
+            area,
+        });
+    }
+    for residue in &mut residues {
+        residue.relative = max_asa(&residue.key.name).map(|max| residue.area / max);
+    }
+    sasa.residues = residues;
+    sasa
+}
+
+pub(crate) fn model_sasa(pdb: &PDB, options: &SasaOptions) -> Sasa {
+    sasa_of(surface_atoms(pdb, options), options)
+}
+
+/// Solvent accessible surface area of the first model (Shrake–Rupley), per atom and per
+/// residue. Water is excluded; ligands count when selected.
+pub fn compute_sasa<R: BufRead>(
+    reader: R,
+    format: &str,
+    selection: &Selection,
+    options: &SasaOptions,
+) -> Result<Sasa, PskitError> {
+    let (pdb, _classifier) = read_selected(
+        reader,
+        format,
+        ReadOptions::default(),
+        ModifiedResidues::default(),
+        selection,
+    )?;
+    Ok(model_sasa(&pdb, options))
+}
+
+/// Surface buried between the protein and nucleic-acid parts of a complex, as split by
+/// [`split_complex`](crate::split::split_complex); ligands are left out of all three surfaces.
+pub fn buried_surface_area<R: BufRead>(
+    reader: R,
+    format: &str,
+    modified: ModifiedResidues,
+    selection: &Selection,
+    options: &SasaOptions,
+) -> Result<BuriedSurface, PskitError> {
+    let (pdb, classifier) =
+        read_selected(reader, format, ReadOptions::default(), modified, selection)?;
+
+    if !classifier.is_complex(&pdb) {
+        return Err(PskitError::NotComplex);
+    }
+
+    let (protein_pdb, nucleic_pdb) = complex_parts(&pdb, &classifier);
+    let protein_atoms = surface_atoms(&protein_pdb, options);
+    let nucleic_atoms = surface_atoms(&nucleic_pdb, options);
+
+    let protein = sasa_of(protein_atoms, options);
+    let nucleic = sasa_of(nucleic_atoms, options);
+    let mut complex_atoms = surface_atoms(&protein_pdb, options);
+    complex_atoms.extend(surface_atoms(&nucleic_pdb, options));
+    let areas = shrake_rupley(&complex_atoms, options.n_points);
+
+    let mut bound: HashMap<&ResidueKey, f64> = HashMap::new();
+    for (atom, area) in complex_atoms.iter().zip(&areas) {
+        *bound.entry(&atom.residue).or_default() += area;
+    }
+    let residues = protein
+        .residues
+        .iter()
+        .chain(&nucleic.residues)
+        .filter_map(|r| {
+            let burial = ResidueBurial {
+                key: r.key.clone(),
+                unbound: r.area,
+                bound: bound.get(&r.key).copied().unwrap_or_default(),
+            };
+            (burial.buried() > 1e-6).then_some(burial)
+        })
+        .collect();
+
+    Ok(BuriedSurface {
+        protein: protein.total,
+        nucleic: nucleic.total,
+        complex: areas.iter().sum(),
+        residues,
+    })
+}
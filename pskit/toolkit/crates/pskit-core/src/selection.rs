@@ -0,0 +1,473 @@
+use crate::chem::{ModifiedResidues, PolymerType, ResidueClassifier};
+use crate::error::PskitError;
+use crate::residue::{ResidueNumber, ResidueRange};
+use crate::utils::{read_raw_with, read_text, ReadOptions};
+use pdbtbx::{Model, PDB};
+use rstar::RTree;
+use std::cell::Cell;
+use std::fmt;
+use std::io::BufRead;
+use std::str::FromStr;
+
+/// Atom selection parsed from a small query language, e.g.
+/// `chain A and resi 10-50 and name CA`, `nucleic and not hydrogen`, `within 5 of chain B`.
+///
+/// Grammar (case-insensitive keywords, `and` binds tighter than `or`):
+///
+/// ```text
+/// expr     := term ("or" term)*
+/// term     := factor ("and" factor)*
+/// factor   := "not" factor | "within" NUMBER "of" factor | "(" expr ")" | predicate
+/// predicate:= "all" | "none" | "protein" | "nucleic" | "dna" | "rna" | "water" | "ligand"
+///           | "hydrogen" | "backbone" | "hetatm"
+///           | ("chain" | "resn" | "name" | "element") VALUE+
+///           | "resi" RANGE+          RANGE: 10, 52A, 10-50, 52A..100
+/// ```
+///
+/// Values may be separated by spaces, `+` or `,`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Selection {
+    expr: Expr,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    All,
+    None,
+    Polymer(Option<PolymerType>),
+    Water,
+    Ligand,
+    Hydrogen,
+    Backbone,
+    Hetatm,
+    Chain(Vec<String>),
+    Resn(Vec<String>),
+    Name(Vec<String>),
+    Element(Vec<String>),
+    Resi(Vec<ResidueRange>),
+    Within(f64, Box<Expr>),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+const PROTEIN_BACKBONE: &[&str] = &["N", "CA", "C", "O", "OXT"];
+const NUCLEIC_BACKBONE: &[&str] = &["P", "OP1", "OP2", "OP3", "O5'", "C5'", "C4'", "C3'", "O3'"];
+pub(crate) const WATER: &[&str] = &["HOH", "WAT", "DOD", "H2O"];
+
+struct AtomInfo<'a> {
+    chain_id: &'a str,
+    number: ResidueNumber,
+    resname: &'a str,
+    name: &'a str,
+    element: Option<&'static str>,
+    hetero: bool,
+    polymer: Option<PolymerType>,
+    pos: [f64; 3],
+}
+
+impl AtomInfo<'_> {
+    fn is_hydrogen(&self) -> bool {
+        match self.element {
+            Some(e) => e == "H" || e == "D",
+            None => self.name.starts_with('H'),
+        }
+    }
+}
+
+impl Selection {
+    pub fn all() -> Self {
+        Selection { expr: Expr::All }
+    }
+
+    pub fn chain(chain_id: impl Into<String>) -> Self {
+        Selection {
+            expr: Expr::Chain(vec![chain_id.into()]),
+        }
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.expr == Expr::All
+    }
+
+    /// Both selections must match.
+    pub fn and(self, other: Selection) -> Self {
+        match (self.is_all(), other.is_all()) {
+            (true, _) => other,
+            (_, true) => self,
+            _ => Selection {
+                expr: Expr::And(Box::new(self.expr), Box::new(other.expr)),
+            },
+        }
+    }
+
+    /// Chain IDs the selection cannot match without, used to report missing chains.
+    ///
+    /// Only single-chain terms joined by `and` at the top level count: `not chain Z`,
+    /// `chain A or chain Z` and `within 5 of chain Z` are well defined without chain Z.
+    pub fn chains(&self) -> Vec<&str> {
+        fn walk<'a>(expr: &'a Expr, out: &mut Vec<&'a str>) {
+            match expr {
+                Expr::Chain(ids) if ids.len() == 1 => out.push(&ids[0]),
+                Expr::And(a, b) => {
+                    walk(a, out);
+                    walk(b, out);
+                }
+                _ => {}
+            }
+        }
+        let mut out = Vec::new();
+        walk(&self.expr, &mut out);
+        out
+    }
+
+    /// Per-atom mask in chain → residue → conformer → atom order, as `model.atoms()` yields them.
+    pub fn mask(&self, model: &Model, classifier: &ResidueClassifier) -> Vec<bool> {
+        let mut atoms = Vec::new();
+        for chain in model.chains() {
+            for residue in chain.residues() {
+                let resname = residue.name().unwrap_or("UNK");
+                let polymer = classifier.classify(chain.id(), resname);
+                let number = ResidueNumber::of(residue);
+                for atom in residue.conformers().flat_map(|c| c.atoms()) {
+                    atoms.push(AtomInfo {
+                        chain_id: chain.id(),
+                        number: number.clone(),
+                        resname,
+                        name: atom.name(),
+                        element: atom.element().map(|e| e.symbol()),
+                        hetero: atom.hetero(),
+                        polymer,
+                        pos: atom.pos().into(),
+                    });
+                }
+            }
+        }
+        eval(&self.expr, &atoms)
+    }
+
+    /// Fail with [`PskitError::ChainNotFound`] if the selection names a chain `pdb` lacks.
+    pub fn check_chains(&self, pdb: &PDB) -> Result<(), PskitError> {
+        let required = self.chains();
+        if required.is_empty() {
+            return Ok(());
+        }
+        let mut chain_ids: Vec<String> = Vec::new();
+        for chain in pdb.chains() {
+            if !chain_ids.iter().any(|id| id == chain.id()) {
+                chain_ids.push(chain.id().to_string());
+            }
+        }
+        match required
+            .into_iter()
+            .find(|c| !chain_ids.iter().any(|id| id == c))
+        {
+            Some(missing) => Err(PskitError::ChainNotFound {
+                chain_id: missing.to_string(),
+                valid: chain_ids,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Keep only the selected atoms in every model of `pdb`, dropping emptied residues and chains.
+    ///
+    /// Fails with [`PskitError::ChainNotFound`] if the selection names a chain the structure lacks.
+    pub fn apply(&self, pdb: &mut PDB, classifier: &ResidueClassifier) -> Result<(), PskitError> {
+        self.check_chains(pdb)?;
+        if self.is_all() {
+            return Ok(());
+        }
+
+        for model in pdb.models_mut() {
+            let mask = self.mask(model, classifier);
+            let index = Cell::new(0);
+            model.remove_atoms_by(|_| {
+                let i = index.get();
+                index.set(i + 1);
+                !mask[i]
+            });
+            model.remove_empty();
+        }
+        pdb.remove_empty();
+        Ok(())
+    }
+}
+
+impl Default for Selection {
+    fn default() -> Self {
+        Selection::all()
+    }
+}
+
+fn eval(expr: &Expr, atoms: &[AtomInfo]) -> Vec<bool> {
+    let each = |f: &dyn Fn(&AtomInfo) -> bool| atoms.iter().map(f).collect::<Vec<bool>>();
+    match expr {
+        Expr::All => vec![true; atoms.len()],
+        Expr::None => vec![false; atoms.len()],
+        Expr::Polymer(None) => each(&|a| a.polymer.is_some_and(PolymerType::is_nucleic)),
+        Expr::Polymer(Some(ty)) => each(&|a| a.polymer == Some(*ty)),
+        Expr::Water => each(&|a| WATER.contains(&a.resname)),
+        Expr::Ligand => each(&|a| a.polymer.is_none() && !WATER.contains(&a.resname)),
+        Expr::Hydrogen => each(&|a| a.is_hydrogen()),
+        Expr::Backbone => each(&|a| match a.polymer {
+            Some(PolymerType::Protein) => PROTEIN_BACKBONE.contains(&a.name),
+            Some(_) => NUCLEIC_BACKBONE.contains(&a.name),
+            None => false,
+        }),
+        Expr::Hetatm => each(&|a| a.hetero),
+        Expr::Chain(ids) => each(&|a| ids.iter().any(|id| id == a.chain_id)),
+        Expr::Resn(names) => each(&|a| names.iter().any(|n| n.eq_ignore_ascii_case(a.resname))),
+        Expr::Name(names) => each(&|a| names.iter().any(|n| n.eq_ignore_ascii_case(a.name))),
+        Expr::Element(elems) => each(&|a| {
+            a.element
+                .is_some_and(|e| elems.iter().any(|n| n.eq_ignore_ascii_case(e)))
+        }),
+        Expr::Resi(ranges) => each(&|a| ranges.iter().any(|r| r.contains(&a.number))),
+        Expr::Within(dist, inner) => {
+            let inner = eval(inner, atoms);
+            let points: Vec<[f64; 3]> = atoms
+                .iter()
+                .zip(&inner)
+                .filter(|(_, &keep)| keep)
+                .map(|(a, _)| a.pos)
+                .collect();
+            let tree = RTree::bulk_load(points);
+            each(&|a| {
+                tree.locate_within_distance(a.pos, dist * dist)
+                    .next()
+                    .is_some()
+            })
+        }
+        Expr::Not(inner) => eval(inner, atoms).into_iter().map(|b| !b).collect(),
+        Expr::And(a, b) => eval(a, atoms)
+            .into_iter()
+            .zip(eval(b, atoms))
+            .map(|(x, y)| x && y)
+            .collect(),
+        Expr::Or(a, b) => eval(a, atoms)
+            .into_iter()
+            .zip(eval(b, atoms))
+            .map(|(x, y)| x || y)
+            .collect(),
+    }
+}
+
+// ---- parsing ----
+
+#[derive(Clone, Debug, PartialEq)]
+enum Tok {
+    Open,
+    Close,
+    Word(String),
+}
+
+fn lex(input: &str) -> Vec<Tok> {
+    let mut toks = Vec::new();
+    let mut word = String::new();
+    let flush = |word: &mut String, toks: &mut Vec<Tok>| {
+        if !word.is_empty() {
+            toks.push(Tok::Word(std::mem::take(word)));
+        }
+    };
+    for c in input.chars() {
+        match c {
+            '(' | ')' => {
+                flush(&mut word, &mut toks);
+                toks.push(if c == '(' { Tok::Open } else { Tok::Close });
+            }
+            c if c.is_whitespace() || c == '+' || c == ',' => flush(&mut word, &mut toks),
+            c => word.push(c),
+        }
+    }
+    flush(&mut word, &mut toks);
+    toks
+}
+
+const KEYWORDS: &[&str] = &[
+    "and", "or", "not", "within", "of", "all", "none", "protein", "nucleic", "dna", "rna", "water",
+    "ligand", "hydrogen", "backbone", "hetatm", "chain", "resn", "resi", "name", "element",
+];
+
+fn is_keyword(tok: &Tok) -> bool {
+    match tok {
+        Tok::Word(w) => KEYWORDS.contains(&w.to_lowercase().as_str()),
+        _ => true,
+    }
+}
+
+struct Parser {
+    toks: Vec<Tok>,
+    pos: usize,
+}
+
+fn syntax_error(msg: impl fmt::Display) -> PskitError {
+    PskitError::InvalidArgument(format!("Invalid selection: {msg}"))
+}
+
+impl Parser {
+    fn peek_word(&self) -> Option<String> {
+        match self.toks.get(self.pos) {
+            Some(Tok::Word(w)) => Some(w.to_lowercase()),
+            _ => None,
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, PskitError> {
+        let mut lhs = self.term()?;
+        while self.peek_word().as_deref() == Some("or") {
+            self.pos += 1;
+            lhs = Expr::Or(Box::new(lhs), Box::new(self.term()?));
+        }
+        Ok(lhs)
+    }
+
+    fn term(&mut self) -> Result<Expr, PskitError> {
+        let mut lhs = self.factor()?;
+        while self.peek_word().as_deref() == Some("and") {
+            self.pos += 1;
+            lhs = Expr::And(Box::new(lhs), Box::new(self.factor()?));
+        }
+        Ok(lhs)
+    }
+
+    fn values(&mut self, keyword: &str) -> Result<Vec<String>, PskitError> {
+        let mut out = Vec::new();
+        while let Some(tok) = self.toks.get(self.pos) {
+            if is_keyword(tok) {
+                break;
+            }
+            if let Tok::Word(w) = tok {
+                out.push(w.clone());
+            }
+            self.pos += 1;
+        }
+        if out.is_empty() {
+            return Err(syntax_error(format!(
+                "`{keyword}` needs at least one value"
+            )));
+        }
+        Ok(out)
+    }
+
+    fn factor(&mut self) -> Result<Expr, PskitError> {
+        let tok = self
+            .toks
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| syntax_error("unexpected end of input"))?;
+        self.pos += 1;
+        let word = match tok {
+            Tok::Open => {
+                let inner = self.expr()?;
+                if self.toks.get(self.pos) != Some(&Tok::Close) {
+                    return Err(syntax_error("missing `)`"));
+                }
+                self.pos += 1;
+                return Ok(inner);
+            }
+            Tok::Close => return Err(syntax_error("unexpected `)`")),
+            Tok::Word(w) => w,
+        };
+        let expr = match word.to_lowercase().as_str() {
+            "not" => Expr::Not(Box::new(self.factor()?)),
+            "within" => {
+                let dist = self
+                    .peek_word()
+                    .and_then(|w| w.parse::<f64>().ok())
+                    .filter(|d| *d >= 0.0)
+                    .ok_or_else(|| syntax_error("`within` needs a distance"))?;
+                self.pos += 1;
+                if self.peek_word().as_deref() != Some("of") {
+                    return Err(syntax_error("expected `of` after `within <distance>`"));
+                }
+                self.pos += 1;
+                Expr::Within(dist, Box::new(self.factor()?))
+            }
+            "all" => Expr::All,
+            "none" => Expr::None,
+            "protein" => Expr::Polymer(Some(PolymerType::Protein)),
+            "nucleic" => Expr::Polymer(None),
+            "dna" => Expr::Polymer(Some(PolymerType::Dna)),
+            "rna" => Expr::Polymer(Some(PolymerType::Rna)),
+            "water" => Expr::Water,
+            "ligand" => Expr::Ligand,
+            "hydrogen" => Expr::Hydrogen,
+            "backbone" => Expr::Backbone,
+            "hetatm" => Expr::Hetatm,
+            "chain" => Expr::Chain(self.values("chain")?),
+            "resn" => Expr::Resn(self.values("resn")?),
+            "name" => Expr::Name(self.values("name")?),
+            "element" => Expr::Element(self.values("element")?),
+            "resi" => Expr::Resi(
+                self.values("resi")?
+                    .iter()
+                    .map(|v| parse_resi(v))
+                    .collect::<Result<_, _>>()?,
+            ),
+            _ => return Err(syntax_error(format!("unknown keyword `{word}`"))),
+        };
+        Ok(expr)
+    }
+}
+
+/// `10`, `52A`, `10-50`, `-5--1`, `52A..100` or `10:50`.
+fn parse_resi(value: &str) -> Result<ResidueRange, PskitError> {
+    if value.contains("..") {
+        return value.parse();
+    }
+    let split = value.find(':').or_else(|| {
+        value
+            .char_indices()
+            .skip(1)
+            .find(|&(_, c)| c == '-')
+            .map(|(i, _)| i)
+    });
+    match split {
+        Some(i) => {
+            let start: ResidueNumber = value[..i].parse()?;
+            let end: ResidueNumber = value[i + 1..].parse()?;
+            Ok(ResidueRange::new(Some(start), Some(end)))
+        }
+        None => value.parse(),
+    }
+}
+
+impl FromStr for Selection {
+    type Err = PskitError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let toks = lex(s);
+        if toks.is_empty() {
+            return Ok(Selection::all());
+        }
+        let mut parser = Parser { toks, pos: 0 };
+        let expr = parser.expr()?;
+        if parser.pos != parser.toks.len() {
+            return Err(syntax_error(format!(
+                "unexpected `{}`",
+                match &parser.toks[parser.pos] {
+                    Tok::Word(w) => w.as_str(),
+                    Tok::Open => "(",
+                    Tok::Close => ")",
+                }
+            )));
+        }
+        Ok(Selection { expr })
+    }
+}
+
+/// Read a structure, classify its residues and keep only the atoms matched by `selection`.
+pub fn read_selected<R: BufRead>(
+    reader: R,
+    format: &str,
+    options: ReadOptions,
+    modified: ModifiedResidues,
+    selection: &Selection,
+) -> Result<(PDB, ResidueClassifier), PskitError> {
+    let text = read_text(reader)?;
+    let (mut pdb, _errors) = read_raw_with(text.as_bytes(), format, options)?;
+    let classifier = ResidueClassifier::from_text(&text, modified);
+    selection.apply(&mut pdb, &classifier)?;
+    Ok((pdb, classifier))
+}
@@ -0,0 +1,557 @@
+use pdbtbx::PDB;
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::str::FromStr;
+
+use crate::chem::{EntityPoly, ModifiedResidues, PolymerType, ResidueClassifier};
+use crate::cif::{is_null, CifIndex};
+use crate::error::PskitError;
+use crate::residue::{ResidueKey, ResidueNumber};
+use crate::selection::Selection;
+use crate::utils::{read_raw_with, read_text, three_to_one, ReadOptions};
+
+/// Character written for each missing residue when gaps are marked.
+pub const GAP: char = '-';
+
+/// Where a chain sequence is read from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SequenceSource {
+    /// Residues with coordinates (ATOM/HETATM).
+    #[default]
+    Atom,
+    /// The deposited sequence (SEQRES / `_entity_poly_seq`), falling back on the coordinates
+    /// for chains that have none.
+    Seqres,
+}
+
+impl FromStr for SequenceSource {
+    type Err = PskitError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "atom" | "structure" => Ok(SequenceSource::Atom),
+            "seqres" | "entity" => Ok(SequenceSource::Seqres),
+            _ => Err(PskitError::InvalidArgument(format!(
+                "Unknown sequence source {s:?}, expected \"atom\" or \"seqres\""
+            ))),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SequenceOptions {
+    pub source: SequenceSource,
+    /// Mark residues missing from the coordinates with [`GAP`], judged by gaps in the residue
+    /// numbering. Only applies to sequences read from the coordinates.
+    pub mark_gaps: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChainSequence {
+    pub chain_id: String,
+    pub polymer_type: Option<PolymerType>,
+    /// Source the sequence was actually read from.
+    pub source: SequenceSource,
+    pub sequence: String,
+    /// Observed residues in sequence order; empty for deposited sequences.
+    pub residues: Vec<ResidueKey>,
+}
+
+/// One-letter code; unknown nucleotides are `N`, everything else unknown is `X`.
+fn one_letter(
+    name: &str,
+    polymer_type: Option<PolymerType>,
+    modres: &HashMap<String, String>,
+) -> char {
+    let code = three_to_one(modres.get(name).map_or(name, String::as_str));
+    match code {
+        'X' if polymer_type.is_some_and(PolymerType::is_nucleic) => 'N',
+        code => code,
+    }
+}
+
+/// Distance beyond which consecutive residues are not bonded, in Å.
+const BREAK_DISTANCE: f64 = 2.0;
+
+/// Atoms of a residue bonding to the previous (N / P) and the next residue (C / O3').
+type Links = (Option<[f64; 3]>, Option<[f64; 3]>);
+
+/// Polymer residues of one chain in the first model.
+struct ObservedChain {
+    chain_id: String,
+    /// Most common polymer type among the residues.
+    polymer_type: Option<PolymerType>,
+    residues: Vec<ResidueKey>,
+    links: Vec<Links>,
+}
+
+fn observed_chains(pdb: &PDB, classifier: &ResidueClassifier) -> Vec<ObservedChain> {
+    let Some(model) = pdb.models().next() else {
+        return Vec::new();
+    };
+    let mut chains = Vec::new();
+    for chain in model.chains() {
+        let mut counts: HashMap<PolymerType, usize> = HashMap::new();
+        let mut residues = Vec::new();
+        let mut links = Vec::new();
+        // pdbtbx 会按 SEQRES 为未建模位置补上无原子的残基，这些不算观测到的残基
+        for residue in chain.residues().filter(|r| r.atom_count() > 0) {
+            let Some(ty) = residue
+                .name()
+                .and_then(|n| classifier.classify(chain.id(), n))
+            else {
+                continue;
+            };
+            *counts.entry(ty).or_default() += 1;
+            let (head, tail) = if ty.is_nucleic() {
+                ("P", "O3'")
+            } else {
+                ("N", "C")
+            };
+            let find = |name: &str| {
+                residue
+                    .atoms()
+                    .find(|a| a.name() == name)
+                    .map(|a| <[f64; 3]>::from(a.pos()))
+            };
+            residues.push(ResidueKey::new(chain.id(), residue));
+            links.push((find(head), find(tail)));
+        }
+        if !residues.is_empty() {
+            let polymer_type = counts.into_iter().max_by_key(|(_, n)| *n).map(|(ty, _)| ty);
+            chains.push(ObservedChain {
+                chain_id: chain.id().to_string(),
+                polymer_type,
+                residues,
+                links,
+            });
+        }
+    }
+    chains
+}
+
+/// Structure with its selection applied, plus the deposited sequences of the file.
+pub(crate) struct Deposited {
+    pub(crate) pdb: PDB,
+    pub(crate) classifier: ResidueClassifier,
+    entities: Vec<EntityPoly>,
+    /// Parents of modified residues declared by `MODRES`.
+    modres: HashMap<String, String>,
+    /// Author number at each sequence position by chain, from `_pdbx_poly_seq_scheme`
+    /// (None for unmodeled positions).
+    schemes: HashMap<String, Vec<Option<ResidueNumber>>>,
+}
+
+impl Deposited {
+    pub(crate) fn read<R: BufRead>(
+        reader: R,
+        format: &str,
+        modified: ModifiedResidues,
+        selection: &Selection,
+    ) -> Result<Self, PskitError> {
+        let text = read_text(reader)?;
+        let (mut pdb, _errors) = read_raw_with(text.as_bytes(), format, ReadOptions::default())?;
+        // mmCIF 只分词一次，实体、分类器和 poly_seq_scheme 共用
+        let index = text.contains("_entity_poly").then(|| CifIndex::new(&text));
+        let (entities, modres) = match &index {
+            Some(index) => (EntityPoly::from_cif(index), HashMap::new()),
+            None => EntityPoly::from_pdb(&text),
+        };
+        let classifier = ResidueClassifier::from_entities(&entities, modres.clone(), modified);
+        selection.apply(&mut pdb, &classifier)?;
+
+        let mut schemes: HashMap<String, Vec<Option<ResidueNumber>>> = HashMap::new();
+        let rows = index
+            .iter()
+            .flat_map(|index| index.category("_pdbx_poly_seq_scheme"));
+        for row in rows {
+            let (Some(chain_id), Some(seq_id)) = (
+                row.get("pdb_strand_id"),
+                row.get("seq_id").and_then(|v| v.parse::<usize>().ok()),
+            ) else {
+                continue;
+            };
+            let number = row
+                .get("auth_seq_num")
+                .filter(|v| !is_null(v))
+                .and_then(|v| v.parse::<isize>().ok())
+                .map(|serial| {
+                    let code = row.get("pdb_ins_code").filter(|v| !is_null(v));
+                    ResidueNumber::new(serial, code.map(String::as_str))
+                });
+            let positions = schemes.entry(chain_id.clone()).or_default();
+            // 微异质性会让同一 seq_id 出现多行，只取第一行
+            if seq_id == positions.len() + 1 {
+                positions.push(number);
+            }
+        }
+
+        Ok(Deposited {
+            pdb,
+            classifier,
+            entities,
+            modres,
+            schemes,
+        })
+    }
+
+    fn entity(&self, chain_id: &str) -> Option<&EntityPoly> {
+        self.entities
+            .iter()
+            .find(|e| !e.monomers.is_empty() && e.strand_ids.iter().any(|id| id == chain_id))
+    }
+}
+
+/// Sequences of the polymer chains (protein, DNA and RNA) of the first model, in chain order.
+///
+/// The selection picks the chains and, for sequences read from the coordinates, the residues.
+/// Chains without polymer residues are left out.
+pub fn chain_sequences<R: BufRead>(
+    reader: R,
+    format: &str,
+    modified: ModifiedResidues,
+    selection: &Selection,
+    options: SequenceOptions,
+) -> Result<Vec<ChainSequence>, PskitError> {
+    let deposited = Deposited::read(reader, format, modified, selection)?;
+    let modres = &deposited.modres;
+
+    let sequences = observed_chains(&deposited.pdb, &deposited.classifier)
+        .into_iter()
+        .map(|chain| {
+            let entity = match options.source {
+                SequenceSource::Atom => None,
+                SequenceSource::Seqres => deposited.entity(&chain.chain_id),
+            };
+            if let Some(entity) = entity {
+                let polymer_type = entity.polymer_type.or(chain.polymer_type);
+                return ChainSequence {
+                    sequence: entity
+                        .monomers
+                        .iter()
+                        .map(|name| one_letter(name, polymer_type, modres))
+                        .collect(),
+                    chain_id: chain.chain_id,
+                    polymer_type,
+                    source: SequenceSource::Seqres,
+                    residues: Vec::new(),
+                };
+            }
+
+            let residues = chain.residues;
+            let mut sequence = String::new();
+            for (i, key) in residues.iter().enumerate() {
+                if options.mark_gaps && i > 0 {
+                    // 编号跳跃视为缺失残基；插入码和编号回退不计
+                    let missing = key.number.serial - residues[i - 1].number.serial - 1;
+                    sequence.extend(std::iter::repeat_n(GAP, missing.max(0) as usize));
+                }
+                sequence.push(one_letter(&key.name, chain.polymer_type, modres));
+            }
+            ChainSequence {
+                chain_id: chain.chain_id,
+                polymer_type: chain.polymer_type,
+                source: SequenceSource::Atom,
+                sequence,
+                residues,
+            }
+        })
+        .collect();
+    Ok(sequences)
+}
+
+/// Unmodeled stretch of the deposited sequence, as 0-based inclusive sequence indices.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MissingRange {
+    pub start: usize,
+    pub end: usize,
+    pub sequence: String,
+}
+
+/// Consecutive observed residues that are not bonded (C–N, or O3'–P for nucleic acids).
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChainBreak {
+    pub before: ResidueKey,
+    pub after: ResidueKey,
+    pub distance: f64,
+}
+
+/// Observed residues of one chain placed on its deposited sequence.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChainMapping {
+    pub chain_id: String,
+    pub polymer_type: Option<PolymerType>,
+    /// Deposited sequence, or the observed one when the file declares none.
+    pub sequence: String,
+    pub source: SequenceSource,
+    /// Observed residue at each sequence position.
+    pub residues: Vec<Option<ResidueKey>>,
+    /// Observed residues that could not be placed on the sequence.
+    pub unaligned: Vec<ResidueKey>,
+    pub missing: Vec<MissingRange>,
+    pub breaks: Vec<ChainBreak>,
+}
+
+impl ChainMapping {
+    /// Sequence index of the observed residue with author number `number`.
+    pub fn index_of(&self, number: &ResidueNumber) -> Option<usize> {
+        self.residues
+            .iter()
+            .position(|r| r.as_ref().is_some_and(|r| &r.number == number))
+    }
+
+    /// Author number of every sequence position; missing residues are numbered on from the
+    /// nearest observed residue before them (after them at the N-terminus).
+    pub fn numbering(&self) -> Vec<ResidueNumber> {
+        let observed: Vec<(usize, isize)> = self
+            .residues
+            .iter()
+            .enumerate()
+            .filter_map(|(i, r)| r.as_ref().map(|r| (i, r.number.serial)))
+            .collect();
+        (0..self.residues.len())
+            .map(|i| match &self.residues[i] {
+                Some(r) => r.number.clone(),
+                None => {
+                    let serial = match observed.iter().rev().find(|(j, _)| *j < i) {
+                        Some(&(j, n)) => n + (i - j) as isize,
+                        None => match observed.first() {
+                            Some(&(j, n)) => n - (j - i) as isize,
+                            None => i as isize + 1,
+                        },
+                    };
+                    ResidueNumber::from(serial)
+                }
+            })
+            .collect()
+    }
+}
+
+/// Residue pair score for placing observed residues on the deposited sequence.
+fn pair_score(seqres: &str, observed: &str) -> i32 {
+    if seqres == observed {
+        2
+    } else if three_to_one(seqres) == three_to_one(observed) && three_to_one(seqres) != 'X' {
+        1
+    } else {
+        -1
+    }
+}
+
+/// Position on `seqres` of every observed residue (None if it had to be left out).
+///
+/// Global alignment with affine penalties on the deposited side: skipping a deposited stretch
+/// costs the same whatever its length and nothing at the termini, while leaving out an
+/// observed residue is expensive.
+///
+/// Only a band of diagonals is computed: every skipped deposited residue moves the path one
+/// diagonal up and every dropped observed residue one down, so with at most
+/// `n - m + SPARE_DROPS` drops the path stays within the band. Memory is one byte per band
+/// cell instead of the full table, which matters for long, nearly complete chains.
+fn align_observed(seqres: &[String], observed: &[&str]) -> Vec<Option<usize>> {
+    const OPEN: i32 = 2;
+    const DROP: i32 = 10;
+    const NEG: i32 = i32::MIN / 4;
+    const SPARE_DROPS: usize = 16;
+    let (n, m) = (observed.len(), seqres.len());
+    // 带内坐标 b = j - i + drops，取值 0..w
+    let drops = n.saturating_sub(m) + SPARE_DROPS;
+    let skips = m + drops - n;
+    let w = drops + skips + 1;
+    // 三个状态：0 对齐，1 跳过 SEQRES 残基，2 丢弃观测残基；只保留上一行的得分
+    let mut prev = [vec![NEG; w], vec![NEG; w], vec![NEG; w]];
+    let mut cur = prev.clone();
+    // 每格一个字节，三个状态的回溯来源各占两位
+    let mut from = vec![0u8; (n + 1) * w];
+    prev[0][drops] = 0;
+    for j in 1..=m.min(skips) {
+        prev[1][drops + j] = 0;
+        from[drops + j] = 1 << 2;
+    }
+    let best = |score: &[Vec<i32>; 3], b: usize| {
+        (0..3u8)
+            .map(|s| (score[s as usize][b], s))
+            .max_by_key(|(v, s)| (*v, std::cmp::Reverse(*s)))
+            .unwrap()
+    };
+    for i in 1..=n {
+        for state in &mut cur {
+            state.fill(NEG);
+        }
+        let open = if i == n { 0 } else { OPEN };
+        for j in i.saturating_sub(drops)..=(i + skips).min(m) {
+            let b = j + drops - i;
+            let mut source = 0u8;
+            if j > 0 {
+                let (v, s) = best(&prev, b);
+                cur[0][b] = v + pair_score(&seqres[j - 1], observed[i - 1]);
+                source |= s;
+
+                if b > 0 {
+                    let skips = [
+                        (cur[0][b - 1] - open, 0u8),
+                        (cur[1][b - 1], 1),
+                        (cur[2][b - 1] - open, 2),
+                    ];
+                    let (v, s) = skips
+                        .into_iter()
+                        .max_by_key(|(v, s)| (*v, std::cmp::Reverse(*s)))
+                        .unwrap();
+                    cur[1][b] = v;
+                    source |= s << 2;
+                }
+            }
+            if b + 1 < w {
+                let (v, s) = best(&prev, b + 1);
+                cur[2][b] = v - DROP;
+                source |= s << 4;
+            }
+            from[i * w + b] = source;
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+
+    let mut placed = vec![None; n];
+    let (mut i, mut j) = (n, m);
+    let mut state = best(&prev, skips).1;
+    while i > 0 || j > 0 {
+        let prev = (from[i * w + j + drops - i] >> (2 * state)) & 3;
+        match state {
+            0 => {
+                placed[i - 1] = Some(j - 1);
+                i -= 1;
+                j -= 1;
+            }
+            1 => j -= 1,
+            _ => i -= 1,
+        }
+        state = prev;
+    }
+    placed
+}
+
+/// Place the observed residues of every polymer chain on its deposited sequence
+/// (SEQRES / `_entity_poly_seq`), reporting unmodeled stretches and chain breaks.
+///
+/// mmCIF files carrying `_pdbx_poly_seq_scheme` are mapped by it; otherwise the observed
+/// residues are aligned to the sequence.
+///
+/// Chains without a deposited sequence are mapped onto their observed residues, so they only
+/// report breaks. Only the first model is used.
+pub fn map_sequences<R: BufRead>(
+    reader: R,
+    format: &str,
+    modified: ModifiedResidues,
+    selection: &Selection,
+) -> Result<Vec<ChainMapping>, PskitError> {
+    let deposited = Deposited::read(reader, format, modified, selection)?;
+    Ok(map_deposited(&deposited))
+}
+
+pub(crate) fn map_deposited(deposited: &Deposited) -> Vec<ChainMapping> {
+    let modres = &deposited.modres;
+
+    observed_chains(&deposited.pdb, &deposited.classifier)
+        .into_iter()
+        .map(|chain| {
+            let mut breaks = Vec::new();
+            for i in 1..chain.residues.len() {
+                if let (Some(tail), Some(head)) = (chain.links[i - 1].1, chain.links[i].0) {
+                    let distance = tail
+                        .iter()
+                        .zip(head)
+                        .map(|(a, b)| (a - b).powi(2))
+                        .sum::<f64>()
+                        .sqrt();
+                    if distance > BREAK_DISTANCE {
+                        breaks.push(ChainBreak {
+                            before: chain.residues[i - 1].clone(),
+                            after: chain.residues[i].clone(),
+                            distance,
+                        });
+                    }
+                }
+            }
+
+            let Some(entity) = deposited.entity(&chain.chain_id) else {
+                return ChainMapping {
+                    sequence: chain
+                        .residues
+                        .iter()
+                        .map(|r| one_letter(&r.name, chain.polymer_type, modres))
+                        .collect(),
+                    chain_id: chain.chain_id,
+                    polymer_type: chain.polymer_type,
+                    source: SequenceSource::Atom,
+                    residues: chain.residues.into_iter().map(Some).collect(),
+                    unaligned: Vec::new(),
+                    missing: Vec::new(),
+                    breaks,
+                };
+            };
+
+            let polymer_type = entity.polymer_type.or(chain.polymer_type);
+            let placed = match deposited.schemes.get(&chain.chain_id) {
+                Some(scheme) if scheme.len() == entity.monomers.len() => {
+                    let mut positions = HashMap::new();
+                    for (j, number) in scheme.iter().enumerate() {
+                        if let Some(number) = number {
+                            positions.entry(number).or_insert(j);
+                        }
+                    }
+                    chain
+                        .residues
+                        .iter()
+                        .map(|r| positions.get(&r.number).copied())
+                        .collect()
+                }
+                _ => {
+                    let names: Vec<&str> = chain.residues.iter().map(|r| r.name.as_str()).collect();
+                    align_observed(&entity.monomers, &names)
+                }
+            };
+            let mut residues = vec![None; entity.monomers.len()];
+            let mut unaligned = Vec::new();
+            for (key, position) in chain.residues.into_iter().zip(placed) {
+                match position {
+                    Some(j) => residues[j] = Some(key),
+                    None => unaligned.push(key),
+                }
+            }
+            let sequence: String = entity
+                .monomers
+                .iter()
+                .map(|name| one_letter(name, polymer_type, modres))
+                .collect();
+
+            let mut missing: Vec<MissingRange> = Vec::new();
+            for (j, c) in sequence.chars().enumerate() {
+                if residues[j].is_some() {
+                    continue;
+                }
+                match missing.last_mut() {
+                    Some(range) if range.end + 1 == j => {
+                        range.end = j;
+                        range.sequence.push(c);
+                    }
+                    _ => missing.push(MissingRange {
+                        start: j,
+                        end: j,
+                        sequence: c.to_string(),
+                    }),
+                }
+            }
+
+            ChainMapping {
+                chain_id: chain.chain_id,
+                polymer_type,
+                sequence,
+                source: SequenceSource::Seqres,
+                residues,
+                unaligned,
+                missing,
+                breaks,
+            }
+        })
+        .collect()
+}
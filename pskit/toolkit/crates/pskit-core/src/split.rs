@@ -1,13 +1,61 @@
-use crate::utils::{is_nucleic_residue, is_protein_residue, read_raw, write_raw};
-use pdbtbx::{Model, PDB};
-use std::collections::{BTreeSet, HashMap};
+use crate::chem::{ModifiedResidues, ResidueClassifier};
+use crate::error::PskitError;
+use crate::residue::{ResidueNumber, ResidueRange};
+use crate::selection::{read_selected, Selection};
+use crate::sequence::{map_deposited, Deposited, SequenceSource};
+use crate::utils::{write_raw, ReadOptions};
+use pdbtbx::{Chain, Model, PDB};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::io::BufRead;
 
+// 新建一个不含模型的 PDB，拷贝元数据（保留 header/对称/晶胞等信息）
+fn empty_like(pdb: &PDB) -> PDB {
+    let mut out = PDB::new();
+    out.identifier = pdb.identifier.clone();
+    out.scale = pdb.scale.clone();
+    out.origx = pdb.origx.clone();
+    out.unit_cell = pdb.unit_cell.clone();
+    out.symmetry = pdb.symmetry.clone();
+    // 复制 MTRIX
+    for m in pdb.mtrix() {
+        out.add_mtrix(m.clone());
+    }
+    // 复制 REMARK
+    for (ty, text) in pdb.remarks() {
+        let _ = out.add_remark(*ty, text.clone());
+    }
+    out
+}
+
+/// Keep only the atoms matched by `selection`, e.g. `chain A and not water`.
+pub fn extract_selection<R: BufRead>(
+    reader: R,
+    format: &str,
+    modified: ModifiedResidues,
+    selection: &Selection,
+) -> Result<Vec<u8>, PskitError> {
+    let (pdb, _classifier) = read_selected(
+        reader,
+        format,
+        ReadOptions::all_models(),
+        modified,
+        selection,
+    )?;
+    Ok(write_raw(pdb, format))
+}
+
 pub fn split_by_chain<R: BufRead>(
     reader: R,
     format: &str,
-) -> Result<HashMap<String, Vec<u8>>, String> {
-    let (pdb, _errors) = read_raw(reader, format)?;
+    selection: &Selection,
+) -> Result<HashMap<String, Vec<u8>>, PskitError> {
+    let (pdb, _classifier) = read_selected(
+        reader,
+        format,
+        ReadOptions::default(),
+        ModifiedResidues::default(),
+        selection,
+    )?;
 
     let mut chain_ids = BTreeSet::new();
 
@@ -20,21 +68,7 @@ pub fn split_by_chain<R: BufRead>(
     let mut chains = HashMap::with_capacity(chain_ids.len());
 
     for chain_id in chain_ids {
-        let mut out = PDB::new();
-        // 可选：拷贝元数据（保留 header/对称/晶胞等信息）
-        out.identifier = pdb.identifier.clone();
-        out.scale = pdb.scale.clone();
-        out.origx = pdb.origx.clone();
-        out.unit_cell = pdb.unit_cell.clone();
-        out.symmetry = pdb.symmetry.clone();
-        // 复制 MTRIX
-        for m in pdb.mtrix() {
-            out.add_mtrix(m.clone());
-        }
-        // 复制 REMARK
-        for (ty, text) in pdb.remarks() {
-            let _ = out.add_remark(*ty, text.clone());
-        }
+        let mut out = empty_like(&pdb);
 
         for model in pdb.models() {
             let mut new_model = Model::new(model.serial_number());
@@ -54,43 +88,69 @@ pub fn split_by_chain<R: BufRead>(
     Ok(chains)
 }
 
-pub fn split_complex<R: BufRead>(
+/// Write every model of a multi-model file (NMR ensemble, MD snapshots) to its own file,
+/// keyed by model serial number.
+pub fn split_by_model<R: BufRead>(
     reader: R,
     format: &str,
-) -> Result<HashMap<String, Vec<u8>>, String> {
-    let (pdb, _errors) = read_raw(reader, format)?;
-
-    let mut protein_has = false;
-    let mut nucleic_has = false;
-    'scan: for chain in pdb.chains() {
-        for residue in chain.residues() {
-            if let Some(name) = residue.name() {
-                protein_has |= is_protein_residue(name);
-                nucleic_has |= is_nucleic_residue(name);
-                if protein_has && nucleic_has {
-                    break 'scan;
-                }
-            }
-        }
-    }
+    selection: &Selection,
+) -> Result<HashMap<String, Vec<u8>>, PskitError> {
+    let (pdb, _classifier) = read_selected(
+        reader,
+        format,
+        ReadOptions::all_models(),
+        ModifiedResidues::default(),
+        selection,
+    )?;
 
-    if !(protein_has && nucleic_has) {
-        return Err("Not a protein-nucleic acid complex.".to_string());
+    let mut models = HashMap::with_capacity(pdb.model_count());
+    for model in pdb.models() {
+        let mut out = empty_like(&pdb);
+        out.add_model(model.clone());
+        models.insert(model.serial_number().to_string(), write_raw(out, format));
     }
 
-    let mut parts: HashMap<String, Vec<u8>> = HashMap::with_capacity(2);
+    Ok(models)
+}
 
+/// Copies of `pdb` holding only its protein and only its nucleic-acid residues.
+pub(crate) fn complex_parts(pdb: &PDB, classifier: &ResidueClassifier) -> (PDB, PDB) {
     let mut protein_pdb = pdb.clone();
     let mut nucleic_pdb = pdb.clone();
 
-    protein_pdb.remove_residues_by(|residue| match residue.name() {
-        Some(name) => !is_protein_residue(name),
-        None => true,
-    });
-    nucleic_pdb.remove_residues_by(|residue| match residue.name() {
-        Some(name) => !is_nucleic_residue(name),
-        None => true,
-    });
+    for chain in protein_pdb.chains_mut() {
+        let chain_id = chain.id().to_string();
+        chain.remove_residues_by(|residue| match residue.name() {
+            Some(name) => !classifier.is_protein(&chain_id, name),
+            None => true,
+        });
+    }
+    for chain in nucleic_pdb.chains_mut() {
+        let chain_id = chain.id().to_string();
+        chain.remove_residues_by(|residue| match residue.name() {
+            Some(name) => !classifier.is_nucleic(&chain_id, name),
+            None => true,
+        });
+    }
+
+    (protein_pdb, nucleic_pdb)
+}
+
+pub fn split_complex<R: BufRead>(
+    reader: R,
+    format: &str,
+    modified: ModifiedResidues,
+    selection: &Selection,
+) -> Result<HashMap<String, Vec<u8>>, PskitError> {
+    let (pdb, classifier) =
+        read_selected(reader, format, ReadOptions::default(), modified, selection)?;
+
+    if !classifier.is_complex(&pdb) {
+        return Err(PskitError::NotComplex);
+    }
+
+    let mut parts: HashMap<String, Vec<u8>> = HashMap::with_capacity(2);
+    let (protein_pdb, nucleic_pdb) = complex_parts(&pdb, &classifier);
 
     let protein_bytes = write_raw(protein_pdb, format);
     let nucleic_bytes = write_raw(nucleic_pdb, format);
@@ -101,75 +161,136 @@ pub fn split_complex<R: BufRead>(
     Ok(parts)
 }
 
+/// Cut residues `range` (default: the whole chain) out of `chain_id`. Returns the fragment and
+/// the numbers of its first and last modeled residues.
+///
+/// Chains with a deposited sequence are checked against it, so ends falling on unmodeled
+/// residues (numbered as in [`ChainMapping::numbering`](crate::sequence::ChainMapping::numbering)) are valid and trimmed to the
+/// modeled residues inside the range.
 pub fn extract_fragment<R: BufRead>(
     reader: R,
     chain_id: String,
-    requested_start: Option<isize>,
-    requested_end: Option<isize>,
+    range: ResidueRange,
     format: &str,
-) -> Result<(Vec<u8>, isize, isize), String> {
-    let (mut pdb, _errors) = read_raw(reader, format)?;
+    selection: &Selection,
+) -> Result<(Vec<u8>, ResidueNumber, ResidueNumber), PskitError> {
+    let deposited = Deposited::read(reader, format, ModifiedResidues::default(), selection)?;
+    let mapping = map_deposited(&deposited)
+        .into_iter()
+        .find(|m| m.chain_id == chain_id && m.source == SequenceSource::Seqres);
+    let mut pdb = deposited.pdb;
 
     let chain_ids: Vec<_> = pdb.chains().map(|chain| chain.id()).collect();
     if !chain_ids.contains(&chain_id.as_str()) {
-        return Err(format!(
-            "Chain {chain_id} not exists. Valid chain IDs are: {:?}",
-            chain_ids
-        ));
+        return Err(PskitError::ChainNotFound {
+            chain_id,
+            valid: chain_ids.into_iter().map(String::from).collect(),
+        });
     }
 
-    let mut actual_start = 0;
-    let mut actual_end = 0;
+    let mut actual_start = ResidueNumber::default();
+    let mut actual_end = ResidueNumber::default();
 
     for chain in pdb.chains_mut() {
         if chain.id() != chain_id {
             chain.remove_residues_by(|_| true);
             continue;
         }
-        let mut fragment_start = if let Some(residue) = chain.residues().next() {
-            residue.id().0
-        } else {
-            0
-        };
-        let mut frgment_end = if let Some(residue) = chain.residues().next_back() {
-            residue.id().0
-        } else {
-            0
-        };
 
-        match (requested_start, requested_end) {
-            (Some(start), Some(end)) => {
-                if start < fragment_start || end > frgment_end {
-                    return Err(format!("Invalid range. For Chain {chain_id}, please enter values between {fragment_start} and {frgment_end}."));
-                } else {
-                    fragment_start = start;
-                    frgment_end = end;
-                }
+        if let Some(mapping) = &mapping {
+            // 按沉积序列的位置截取，未建模的残基也占位置
+            let numbering = mapping.numbering();
+            let invalid = || PskitError::InvalidRange {
+                chain_id: chain_id.clone(),
+                min: numbering.first().cloned().unwrap_or_default(),
+                max: numbering.last().cloned().unwrap_or_default(),
+            };
+            let index_of = |number: &Option<ResidueNumber>, default: usize| match number {
+                Some(n) => numbering.iter().position(|m| m == n).ok_or_else(invalid),
+                None => Ok(default),
+            };
+            let start = index_of(&range.start, 0)?;
+            let end = index_of(&range.end, numbering.len().saturating_sub(1))?;
+            if numbering.is_empty() || start > end {
+                return Err(invalid());
             }
-            (Some(start), None) => {
-                if start < fragment_start {
-                    return Err(format!("Invalid range. For Chain {chain_id}, please enter values between {fragment_start} and {frgment_end}."));
-                } else {
-                    fragment_start = start;
-                }
-            }
-            (None, Some(end)) => {
-                if end > frgment_end {
-                    return Err(format!("Invalid range. For Chain {chain_id}, please enter values between {fragment_start} and {frgment_end}."));
-                } else {
-                    frgment_end = end;
-                }
-            }
-            (None, None) => {}
+            let keep: HashSet<&ResidueNumber> = mapping.residues[start..=end]
+                .iter()
+                .flatten()
+                .map(|key| &key.number)
+                .collect();
+            chain.remove_residues_by(|residue| !keep.contains(&ResidueNumber::of(residue)));
+        } else {
+            cut_by_position(chain, &chain_id, &range)?;
         }
 
-        actual_start = fragment_start;
-        actual_end = frgment_end;
-
-        chain.remove_residues_by(|residue| {
-            residue.id().0 < fragment_start || residue.id().0 > frgment_end
-        });
+        // 区间落在未建模的缺口里时没有残基可取；返回实际保留的首尾残基
+        let (Some(first), Some(last)) = (chain.residues().next(), chain.residues().next_back())
+        else {
+            let bound = |n: &Option<ResidueNumber>| n.as_ref().map(|n| n.to_string());
+            return Err(PskitError::InvalidArgument(format!(
+                "Residues {} to {} of chain {chain_id} are not modeled",
+                bound(&range.start).unwrap_or_else(|| "start".into()),
+                bound(&range.end).unwrap_or_else(|| "end".into()),
+            )));
+        };
+        actual_start = ResidueNumber::of(first);
+        actual_end = ResidueNumber::of(last);
     }
 
     Ok((write_raw(pdb, format), actual_start, actual_end))
 }
+
+/// Cut `range` out of a chain by position in the chain, for chains without a deposited
+/// sequence. Ends missing from the chain are moved to the nearest residue inside the range.
+fn cut_by_position(
+    chain: &mut Chain,
+    chain_id: &str,
+    range: &ResidueRange,
+) -> Result<(), PskitError> {
+    let numbers: Vec<ResidueNumber> = chain.residues().map(ResidueNumber::of).collect();
+    let chain_start = numbers.first().cloned().unwrap_or_default();
+    let chain_end = numbers.last().cloned().unwrap_or_default();
+    let fragment_start = range.start.clone().unwrap_or_else(|| chain_start.clone());
+    let fragment_end = range.end.clone().unwrap_or_else(|| chain_end.clone());
+    let invalid = || PskitError::InvalidRange {
+        chain_id: chain_id.to_string(),
+        min: chain_start.clone(),
+        max: chain_end.clone(),
+    };
+
+    // 按链内位置取片段：编号可能乱序（如 IMGT 的 112B、112A、112），不能按编号比较筛选。
+    // 端点不在链中时取缺口另一侧最近的残基。
+    let start_index = match numbers.iter().position(|n| *n == fragment_start) {
+        Some(i) => i,
+        None if fragment_start < chain_start => return Err(invalid()),
+        None => numbers
+            .iter()
+            .position(|n| *n > fragment_start)
+            .ok_or_else(invalid)?,
+    };
+    let end_index = match numbers.iter().position(|n| *n == fragment_end) {
+        Some(i) => i,
+        None if fragment_end > chain_end => return Err(invalid()),
+        None => numbers
+            .iter()
+            .rposition(|n| *n < fragment_end)
+            .ok_or_else(invalid)?,
+    };
+    let both_modeled = numbers.contains(&fragment_start) && numbers.contains(&fragment_end);
+    let reversed = if both_modeled {
+        start_index > end_index
+    } else {
+        fragment_start > fragment_end
+    };
+    if numbers.is_empty() || reversed {
+        return Err(invalid());
+    }
+
+    let index = std::cell::Cell::new(0);
+    chain.remove_residues_by(|_| {
+        let i = index.replace(index.get() + 1);
+        i < start_index || i > end_index
+    });
+    Ok(())
+}
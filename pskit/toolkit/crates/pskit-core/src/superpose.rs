@@ -0,0 +1,363 @@
+use pdbtbx::PDB;
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::str::FromStr;
+
+use crate::align::{align, AlignMode, AlignOptions};
+use crate::chem::{ModifiedResidues, ResidueClassifier};
+use crate::error::PskitError;
+use crate::residue::ResidueKey;
+use crate::selection::{read_selected, Selection};
+use crate::utils::{three_to_one, write_raw, ReadOptions};
+
+/// How residues of the mobile structure are paired with residues of the target.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResidueMatch {
+    /// Same chain id, residue number and insertion code.
+    #[default]
+    ResidueId,
+    /// Global sequence alignment of the n-th selected chain of each structure.
+    Alignment,
+}
+
+impl FromStr for ResidueMatch {
+    type Err = PskitError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "id" | "residue_id" | "resid" => Ok(ResidueMatch::ResidueId),
+            "alignment" | "align" | "sequence" => Ok(ResidueMatch::Alignment),
+            _ => Err(PskitError::InvalidArgument(format!(
+                "Unknown residue matching {s:?}, expected id or alignment"
+            ))),
+        }
+    }
+}
+
+/// Deviation of one matched residue pair after fitting.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResidueDeviation {
+    pub mobile: ResidueKey,
+    pub target: ResidueKey,
+    /// Matched atoms of the pair.
+    pub atoms: usize,
+    pub rmsd: f64,
+}
+
+/// Rigid transform taking mobile coordinates onto the target, `y = R x + t`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Superposition {
+    pub rotation: [[f64; 3]; 3],
+    pub translation: [f64; 3],
+    pub matched_atoms: usize,
+    /// RMSD of the matched atoms in the input coordinates.
+    pub rmsd_before: f64,
+    pub rmsd_after: f64,
+    pub residues: Vec<ResidueDeviation>,
+}
+
+impl Superposition {
+    pub fn apply(&self, p: [f64; 3]) -> [f64; 3] {
+        let r = &self.rotation;
+        std::array::from_fn(|i| {
+            r[i][0] * p[0] + r[i][1] * p[1] + r[i][2] * p[2] + self.translation[i]
+        })
+    }
+}
+
+fn centroid(points: &[[f64; 3]]) -> [f64; 3] {
+    let n = points.len().max(1) as f64;
+    let mut c = [0.0; 3];
+    for p in points {
+        for k in 0..3 {
+            c[k] += p[k] / n;
+        }
+    }
+    c
+}
+
+/// Eigenvector of the largest eigenvalue of a symmetric 4×4 matrix (cyclic Jacobi).
+fn max_eigenvector(mut a: [[f64; 4]; 4]) -> [f64; 4] {
+    let mut v = [[0.0; 4]; 4];
+    for (i, row) in v.iter_mut().enumerate() {
+        row[i] = 1.0;
+    }
+    for _ in 0..64 {
+        let off: f64 = (0..4)
+            .flat_map(|p| (p + 1..4).map(move |q| (p, q)))
+            .map(|(p, q)| a[p][q] * a[p][q])
+            .sum();
+        if off < 1e-22 {
+            break;
+        }
+        for p in 0..4 {
+            for q in p + 1..4 {
+                if a[p][q].abs() < 1e-300 {
+                    continue;
+                }
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for row in a.iter_mut() {
+                    let (akp, akq) = (row[p], row[q]);
+                    row[p] = c * akp - s * akq;
+                    row[q] = s * akp + c * akq;
+                }
+                let (ap, aq) = (a[p], a[q]);
+                a[p] = std::array::from_fn(|k| c * ap[k] - s * aq[k]);
+                a[q] = std::array::from_fn(|k| s * ap[k] + c * aq[k]);
+                for row in v.iter_mut() {
+                    let (vkp, vkq) = (row[p], row[q]);
+                    row[p] = c * vkp - s * vkq;
+                    row[q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+    let best = (0..4)
+        .max_by(|&i, &j| a[i][i].total_cmp(&a[j][j]))
+        .unwrap_or(0);
+    std::array::from_fn(|k| v[k][best])
+}
+
+/// Least-squares rotation and translation taking `mobile` onto `target` (Kabsch), solved as
+/// Horn's quaternion eigenproblem so no SVD is needed.
+pub fn kabsch(mobile: &[[f64; 3]], target: &[[f64; 3]]) -> ([[f64; 3]; 3], [f64; 3]) {
+    let cx = centroid(mobile);
+    let cy = centroid(target);
+    let mut s = [[0.0; 3]; 3];
+    for (x, y) in mobile.iter().zip(target) {
+        for i in 0..3 {
+            for j in 0..3 {
+                s[i][j] += (x[i] - cx[i]) * (y[j] - cy[j]);
+            }
+        }
+    }
+    let [[sxx, sxy, sxz], [syx, syy, syz], [szx, szy, szz]] = s;
+    let n = [
+        [sxx + syy + szz, syz - szy, szx - sxz, sxy - syx],
+        [syz - szy, sxx - syy - szz, sxy + syx, szx + sxz],
+        [szx - sxz, sxy + syx, -sxx + syy - szz, syz + szy],
+        [sxy - syx, szx + sxz, syz + szy, -sxx - syy + szz],
+    ];
+    let [q0, q1, q2, q3] = max_eigenvector(n);
+    let r = [
+        [
+            q0 * q0 + q1 * q1 - q2 * q2 - q3 * q3,
+            2.0 * (q1 * q2 - q0 * q3),
+            2.0 * (q1 * q3 + q0 * q2),
+        ],
+        [
+            2.0 * (q1 * q2 + q0 * q3),
+            q0 * q0 - q1 * q1 + q2 * q2 - q3 * q3,
+            2.0 * (q2 * q3 - q0 * q1),
+        ],
+        [
+            2.0 * (q1 * q3 - q0 * q2),
+            2.0 * (q2 * q3 + q0 * q1),
+            q0 * q0 - q1 * q1 - q2 * q2 + q3 * q3,
+        ],
+    ];
+    let t = std::array::from_fn(|i| cy[i] - (r[i][0] * cx[0] + r[i][1] * cx[1] + r[i][2] * cx[2]));
+    (r, t)
+}
+
+pub fn rmsd(a: &[[f64; 3]], b: &[[f64; 3]]) -> f64 {
+    let n = a.len().max(1) as f64;
+    let sum: f64 = a
+        .iter()
+        .zip(b)
+        .map(|(p, q)| (0..3).map(|k| (p[k] - q[k]).powi(2)).sum::<f64>())
+        .sum();
+    (sum / n).sqrt()
+}
+
+/// Selected atoms of the first model, grouped by residue and chain in structure order.
+pub(crate) struct SelectedResidue {
+    pub(crate) key: ResidueKey,
+    pub(crate) atoms: Vec<(String, [f64; 3])>,
+}
+
+impl SelectedResidue {
+    /// CA for amino acids, C3' for nucleotides, otherwise the centroid of the selected atoms.
+    pub(crate) fn point(&self) -> [f64; 3] {
+        let named = ["CA", "C3'"]
+            .iter()
+            .find_map(|name| self.atoms.iter().find(|(n, _)| n == name));
+        match named {
+            Some((_, p)) => *p,
+            None => {
+                let points: Vec<[f64; 3]> = self.atoms.iter().map(|(_, p)| *p).collect();
+                centroid(&points)
+            }
+        }
+    }
+}
+
+pub(crate) fn selected_chains(
+    pdb: &PDB,
+    classifier: &ResidueClassifier,
+    selection: &Selection,
+) -> Result<Vec<Vec<SelectedResidue>>, PskitError> {
+    selection.check_chains(pdb)?;
+    let Some(model) = pdb.models().next() else {
+        return Ok(Vec::new());
+    };
+    let mask = selection.mask(model, classifier);
+    let mut index = 0;
+    let mut chains = Vec::new();
+    for chain in model.chains() {
+        let mut residues: Vec<SelectedResidue> = Vec::new();
+        for residue in chain.residues() {
+            let mut atoms = Vec::new();
+            for conformer in residue.conformers() {
+                for atom in conformer.atoms() {
+                    // 只取第一个构象中的同名原子
+                    if mask[index] && !atoms.iter().any(|(name, _)| name == atom.name()) {
+                        atoms.push((atom.name().to_string(), atom.pos().into()));
+                    }
+                    index += 1;
+                }
+            }
+            if !atoms.is_empty() {
+                residues.push(SelectedResidue {
+                    key: ResidueKey::new(chain.id(), residue),
+                    atoms,
+                });
+            }
+        }
+        if !residues.is_empty() {
+            chains.push(residues);
+        }
+    }
+    Ok(chains)
+}
+
+pub(crate) type ResiduePairs<'a> = Vec<(&'a SelectedResidue, &'a SelectedResidue)>;
+
+pub(crate) fn match_residues<'a>(
+    mobile: &'a [Vec<SelectedResidue>],
+    target: &'a [Vec<SelectedResidue>],
+    matching: ResidueMatch,
+) -> ResiduePairs<'a> {
+    match matching {
+        ResidueMatch::ResidueId => {
+            let by_id: HashMap<_, _> = target
+                .iter()
+                .flatten()
+                .map(|r| ((r.key.chain_id.as_str(), &r.key.number), r))
+                .collect();
+            mobile
+                .iter()
+                .flatten()
+                .filter_map(|r| {
+                    by_id
+                        .get(&(r.key.chain_id.as_str(), &r.key.number))
+                        .map(|t| (r, *t))
+                })
+                .collect()
+        }
+        ResidueMatch::Alignment => mobile
+            .iter()
+            .zip(target)
+            .flat_map(|(a, b)| {
+                let sa: String = a.iter().map(|r| three_to_one(&r.key.name)).collect();
+                let sb: String = b.iter().map(|r| three_to_one(&r.key.name)).collect();
+                let options = AlignOptions {
+                    mode: AlignMode::SemiGlobal,
+                    ..Default::default()
+                };
+                align(&sa, &sb, &options)
+                    .pairs
+                    .into_iter()
+                    .map(|(i, j)| (&a[i], &b[j]))
+                    .collect::<Vec<_>>()
+            })
+            .collect(),
+    }
+}
+
+/// Superpose `mobile` onto `target` on matched atoms.
+///
+/// Residues are paired by `matching`; within a pair, selected atoms with the same name are
+/// matched. The fit uses the first model of both files. Returns the fit and the whole mobile
+/// structure (all models) transformed onto the target, written in `mobile_format`.
+pub fn superpose<R1: BufRead, R2: BufRead>(
+    mobile: R1,
+    mobile_format: &str,
+    mobile_selection: &Selection,
+    target: R2,
+    target_format: &str,
+    target_selection: &Selection,
+    matching: ResidueMatch,
+) -> Result<(Superposition, Vec<u8>), PskitError> {
+    let (mut mobile_pdb, mobile_classifier) = read_selected(
+        mobile,
+        mobile_format,
+        ReadOptions::all_models(),
+        ModifiedResidues::default(),
+        &Selection::all(),
+    )?;
+    let (target_pdb, target_classifier) = read_selected(
+        target,
+        target_format,
+        ReadOptions::default(),
+        ModifiedResidues::default(),
+        &Selection::all(),
+    )?;
+    let mobile_chains = selected_chains(&mobile_pdb, &mobile_classifier, mobile_selection)?;
+    let target_chains = selected_chains(&target_pdb, &target_classifier, target_selection)?;
+
+    // 按残基配对后再按原子名配对
+    let mut x: Vec<[f64; 3]> = Vec::new();
+    let mut y: Vec<[f64; 3]> = Vec::new();
+    let mut spans = Vec::new();
+    for (a, b) in match_residues(&mobile_chains, &target_chains, matching) {
+        let start = x.len();
+        for (name, p) in &a.atoms {
+            if let Some((_, q)) = b.atoms.iter().find(|(other, _)| other == name) {
+                x.push(*p);
+                y.push(*q);
+            }
+        }
+        if x.len() > start {
+            spans.push((a, b, start..x.len()));
+        }
+    }
+    if x.len() < 3 {
+        return Err(PskitError::InvalidArgument(format!(
+            "Superposition needs at least 3 matched atoms, found {}",
+            x.len()
+        )));
+    }
+
+    let (rotation, translation) = kabsch(&x, &y);
+    let mut fit = Superposition {
+        rotation,
+        translation,
+        matched_atoms: x.len(),
+        rmsd_before: rmsd(&x, &y),
+        rmsd_after: 0.0,
+        residues: Vec::new(),
+    };
+    let moved: Vec<[f64; 3]> = x.iter().map(|p| fit.apply(*p)).collect();
+    fit.rmsd_after = rmsd(&moved, &y);
+    fit.residues = spans
+        .into_iter()
+        .map(|(a, b, range)| ResidueDeviation {
+            mobile: a.key.clone(),
+            target: b.key.clone(),
+            atoms: range.len(),
+            rmsd: rmsd(&moved[range.clone()], &y[range]),
+        })
+        .collect();
+
+    for atom in mobile_pdb.atoms_mut() {
+        let (px, py, pz) = atom.pos();
+        let [qx, qy, qz] = fit.apply([px, py, pz]);
+        let _ = atom.set_pos((qx, qy, qz));
+    }
+
+    Ok((fit, write_raw(mobile_pdb, mobile_format)))
+}
@@ -1,8 +1,10 @@
+use crate::chem::standard_residue;
+use crate::error::{ParseIssue, PskitError};
 use pdbtbx::{Format, PDBError, PDB};
 use std::io::{BufRead, BufReader, BufWriter};
 
 pub fn three_to_one(three: &str) -> char {
-    match three {
+    match standard_residue(three).unwrap_or(three) {
         "ALA" => 'A',
         "ARG" => 'R',
         "ASN" => 'N',
@@ -23,6 +25,11 @@ pub fn three_to_one(three: &str) -> char {
         "TRP" => 'W',
         "TYR" => 'Y',
         "VAL" => 'V',
+        "A" | "DA" => 'A',
+        "C" | "DC" => 'C',
+        "G" | "DG" => 'G',
+        "U" | "DU" => 'U',
+        "DT" => 'T',
         "UNK" => 'X',
         _ => 'X',
     }
@@ -42,7 +49,67 @@ pub fn is_nucleic_residue(name: &str) -> bool {
     NUCLEIC_RESIDUES.contains(&name)
 }
 
-pub fn read_raw<R: BufRead>(reader: R, format: &str) -> Result<(PDB, Vec<PDBError>), String> {
+/// An I/O failure while reading the input; content that is not UTF-8 text counts as a parse
+/// failure.
+pub(crate) fn read_error(e: std::io::Error) -> PskitError {
+    if e.kind() != std::io::ErrorKind::InvalidData {
+        return PskitError::Io(e.to_string());
+    }
+    PskitError::Parse(vec![ParseIssue {
+        level: "BreakingError".to_string(),
+        message: "Could not read input".to_string(),
+        detail: e.to_string(),
+        line: None,
+        context: String::new(),
+    }])
+}
+
+/// Read the whole input as text, for callers that also need records pdbtbx does not keep.
+pub fn read_text<R: BufRead>(mut reader: R) -> Result<String, PskitError> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text).map_err(read_error)?;
+    Ok(text)
+}
+
+/// Structure format of a file: from the extension of `name`, otherwise "cif" when the content
+/// starts with a `data_` block and "pdb" if not.
+pub fn detect_format(name: &str, data: &[u8]) -> &'static str {
+    let lower = name.to_lowercase();
+    if lower.ends_with(".cif") || lower.ends_with(".mmcif") {
+        return "cif";
+    }
+    if lower.ends_with(".pdb") || lower.ends_with(".ent") {
+        return "pdb";
+    }
+    let text = String::from_utf8_lossy(&data[..data.len().min(4096)]);
+    match text.lines().map(str::trim).find(|l| !l.is_empty()) {
+        Some(line) if line.starts_with("data_") => "cif",
+        _ => "pdb",
+    }
+}
+
+/// How much of the input `read_raw_with` keeps.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReadOptions {
+    /// Keep every model (NMR ensembles, MD snapshots) instead of only the first one.
+    pub all_models: bool,
+}
+
+impl ReadOptions {
+    pub fn all_models() -> Self {
+        ReadOptions { all_models: true }
+    }
+}
+
+pub fn read_raw<R: BufRead>(reader: R, format: &str) -> Result<(PDB, Vec<PDBError>), PskitError> {
+    read_raw_with(reader, format, ReadOptions::default())
+}
+
+pub fn read_raw_with<R: BufRead>(
+    reader: R,
+    format: &str,
+    options: ReadOptions,
+) -> Result<(PDB, Vec<PDBError>), PskitError> {
     let buf = BufReader::new(reader);
     let format = match format.to_uppercase().as_str() {
         "PDB" => Format::Pdb,
@@ -52,14 +119,18 @@ pub fn read_raw<R: BufRead>(reader: R, format: &str) -> Result<(PDB, Vec<PDBErro
     let (pdb, errors) = pdbtbx::ReadOptions::default()
         .set_format(format)
         .set_level(pdbtbx::StrictnessLevel::Loose)
-        .set_only_first_model(true)
+        .set_only_first_model(!options.all_models)
         .read_raw(buf)
         .map_err(|errs| {
-            let true_errs: Vec<_> = errs
-                .into_iter()
+            let mut true_errs: Vec<_> = errs
+                .iter()
                 .filter(|err| err.fails(pdbtbx::StrictnessLevel::Loose))
+                .map(ParseIssue::from)
                 .collect();
-            format!("File parsing failed due to: {:?}", true_errs[0])
+            if true_errs.is_empty() {
+                true_errs = errs.iter().map(ParseIssue::from).collect();
+            }
+            PskitError::Parse(true_errs)
         })?;
 
     Ok((pdb, errors))
@@ -1,8 +1,33 @@
 // use pskit_core::annotate;
-use pskit_core::annotate::compute_binding_pairs;
-use pskit_core::contact::d2_map;
-use pskit_core::split::extract_fragment;
-use pskit_core::split::split_complex;
+use pskit_core::align::{align, AlignMode, AlignOptions, ScoringMatrix};
+use pskit_core::annotate::{
+    annotate_interface, binding_labels, compute_binding_frequency, compute_binding_pairs,
+    InterfaceSide, LabelTarget, LabelValue, NucleicRegion,
+};
+use pskit_core::basepair::{compute_base_pairs, BasePairCriteria, PairClass};
+use pskit_core::batch::{run_batch, BatchOptions, BatchSource};
+use pskit_core::chem::{EntityPoly, ModifiedResidues, PolymerType};
+use pskit_core::contact::{
+    contact_list, d2_map, d_map, d_map_between, d_map_gapped, d_map_models, insert_gaps, knn_map,
+    ModelAggregate, Representative,
+};
+use pskit_core::dssp::{compute_dssp, write_dssp, write_dssp_mmcif, SecondaryStructure};
+use pskit_core::export::{
+    write_binding_pairs_csv, write_dot_bracket, write_fasta, write_label_fasta, write_label_json,
+    write_map, write_sequence_mapping_json, MapFormat,
+};
+use pskit_core::graph::{residue_graph, EdgeRule, GraphOptions};
+use pskit_core::interaction::{classify_interactions, InteractionCriteria, InteractionType};
+use pskit_core::quality::compare_structures;
+use pskit_core::sasa::{buried_surface_area, compute_sasa, SasaOptions};
+use pskit_core::sequence::{
+    chain_sequences, map_sequences, ChainSequence, SequenceOptions, SequenceSource,
+};
+use pskit_core::split::{extract_fragment, extract_selection};
+use pskit_core::split::{split_by_model, split_complex};
+use pskit_core::superpose::{superpose, ResidueMatch};
+use pskit_core::utils::{detect_format, read_raw, three_to_one, write_raw};
+use pskit_core::{PskitError, ResidueKey, ResidueNumber, ResidueRange, Selection};
 
 #[cfg(test)]
 mod tests {
@@ -12,26 +37,46 @@ mod tests {
     #[test]
     fn test_binding_pairs() {
         use std::fs::File;
+        let all = Selection::all();
         let pdb_path = "./test_pdbs/7U5E.cif";
         let reader = BufReader::new(File::open(pdb_path).expect("open file"));
-        let pairs = compute_binding_pairs(reader, 3.5, "cif").expect("compute_pairs");
+        let pairs = compute_binding_pairs(reader, 3.5, "cif", ModifiedResidues::Polymer, &all)
+            .expect("compute_pairs");
         println!("{pairs:?}");
+
+        let csv = String::from_utf8(write_binding_pairs_csv(&pairs)).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+        assert!(lines[0].starts_with("prot_chain,prot_resi,prot_resn,na_chain,na_resi,na_resn"));
+        assert_eq!(lines.len(), pairs.len() + 1);
+        let first = &pairs[0];
+        assert!(lines[1].starts_with(&format!(
+            "{},{},{},",
+            first.protein.chain_id, first.protein.number, first.protein.name
+        )));
     }
     #[test]
     fn test_extract_fragment() {
         use std::fs::File;
+        let all = Selection::all();
         let pdb_path = "./test_pdbs/7U5E.cif";
         let reader = BufReader::new(File::open(pdb_path).expect("open file"));
-        let (frag_bytes, start, end) =
-            extract_fragment(reader, "A".to_string(), None, None, "cif").unwrap();
+        let (frag_bytes, start, end) = extract_fragment(
+            reader,
+            "A".to_string(),
+            ResidueRange::default(),
+            "cif",
+            &all,
+        )
+        .unwrap();
         println!("{:?}\n{start}-{end}", std::str::from_utf8(&frag_bytes));
     }
     #[test]
     fn test_split() {
         use std::fs::File;
+        let all = Selection::all();
         let pdb_path = "./test_pdbs/7U5E.cif";
         let reader = BufReader::new(File::open(pdb_path).expect("open file"));
-        let parts = split_complex(reader, "cif").unwrap();
+        let parts = split_complex(reader, "cif", ModifiedResidues::Polymer, &all).unwrap();
         for part in parts {
             println!(
                 "========{}========\n{:?}",
@@ -44,9 +89,1315 @@ mod tests {
     #[test]
     fn test_pdbtbx() {
         use std::fs::File;
+        let all = Selection::all();
         let pdb_path = "./test_pdbs/8W2S.cif";
         let reader = BufReader::new(File::open(pdb_path).unwrap());
-        let d2_map = d2_map(reader, Some("A".into()), "cif");
+        let d2_map = d2_map(reader, Some("A".into()), Representative::Auto, "cif", &all);
         println!("{:?}", d2_map);
     }
+
+    #[test]
+    fn test_error_kinds() {
+        use std::fs::File;
+        let all = Selection::all();
+        let pdb_path = "./test_pdbs/7U5E.cif";
+        let reader = BufReader::new(File::open(pdb_path).expect("open file"));
+        let err = extract_fragment(
+            reader,
+            "Z".to_string(),
+            ResidueRange::default(),
+            "cif",
+            &all,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), "chain_not_found");
+        if let PskitError::ChainNotFound { valid, .. } = &err {
+            assert!(valid.contains(&"A".to_string()));
+        }
+
+        let garbage =
+            "ATOM      1  N   MET A   1      xx.xxx  24.447   2.594  1.00 11.79           N\n";
+        let err = d2_map(
+            BufReader::new(garbage.as_bytes()),
+            None,
+            Representative::Auto,
+            "pdb",
+            &all,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), "parse");
+    }
+
+    #[test]
+    fn test_insertion_codes() {
+        let all = Selection::all();
+        let kabat = "\
+ATOM      1  CA  SER H  52      10.000  10.000  10.000  1.00  0.00           C
+ATOM      2  CA  TYR H  52A     13.800  10.000  10.000  1.00  0.00           C
+ATOM      3  CA  GLY H  52B     17.600  10.000  10.000  1.00  0.00           C
+ATOM      4  CA  ASP H  53      21.400  10.000  10.000  1.00  0.00           C
+END
+";
+        let range: ResidueRange = "52A..53".parse().unwrap();
+        let (bytes, start, end) = extract_fragment(
+            BufReader::new(kabat.as_bytes()),
+            "H".into(),
+            range,
+            "pdb",
+            &all,
+        )
+        .unwrap();
+        assert_eq!(
+            (start.to_string(), end.to_string()),
+            ("52A".into(), "53".into())
+        );
+        let (frag, _) = read_raw(BufReader::new(&bytes[..]), "pdb").unwrap();
+        let keys: Vec<_> = frag
+            .chains()
+            .flat_map(|c| c.residues().map(|r| ResidueKey::new(c.id(), r).to_string()))
+            .collect();
+        assert_eq!(keys, ["H-52A-TYR", "H-52B-GLY", "H-53-ASP"]);
+
+        // IMGT 编号中 CDR3 的插入码倒序排列，片段按链内位置截取
+        let imgt = "\
+ATOM      1  CA  ALA H 111      10.000  10.000  10.000  1.00  0.00           C
+ATOM      2  CA  SER H 112B     13.800  10.000  10.000  1.00  0.00           C
+ATOM      3  CA  TYR H 112A     17.600  10.000  10.000  1.00  0.00           C
+ATOM      4  CA  GLY H 112      21.400  10.000  10.000  1.00  0.00           C
+ATOM      5  CA  ASP H 113      25.200  10.000  10.000  1.00  0.00           C
+END
+";
+        let (bytes, start, end) = extract_fragment(
+            BufReader::new(imgt.as_bytes()),
+            "H".into(),
+            "112B..112".parse().unwrap(),
+            "pdb",
+            &all,
+        )
+        .unwrap();
+        assert_eq!(
+            (start.to_string(), end.to_string()),
+            ("112B".into(), "112".into())
+        );
+        let (frag, _) = read_raw(BufReader::new(&bytes[..]), "pdb").unwrap();
+        let names: Vec<_> = frag.residues().filter_map(|r| r.name()).collect();
+        assert_eq!(names, ["SER", "TYR", "GLY"]);
+
+        let (axis, _) = d2_map(
+            BufReader::new(kabat.as_bytes()),
+            None,
+            Representative::Auto,
+            "pdb",
+            &all,
+        )
+        .unwrap();
+        assert_eq!(axis, ["H-52-S", "H-52A-Y", "H-52B-G", "H-53-D"]);
+    }
+
+    #[test]
+    fn test_modified_residues() {
+        let all = Selection::all();
+        let complex = "\
+ATOM      1  CA  GLY A   1      10.000  10.000  10.000  1.00  0.00           C
+HETATM    2  CA  MSE A   2      13.800  10.000  10.000  1.00  0.00           C
+HETATM    3 SE   MSE A   2      14.800  11.000  10.000  1.00  0.00          SE
+ATOM      4  P     G B   1      15.000  13.000  10.000  1.00  0.00           P
+HETATM    5  P   PSU B   2      30.000  30.000  30.000  1.00  0.00           P
+END
+";
+        assert_eq!(three_to_one("MSE"), 'M');
+        assert_eq!(three_to_one("PSU"), 'U');
+        // L-adenosine 的母体是 A；无碱基位点 3DR 没有母体，在 DNA 序列中记为 N 而不是 T
+        assert_eq!(three_to_one("0A"), 'A');
+        let abasic = "\
+DBREF  1ABC C    1     3  PDB    1ABC     1ABC             1      3             \n\
+SEQRES   1 C    3   DA 3DR  DC
+ATOM      1  P    DA C   1       0.000   0.000   0.000  1.00  0.00           P
+HETATM    2  P   3DR C   2       6.000   0.000   0.000  1.00  0.00           P
+ATOM      3  P    DC C   3      12.000   0.000   0.000  1.00  0.00           P
+END
+";
+        let sequences = chain_sequences(
+            BufReader::new(abasic.as_bytes()),
+            "pdb",
+            ModifiedResidues::Polymer,
+            &all,
+            SequenceOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(sequences[0].sequence, "ANC");
+
+        let pairs = compute_binding_pairs(
+            BufReader::new(complex.as_bytes()),
+            3.5,
+            "pdb",
+            ModifiedResidues::Polymer,
+            &all,
+        )
+        .unwrap();
+        let names: Vec<_> = pairs.iter().map(|p| p.to_string()).collect();
+        assert_eq!(names, ["A-2-MSE_B-1-G"]);
+        let pair = &pairs[0];
+        assert_eq!(
+            (pair.protein_atom.as_str(), pair.nucleic_atom.as_str()),
+            ("SE", "P")
+        );
+        assert!((pair.distance - 4.04f64.sqrt()).abs() < 1e-9);
+        assert_eq!(
+            (pair.contacts, pair.backbone_contacts, pair.base_contacts),
+            (2, 2, 0)
+        );
+        assert_eq!(pair.region(), NucleicRegion::Backbone);
+
+        let pairs = compute_binding_pairs(
+            BufReader::new(complex.as_bytes()),
+            3.5,
+            "pdb",
+            ModifiedResidues::Ligand,
+            &all,
+        )
+        .unwrap();
+        assert!(pairs.is_empty());
+
+        let parts = split_complex(
+            BufReader::new(complex.as_bytes()),
+            "pdb",
+            ModifiedResidues::Ligand,
+            &all,
+        )
+        .unwrap();
+        let (na, _) = read_raw(BufReader::new(&parts["NA"][..]), "pdb").unwrap();
+        let names: Vec<_> = na.residues().filter_map(|r| r.name()).collect();
+        assert_eq!(names, ["G"]);
+
+        let text = std::fs::read_to_string("./test_pdbs/7U5E.cif").unwrap();
+        let entities = EntityPoly::from_mmcif(&text);
+        assert_eq!(entities[0].polymer_type, Some(PolymerType::Rna));
+        assert_eq!(entities[1].polymer_type, Some(PolymerType::Dna));
+        assert!(entities.iter().all(|e| !e.monomers.is_empty()));
+
+        // 微不均一位点：同一 num 的第二行不计入序列
+        let hetero = "\
+data_test
+loop_
+_entity_poly.entity_id
+_entity_poly.type
+_entity_poly.pdbx_strand_id
+1 'polypeptide(L)' A
+loop_
+_entity_poly_seq.entity_id
+_entity_poly_seq.num
+_entity_poly_seq.mon_id
+_entity_poly_seq.hetero
+1 1 MET n
+1 2 SER y
+1 2 THR y
+1 3 LYS n
+";
+        let entities = EntityPoly::from_mmcif(hetero);
+        assert_eq!(entities[0].monomers, ["MET", "SER", "LYS"]);
+    }
+
+    #[test]
+    fn test_multi_model() {
+        let all = Selection::all();
+        let ensemble = "\
+MODEL        1
+ATOM      1  CA  LYS A   1       0.000   0.000   0.000  1.00  0.00           C
+ATOM      2  P     U B   1       3.000   0.000   0.000  1.00  0.00           P
+ENDMDL
+MODEL        2
+ATOM      1  CA  LYS A   1       0.000   0.000   0.000  1.00  0.00           C
+ATOM      2  P     U B   1       5.000   0.000   0.000  1.00  0.00           P
+ENDMDL
+END
+";
+        let models = split_by_model(BufReader::new(ensemble.as_bytes()), "pdb", &all).unwrap();
+        assert_eq!(models.len(), 2);
+
+        let reader = || BufReader::new(ensemble.as_bytes());
+        let (axis, mean) = d_map_models(reader(), None, ModelAggregate::Mean, "pdb", &all).unwrap();
+        assert_eq!(axis, ["A-1-K", "B-1-U"]);
+        assert!((mean[0][0] - 4.0).abs() < 1e-9);
+        let (_, min) = d_map_models(reader(), None, ModelAggregate::Min, "pdb", &all).unwrap();
+        let (_, max) = d_map_models(reader(), None, ModelAggregate::Max, "pdb", &all).unwrap();
+        let (_, std) = d_map_models(reader(), None, ModelAggregate::Std, "pdb", &all).unwrap();
+        assert!((min[0][0] - 3.0).abs() < 1e-9 && (max[0][0] - 5.0).abs() < 1e-9);
+        assert!((std[0][0] - 1.0).abs() < 1e-9);
+
+        let freq = compute_binding_frequency(reader(), 3.5, "pdb", ModifiedResidues::Polymer, &all)
+            .unwrap();
+        assert_eq!(freq.len(), 1);
+        assert_eq!(freq[0].protein.to_string(), "A-1-LYS");
+        assert_eq!(freq[0].nucleic.to_string(), "B-1-U");
+        assert_eq!(freq[0].frequency, 0.5);
+        assert_eq!(freq[0].to_string(), "A-1-LYS_B-1-U");
+    }
+
+    #[test]
+    fn test_selection() {
+        let complex = "\
+ATOM      1  N   GLY A  10      10.000  10.000  10.000  1.00  0.00           N
+ATOM      2  CA  GLY A  10      11.000  10.000  10.000  1.00  0.00           C
+ATOM      3  H   GLY A  10       9.500  10.000  10.000  1.00  0.00           H
+ATOM      4  CA  ALA A  60      40.000  40.000  40.000  1.00  0.00           C
+ATOM      5  P     G B   1      14.000  10.000  10.000  1.00  0.00           P
+ATOM      6  H5'   G B   1      14.500  10.000  10.000  1.00  0.00           H
+HETATM    7  O   HOH W   1      50.000  50.000  50.000  1.00  0.00           O
+END
+";
+        let select = |query: &str| {
+            let selection: Selection = query.parse().unwrap();
+            let bytes = extract_selection(
+                BufReader::new(complex.as_bytes()),
+                "pdb",
+                ModifiedResidues::Polymer,
+                &selection,
+            )
+            .unwrap();
+            let (pdb, _) = read_raw(BufReader::new(&bytes[..]), "pdb").unwrap();
+            pdb.atoms().map(|a| a.serial_number()).collect::<Vec<_>>()
+        };
+        assert_eq!(select("chain A and resi 10-50 and name CA"), [2]);
+        assert_eq!(select("nucleic and not hydrogen"), [5]);
+        assert_eq!(select("within 5 of chain B"), [1, 2, 3, 5, 6]);
+        assert_eq!(select("(protein or water) and not hydrogen"), [1, 2, 4, 7]);
+        assert_eq!(select("backbone or resn hoh"), [1, 2, 4, 5, 7]);
+
+        for bad in ["chain", "resi 10-", "within of chain A", "(protein", "foo"] {
+            let err = bad.parse::<Selection>().unwrap_err();
+            assert_eq!(err.kind(), "invalid_argument", "{bad}");
+        }
+
+        let selection: Selection = "chain Z".parse().unwrap();
+        let err = d2_map(
+            BufReader::new(complex.as_bytes()),
+            None,
+            Representative::Auto,
+            "pdb",
+            &selection,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), "chain_not_found");
+        let (axis, _) = d2_map(
+            BufReader::new(complex.as_bytes()),
+            None,
+            Representative::Auto,
+            "pdb",
+            &"protein".parse().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(axis, ["A-10-G", "A-60-A"]);
+
+        // 只有必须命中的链缺失才报错，否定、或、within 中的缺失链照常求值
+        assert_eq!(select("not chain Z and not hydrogen"), [1, 2, 4, 5, 7]);
+        assert_eq!(select("chain B or chain Z"), [5, 6]);
+        assert_eq!(select("within 5 of chain Z or chain W"), [7]);
+        let selection: Selection = "protein and chain Z".parse().unwrap();
+        assert_eq!(selection.chains(), ["Z"]);
+    }
+
+    #[test]
+    fn test_d_map_between() {
+        let complex = "\
+ATOM      1  CA  GLY H   1       0.000   0.000   0.000  1.00  0.00           C
+ATOM      2  CA  ALA H   2       3.000   0.000   0.000  1.00  0.00           C
+ATOM      3  CA  SER A   1       0.000   4.000   0.000  1.00  0.00           C
+ATOM      4  P     G B   1       0.000   0.000   5.000  1.00  0.00           P
+END
+";
+        let reader = || BufReader::new(complex.as_bytes());
+        let heavy = "chain H".parse().unwrap();
+        let (rows, cols, values) =
+            d_map_between(reader(), &heavy, &"chain A or rna".parse().unwrap(), "pdb").unwrap();
+        assert_eq!(rows, ["H-1-G", "H-2-A"]);
+        assert_eq!(cols, ["A-1-S", "B-1-G"]);
+        assert_eq!(values.len(), 2);
+        assert!((values[0][0] - 4.0).abs() < 1e-9 && (values[0][1] - 5.0).abs() < 1e-9);
+        assert!((values[1][0] - 5.0).abs() < 1e-9);
+
+        let err = d_map_between(reader(), &heavy, &"chain Z".parse().unwrap(), "pdb").unwrap_err();
+        assert_eq!(err.kind(), "chain_not_found");
+    }
+
+    #[test]
+    fn test_representative_atoms() {
+        let all = Selection::all();
+        let structure = "\
+ATOM      1  N   GLY A   1      -1.458   0.000   0.000  1.00  0.00           N
+ATOM      2  CA  GLY A   1       0.000   0.000   0.000  1.00  0.00           C
+ATOM      3  C   GLY A   1       0.551   1.420   0.000  1.00  0.00           C
+ATOM      4  CA  ALA A   2      10.000   0.000   0.000  1.00  0.00           C
+ATOM      5  CB  ALA A   2      10.000   0.000   3.000  1.00  0.00           C
+ATOM      6  H   ALA A   2       6.000   0.000   0.000  1.00  0.00           H
+ATOM      7  P     G B   1      20.000   0.000   0.000  1.00  0.00           P
+ATOM      8  C1'   G B   1      20.000   4.000   0.000  1.00  0.00           C
+END
+";
+        let map = |repr: &str| {
+            let reader = BufReader::new(structure.as_bytes());
+            d_map(reader, None, repr.parse().unwrap(), "pdb", &all)
+                .unwrap()
+                .1
+        };
+
+        let ca = map("ca");
+        assert!((ca[0][0] - 10.0).abs() < 1e-9);
+        // Glycine gets a virtual CB about 1.53 Å from CA.
+        let cb = map("cb");
+        let gly_cb_to_ala_cb = cb[0][0];
+        assert!(gly_cb_to_ala_cb > 10.0 && gly_cb_to_ala_cb < 12.5);
+        let c1 = map("c1'");
+        // Alanine has no C1' and falls back to its atom centroid.
+        let ala_centroid_to_c1 = ((20.0f64 - 26.0 / 3.0).powi(2) + 16.0 + 1.0).sqrt();
+        assert!((c1[1][0] - ala_centroid_to_c1).abs() < 1e-9);
+        // Hydrogens are ignored by the minimum heavy-atom distance.
+        let min = map("min_heavy");
+        assert!((min[0][0] - (9.449f64.powi(2) + 1.42f64.powi(2)).sqrt()).abs() < 1e-9);
+        assert!((min[1][0] - 10.0).abs() < 1e-9);
+
+        assert_eq!(
+            "cg".parse::<Representative>().unwrap_err().kind(),
+            "invalid_argument"
+        );
+    }
+
+    #[test]
+    fn test_contact_list() {
+        use std::fs::File;
+        let all = Selection::all();
+        let pdb_path = "./test_pdbs/8W2S.cif";
+        let reader = || BufReader::new(File::open(pdb_path).unwrap());
+        let repr = Representative::Cb;
+        let (axis, dense) = d_map(reader(), Some("A".into()), repr, "cif", &all).unwrap();
+        let sparse = contact_list(reader(), Some("A".into()), 8.0, repr, "cif", &all).unwrap();
+        assert_eq!(sparse.axis, axis);
+
+        let mut expected = Vec::new();
+        for (i, row) in dense.iter().enumerate() {
+            for (k, &d) in row.iter().enumerate() {
+                if d <= 8.0 {
+                    expected.push((i as u32, (i + 1 + k) as u32, d));
+                }
+            }
+        }
+        assert_eq!(sparse.rows.len(), expected.len());
+        for (k, (i, j, d)) in expected.into_iter().enumerate() {
+            assert_eq!((sparse.rows[k], sparse.cols[k]), (i, j));
+            assert!((sparse.distances[k] - d).abs() < 1e-9);
+        }
+
+        let (indptr, indices, _) = sparse.to_csr();
+        assert_eq!(indptr.len(), axis.len() + 1);
+        assert_eq!(*indptr.last().unwrap() as usize, indices.len());
+    }
+
+    #[test]
+    fn test_knn_map_export() {
+        let all = Selection::all();
+        let line = "\
+ATOM      1  CA  GLY A   1       0.000   0.000   0.000  1.00  0.00           C
+ATOM      2  CA  ALA A   2       1.000   0.000   0.000  1.00  0.00           C
+ATOM      3  CA  SER A   3       3.000   0.000   0.000  1.00  0.00           C
+ATOM      4  CA  CYS A   4       6.000   0.000   0.000  1.00  0.00           C
+END
+";
+        let reader = || BufReader::new(line.as_bytes());
+        let (axis, knn) = knn_map(reader(), None, 1, Representative::Ca, "pdb", &all).unwrap();
+        assert_eq!(
+            knn,
+            [vec![1.0, 0.0, 0.0], vec![2.0, 0.0], vec![3.0], vec![]]
+        );
+
+        // 与第 k 个距离相等的残基按顺序补足 k 个
+        let star = "\
+ATOM      1  CA  GLY A   1       0.000   0.000   0.000  1.00  0.00           C
+ATOM      2  CA  ALA A   2       1.000   0.000   0.000  1.00  0.00           C
+ATOM      3  CA  SER A   3      -1.000   0.000   0.000  1.00  0.00           C
+ATOM      4  CA  CYS A   4       0.000   1.000   0.000  1.00  0.00           C
+END
+";
+        let (_, tied) = knn_map(
+            BufReader::new(star.as_bytes()),
+            None,
+            2,
+            Representative::Ca,
+            "pdb",
+            &all,
+        )
+        .unwrap();
+        assert_eq!(tied[0], [1.0, 1.0, 0.0]);
+
+        let npy = write_map(&axis, &knn, MapFormat::Npy);
+        assert_eq!(&npy[..8], b"\x93NUMPY\x01\x00");
+        let header_len = u16::from_le_bytes([npy[8], npy[9]]) as usize;
+        assert_eq!((10 + header_len) % 64, 0);
+        assert!(std::str::from_utf8(&npy[10..10 + header_len])
+            .unwrap()
+            .contains("'shape': (4, 4)"));
+        assert_eq!(npy.len(), 10 + header_len + 16 * 8);
+        let at =
+            |i: usize| f64::from_le_bytes(npy[10 + header_len + i * 8..][..8].try_into().unwrap());
+        assert_eq!((at(1), at(4)), (1.0, 1.0));
+
+        let csv = String::from_utf8(write_map(&axis, &knn, MapFormat::Csv)).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some(",A-1-G,A-2-A,A-3-S,A-4-C"));
+        assert_eq!(lines.next(), Some("A-1-G,0,1,0,0"));
+
+        let (axis, dist) = d_map(reader(), None, Representative::Ca, "pdb", &all).unwrap();
+        let rr = String::from_utf8(write_map(&axis, &dist, MapFormat::CaspRr)).unwrap();
+        assert_eq!(rr, "PFRMAT RR\nMODEL  1\nGASC\nEND\n");
+        let rr = pskit_core::export::write_casp_rr(&axis, &dist, 8.0, 2);
+        let rr = String::from_utf8(rr).unwrap();
+        assert!(rr.contains("1 3 0 8 1.000\n") && !rr.contains("1 2 0 8"));
+    }
+
+    #[test]
+    fn test_interactions() {
+        let all = Selection::all();
+        let complex = "\
+ATOM      1  CE  LYS A   1      -1.500   0.000   0.000  1.00  0.00           C
+ATOM      2  NZ  LYS A   1       0.000   0.000   0.000  1.00  0.00           N
+ATOM      3  CG  PHE A   2      21.400   0.000  10.000  1.00  0.00           C
+ATOM      4  CD1 PHE A   2      20.700   1.212  10.000  1.00  0.00           C
+ATOM      5  CE1 PHE A   2      19.300   1.212  10.000  1.00  0.00           C
+ATOM      6  CZ  PHE A   2      18.600   0.000  10.000  1.00  0.00           C
+ATOM      7  CE2 PHE A   2      19.300  -1.212  10.000  1.00  0.00           C
+ATOM      8  CD2 PHE A   2      20.700  -1.212  10.000  1.00  0.00           C
+ATOM      9  P     G B   1       4.000   0.500   0.000  1.00  0.00           P
+ATOM     10  OP1   G B   1       2.800   0.000   0.000  1.00  0.00           O
+ATOM     11  C4    G B   1      21.400   0.000  13.500  1.00  0.00           C
+ATOM     12  C5    G B   1      20.700   1.212  13.500  1.00  0.00           C
+ATOM     13  C6    G B   1      19.300   1.212  13.500  1.00  0.00           C
+ATOM     14  N1    G B   1      18.600   0.000  13.500  1.00  0.00           N
+ATOM     15  C2    G B   1      19.300  -1.212  13.500  1.00  0.00           C
+ATOM     16  N3    G B   1      20.700  -1.212  13.500  1.00  0.00           N
+ATOM     17  N7    G B   1      22.600   0.900  13.500  1.00  0.00           N
+ATOM     18  C8    G B   1      23.300  -0.300  13.500  1.00  0.00           C
+ATOM     19  N9    G B   1      22.300  -1.300  13.500  1.00  0.00           N
+END
+";
+        let run = |criteria: &InteractionCriteria| {
+            let reader = BufReader::new(complex.as_bytes());
+            classify_interactions(reader, "pdb", ModifiedResidues::Polymer, &all, criteria).unwrap()
+        };
+
+        let found = run(&InteractionCriteria::default());
+        let of = |kind| found.iter().filter(|i| i.kind == kind).count();
+        let lys: Vec<_> = found
+            .iter()
+            .filter(|i| i.protein.name == "LYS")
+            .map(|i| (i.kind, i.protein_atom.as_str(), i.nucleic_atom.as_str()))
+            .collect();
+        assert_eq!(
+            lys,
+            [
+                (InteractionType::HydrogenBond, "NZ", "OP1"),
+                (InteractionType::SaltBridge, "NZ", "OP1"),
+            ]
+        );
+        // Phe stacks on both guanine rings, plus ring-atom vdW contacts.
+        assert_eq!(of(InteractionType::PiStacking), 2);
+        assert!(of(InteractionType::VanDerWaals) > 0);
+        assert_eq!(of(InteractionType::CationPi), 0);
+
+        let loose = InteractionCriteria {
+            cation_pi_distance: 30.0,
+            cation_pi_angle: 90.0,
+            stacking_angle: 0.0,
+            ..Default::default()
+        };
+        let found = run(&loose);
+        assert!(found.iter().any(|i| i.kind == InteractionType::CationPi));
+        assert!(found
+            .iter()
+            .filter(|i| i.kind == InteractionType::PiStacking)
+            .all(|i| i.angle.unwrap() < 1e-6));
+    }
+
+    #[test]
+    fn test_interface() {
+        let complex = "\
+ATOM      1  CA  ALA A   1       0.000   0.000   0.000  1.00  0.00           C
+ATOM      2  CA  GLY A   2      20.000   0.000   0.000  1.00  0.00           C
+ATOM      3  CA  SER B   1       3.000   0.000   0.000  1.00  0.00           C
+ATOM      4  CB  SER B   1       3.500   1.000   0.000  1.00  0.00           C
+ATOM      5  CA  LEU B   2       0.000  30.000   0.000  1.00  0.00           C
+HETATM    6  C1  LIG A 101      20.000   3.000   0.000  1.00  0.00           C
+END
+";
+        let run = |a: &str, b: &str| {
+            let reader = BufReader::new(complex.as_bytes());
+            annotate_interface(
+                reader,
+                &a.parse().unwrap(),
+                &b.parse().unwrap(),
+                4.0,
+                "pdb",
+                ModifiedResidues::Polymer,
+            )
+            .unwrap()
+        };
+
+        // Protein–protein: no nucleic acid, so nothing is refused as NotComplex.
+        let pp = run("chain A and protein", "chain B");
+        assert_eq!(pp.contacts.len(), 1);
+        let contact = &pp.contacts[0];
+        assert_eq!(
+            (contact.a.name.as_str(), contact.b.name.as_str()),
+            ("ALA", "SER")
+        );
+        assert_eq!((contact.contacts, contact.b_atom.as_str()), (2, "CA"));
+        assert!((contact.distance - 3.0).abs() < 1e-6);
+        let labels: Vec<_> = pp
+            .residues
+            .iter()
+            .map(|r| (r.side, r.key.name.as_str(), r.is_interface()))
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                (InterfaceSide::A, "ALA", true),
+                (InterfaceSide::A, "GLY", false),
+                (InterfaceSide::B, "SER", true),
+                (InterfaceSide::B, "LEU", false),
+            ]
+        );
+
+        // Protein–ligand through the HETATM selection.
+        let pl = run("protein", "ligand");
+        assert_eq!(pl.contacts.len(), 1);
+        assert_eq!(pl.contacts[0].a.name, "GLY");
+        assert_eq!(pl.contacts[0].b.name, "LIG");
+
+        // 重叠的选择：同在两侧的残基对只计一次
+        let overlap = run("protein", "protein or ligand");
+        let pairs: Vec<_> = overlap
+            .contacts
+            .iter()
+            .map(|c| (c.a.name.as_str(), c.b.name.as_str()))
+            .collect();
+        assert_eq!(pairs, [("ALA", "SER"), ("GLY", "LIG")]);
+        let partners: Vec<_> = overlap
+            .residues
+            .iter()
+            .map(|r| (r.side, r.key.name.as_str(), r.partners))
+            .filter(|r| r.2 > 0)
+            .collect();
+        assert_eq!(
+            partners,
+            [
+                (InterfaceSide::A, "ALA", 1),
+                (InterfaceSide::A, "GLY", 1),
+                (InterfaceSide::B, "LIG", 1),
+                (InterfaceSide::B, "SER", 1),
+            ]
+        );
+
+        let reader = BufReader::new(complex.as_bytes());
+        let missing = annotate_interface(
+            reader,
+            &"chain Z".parse().unwrap(),
+            &Selection::all(),
+            4.0,
+            "pdb",
+            ModifiedResidues::Polymer,
+        );
+        assert!(matches!(missing, Err(PskitError::ChainNotFound { .. })));
+    }
+
+    #[test]
+    fn test_binding_labels() {
+        let all = Selection::all();
+        let complex = "\
+ATOM      1  CA  MET A   1       0.000   0.000   0.000  1.00  0.00           C
+ATOM      2  CA  LYS A   2      10.000   0.000   0.000  1.00  0.00           C
+ATOM      3  CA  ARG A   3      20.000   0.000   0.000  1.00  0.00           C
+ATOM      4  P    DG B   1      13.000   0.000   0.000  1.00  0.00           P
+ATOM      5  P     U C   1      22.000   0.000   0.000  1.00  0.00           P
+END
+";
+        let reader = BufReader::new(complex.as_bytes());
+        let labels = binding_labels(
+            reader,
+            3.5,
+            "pdb",
+            ModifiedResidues::Polymer,
+            &all,
+            SequenceSource::Atom,
+        )
+        .unwrap();
+        assert_eq!(labels.len(), 1);
+        let chain = &labels[0];
+        assert_eq!(
+            (chain.chain_id.as_str(), chain.sequence.as_str()),
+            ("A", "MKR")
+        );
+        assert_eq!(
+            chain.values(LabelTarget::Dna, LabelValue::Binary),
+            [0.0, 1.0, 0.0]
+        );
+        assert_eq!(
+            chain.values(LabelTarget::Rna, LabelValue::Binary),
+            [0.0, 0.0, 1.0]
+        );
+        assert_eq!(
+            chain.values(LabelTarget::Nucleic, LabelValue::Distance),
+            [3.5, 3.0, 2.0]
+        );
+
+        let fasta = write_label_fasta(&labels, "1abc", LabelTarget::Dna, LabelValue::Binary);
+        assert_eq!(String::from_utf8(fasta).unwrap(), ">1abc_A dna\nMKR\n010\n");
+        let json =
+            String::from_utf8(write_label_json(&labels, "1abc", LabelValue::Binary)).unwrap();
+        assert_eq!(
+            json,
+            "{\"id\":\"1abc\",\"cutoff\":3.5,\"chains\":[{\"chain\":\"A\",\"residues\":[\"1\",\"2\",\"3\"],\
+             \"sequence\":\"MKR\",\"dna\":[0,1,0],\"rna\":[0,0,1]}]}\n"
+        );
+
+        // 按 SEQRES 对齐时，未建模的残基占位且标签为缺失
+        let deposited = complex.replace("ARG A   3", "ARG A   4").replace(
+            "ATOM      1",
+            "DBREF  1ABC A    1     4  UNP    P00001   TEST_HUMAN       1      4             \n\
+                 SEQRES   1 A    4  MET LYS GLY ARG\nATOM      1",
+        );
+        let labels = binding_labels(
+            BufReader::new(deposited.as_bytes()),
+            3.5,
+            "pdb",
+            ModifiedResidues::Polymer,
+            &all,
+            SequenceSource::Seqres,
+        )
+        .unwrap();
+        let chain = &labels[0];
+        assert_eq!(
+            (chain.source, chain.sequence.as_str()),
+            (SequenceSource::Seqres, "MKGR")
+        );
+        assert!(chain.residues[2].is_none());
+        let dna = chain.values(LabelTarget::Dna, LabelValue::Binary);
+        assert_eq!((dna[1], dna[3]), (1.0, 0.0));
+        assert!(dna[2].is_nan());
+        let fasta = write_label_fasta(&labels, "", LabelTarget::Rna, LabelValue::Binary);
+        assert_eq!(String::from_utf8(fasta).unwrap(), ">A rna\nMKGR\n00-1\n");
+    }
+
+    #[test]
+    fn test_sasa() {
+        use std::f64::consts::PI;
+        let all = Selection::all();
+        let options = SasaOptions::default();
+        let complex = "\
+ATOM      1  CA  ALA A   1       0.000   0.000   0.000  1.00  0.00           C
+ATOM      2  P    DG B   1       4.000   0.000   0.000  1.00  0.00           P
+HETATM    3  O   HOH W   1       2.000   0.000   0.000  1.00  0.00           O
+END
+";
+        let run = |selection: &Selection| {
+            let reader = BufReader::new(complex.as_bytes());
+            compute_sasa(reader, "pdb", selection, &options).unwrap()
+        };
+
+        // A lone carbon is fully exposed; water never counts.
+        let alone = run(&"chain A or water".parse().unwrap());
+        let full = 4.0 * PI * 3.1f64.powi(2);
+        assert_eq!(alone.atoms.len(), 1);
+        assert!((alone.total - full).abs() < 1e-9);
+        assert!((alone.residues[0].relative.unwrap() - full / 129.0).abs() < 1e-9);
+
+        let both = run(&all);
+        assert_eq!(both.residues.len(), 2);
+        assert!(both.residues[0].area < full);
+        assert_eq!(both.residues[1].relative, None);
+
+        let reader = BufReader::new(complex.as_bytes());
+        let bsa =
+            buried_surface_area(reader, "pdb", ModifiedResidues::Polymer, &all, &options).unwrap();
+        assert!((bsa.protein - full).abs() < 1e-9);
+        assert!((bsa.complex - both.total).abs() < 1e-9);
+        assert!(bsa.buried() > 0.0);
+        assert_eq!(bsa.residues.len(), 2);
+        let buried: f64 = bsa.residues.iter().map(|r| r.buried()).sum();
+        assert!((buried - bsa.buried()).abs() < 1e-9);
+
+        // Real structure: buried area is positive and much smaller than the surfaces.
+        let reader = BufReader::new(std::fs::File::open("./test_pdbs/7U5E.cif").unwrap());
+        let bsa =
+            buried_surface_area(reader, "cif", ModifiedResidues::Polymer, &all, &options).unwrap();
+        assert!(bsa.buried() > 0.0 && bsa.buried() < bsa.protein);
+    }
+
+    #[test]
+    fn test_dssp() {
+        use std::fs::File;
+        let all = Selection::all();
+        let pdb_path = "./test_pdbs/8W2S.cif";
+        let reader = BufReader::new(File::open(pdb_path).expect("open file"));
+        let dssp = compute_dssp(reader, "cif", ModifiedResidues::Polymer, &all).unwrap();
+        let summary = dssp.summary();
+        assert_eq!(summary.len(), dssp.residues.len());
+        assert!(summary.starts_with("--HHHHHHHHHHHHHHHHHHHHHHHHHHHHHHT"));
+
+        // Inside the first helix every N-H bonds to the C=O four residues back.
+        let r = &dssp.residues[10];
+        assert_eq!(r.structure, SecondaryStructure::AlphaHelix);
+        assert_eq!(r.nh_o[0].partner, Some(6));
+        assert!(r.nh_o[0].energy < -2.0);
+        assert_eq!(r.helix_flags[1], 'X');
+        let (phi, psi) = (r.phi.unwrap(), r.psi.unwrap());
+        assert!((-80.0..-50.0).contains(&phi) && (-55.0..-25.0).contains(&psi));
+
+        // Fixed columns as read by DSSP parsers: AA at 13, SS at 16, ACC at 34..38.
+        let text = String::from_utf8(write_dssp(&dssp)).unwrap();
+        let line = text
+            .lines()
+            .skip_while(|l| !l.starts_with("  #  RESIDUE"))
+            .nth(11)
+            .unwrap();
+        assert_eq!(&line[13..14], "I");
+        assert_eq!(&line[16..17], "H");
+        assert_eq!(line[5..10].trim(), dssp.residues[10].key.number.to_string());
+        assert!(line[34..38].trim().parse::<u32>().is_ok());
+
+        let reader = BufReader::new(File::open(pdb_path).expect("open file"));
+        let cif = write_dssp_mmcif(reader, "cif", ModifiedResidues::Polymer, &all).unwrap();
+        let cif = String::from_utf8(cif).unwrap();
+        assert!(cif.contains("_struct_conf.conf_type_id"));
+        assert!(cif.contains("HELX_RH_AL_P HELX_RH_AL_P1 A 45"));
+        assert!(read_raw(BufReader::new(cif.as_bytes()), "cif").is_ok());
+    }
+
+    #[test]
+    fn test_superpose() {
+        let text = std::fs::read("./test_pdbs/8W2S.cif").unwrap();
+        let (mut moved, _) = read_raw(BufReader::new(text.as_slice()), "cif").unwrap();
+        // 绕 z 轴旋转 90° 并平移
+        for atom in moved.atoms_mut() {
+            let (x, y, z) = atom.pos();
+            atom.set_pos((-y + 10.0, x - 5.0, z + 3.0)).unwrap();
+        }
+        let moved = write_raw(moved, "cif");
+
+        let ca: Selection = "name CA".parse().unwrap();
+        let run = |mobile: &Selection, matching| {
+            superpose(
+                BufReader::new(moved.as_slice()),
+                "cif",
+                mobile,
+                BufReader::new(text.as_slice()),
+                "cif",
+                &ca,
+                matching,
+            )
+            .unwrap()
+        };
+
+        let (fit, coordinates) = run(&ca, ResidueMatch::ResidueId);
+        assert_eq!(fit.matched_atoms, fit.residues.len());
+        assert!(fit.rmsd_before > 1.0);
+        assert!(fit.rmsd_after < 1e-3);
+        assert!((fit.rotation[0][1] - 1.0).abs() < 1e-6);
+        assert!(fit.residues.iter().all(|r| r.rmsd < 1e-3));
+
+        // The transformed structure lies back on the reference.
+        let (back, _) = read_raw(BufReader::new(coordinates.as_slice()), "cif").unwrap();
+        let (reference, _) = read_raw(BufReader::new(text.as_slice()), "cif").unwrap();
+        let a = back.atoms().next().unwrap().pos();
+        let b = reference.atoms().next().unwrap().pos();
+        assert!((a.0 - b.0).abs() + (a.1 - b.1).abs() + (a.2 - b.2).abs() < 1e-2);
+
+        // Sequence alignment pairs the same residues even with a gap in the mobile chain.
+        let gapped: Selection = "name CA and not resi 60-70".parse().unwrap();
+        let (aligned, _) = run(&gapped, ResidueMatch::Alignment);
+        assert_eq!(aligned.matched_atoms, fit.matched_atoms - 11);
+        assert!(aligned.rmsd_after < 1e-3);
+        assert!(aligned
+            .residues
+            .iter()
+            .all(|r| r.mobile.number == r.target.number));
+    }
+
+    #[test]
+    fn test_compare_structures() {
+        let text = std::fs::read("./test_pdbs/8W2S.cif").unwrap();
+        let (mut model, _) = read_raw(BufReader::new(text.as_slice()), "cif").unwrap();
+        // 旋转后把第 60-70 号残基整体平移 6 Å
+        for atom in model.atoms_mut() {
+            let (x, y, z) = atom.pos();
+            atom.set_pos((-y + 10.0, x - 5.0, z + 3.0)).unwrap();
+        }
+        let shifted = model.clone();
+        let rigid = write_raw(model, "cif");
+        let mut shifted = shifted;
+        for chain in shifted.chains_mut() {
+            for residue in chain.residues_mut() {
+                if (60..=70).contains(&residue.serial_number()) {
+                    for atom in residue.atoms_mut() {
+                        let (x, y, z) = atom.pos();
+                        atom.set_pos((x + 6.0, y, z)).unwrap();
+                    }
+                }
+            }
+        }
+        let shifted = write_raw(shifted, "cif");
+
+        let all = Selection::all();
+        let compare = |model: &[u8]| {
+            compare_structures(
+                BufReader::new(model),
+                "cif",
+                &all,
+                BufReader::new(text.as_slice()),
+                "cif",
+                &all,
+                ResidueMatch::ResidueId,
+            )
+            .unwrap()
+        };
+
+        let same = compare(&rigid);
+        assert_eq!(same.aligned, same.reference_length);
+        assert!((same.tm_score - 1.0).abs() < 1e-6);
+        assert!((same.gdt_ts - 1.0).abs() < 1e-9);
+        assert!((same.lddt - 1.0).abs() < 1e-9);
+
+        let moved = compare(&shifted);
+        assert!(moved.tm_score < same.tm_score && moved.tm_score > 0.8);
+        assert!(moved.gdt_ts < 1.0);
+        assert!(moved.lddt < 1.0);
+        let lddt_of = |n: isize| {
+            moved
+                .residues
+                .iter()
+                .find(|r| r.reference.number.serial == n)
+                .and_then(|r| r.lddt)
+                .unwrap()
+        };
+        assert!(lddt_of(65) < lddt_of(20));
+        // The best superposition keeps the rest of the chain on the reference.
+        for r in &moved.residues {
+            let deviation = r.deviation.unwrap();
+            if (60..=70).contains(&r.reference.number.serial) {
+                assert!(deviation > 5.0);
+            } else {
+                assert!(deviation < 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_chain_sequences() {
+        let text = std::fs::read("./test_pdbs/7U5E.cif").unwrap();
+        let all = Selection::all();
+        let read = |options| {
+            chain_sequences(
+                BufReader::new(text.as_slice()),
+                "cif",
+                ModifiedResidues::Polymer,
+                &all,
+                options,
+            )
+            .unwrap()
+        };
+        let atom = read(SequenceOptions::default());
+        let gapped = read(SequenceOptions {
+            mark_gaps: true,
+            ..Default::default()
+        });
+        let seqres = read(SequenceOptions {
+            source: SequenceSource::Seqres,
+            ..Default::default()
+        });
+        let chain = |list: &[ChainSequence], id: &str| {
+            list.iter().find(|c| c.chain_id == id).unwrap().clone()
+        };
+        assert_eq!(atom.len(), seqres.len());
+        assert_eq!(chain(&atom, "1").polymer_type, Some(PolymerType::Rna));
+        assert_eq!(chain(&atom, "2").polymer_type, Some(PolymerType::Dna));
+
+        // Gaps follow the numbering; the deposited sequence adds the unobserved termini.
+        let b = chain(&atom, "B");
+        assert_eq!(b.residues.len(), b.sequence.len());
+        assert_eq!(chain(&seqres, "B").source, SequenceSource::Seqres);
+        assert_eq!(chain(&seqres, "B").sequence, format!("ME{}KGR", b.sequence));
+        let g = chain(&gapped, "G");
+        assert!(g
+            .sequence
+            .contains("EVAKI-----------------------------PQTIE"));
+        assert_eq!(g.sequence.replace('-', ""), chain(&atom, "G").sequence);
+
+        let fasta = String::from_utf8(write_fasta(&atom[..2], "7U5E", 60)).unwrap();
+        assert_eq!(
+            fasta,
+            ">7U5E_1 rna\nCCAAGAAAAGGACUGGAAGAAAUCAUCCAAGUUGGGGACUAUUUUCUGCCGUAUAGGCAG\n\
+             >7U5E_2 dna\nCCCAACTTGGATGATTTCTTCCAGTCCTGGTAA\n"
+        );
+    }
+
+    #[test]
+    fn test_map_sequences() {
+        let text = std::fs::read("./test_pdbs/7U5E.cif").unwrap();
+        let all = Selection::all();
+        let map = |text: &[u8]| {
+            map_sequences(BufReader::new(text), "cif", ModifiedResidues::Polymer, &all).unwrap()
+        };
+        let mappings = map(&text);
+        let g = mappings.iter().find(|m| m.chain_id == "G").unwrap();
+        let missing: Vec<_> = g.missing.iter().map(|r| (r.start, r.end)).collect();
+        assert_eq!(
+            missing,
+            [(0, 1), (39, 67), (226, 233), (317, 321), (344, 346)]
+        );
+        assert_eq!(g.missing[0].sequence, "ME");
+        let breaks: Vec<_> = g.breaks.iter().map(|b| b.before.number.serial).collect();
+        assert_eq!(breaks, [39, 226, 317]);
+        assert_eq!(g.index_of(&ResidueNumber::from(3)), Some(2));
+        assert_eq!(g.index_of(&ResidueNumber::from(50)), None);
+        assert_eq!(g.numbering()[50], ResidueNumber::from(51));
+
+        let json = String::from_utf8(write_sequence_mapping_json(&mappings[..1], "7U5E")).unwrap();
+        assert!(json.starts_with(
+            "{\"id\":\"7U5E\",\"chains\":[{\"chain\":\"1\",\"type\":\"rna\",\"source\":\"seqres\""
+        ));
+
+        // Without the mmCIF numbering scheme the residues are aligned to the same places.
+        let unscheme = String::from_utf8(text.clone())
+            .unwrap()
+            .replace("_pdbx_poly_seq_scheme.", "_pdbx_poly_seq_unused.");
+        let aligned = map(unscheme.as_bytes());
+        for (a, b) in mappings.iter().zip(&aligned) {
+            assert_eq!(a.residues, b.residues, "chain {}", a.chain_id);
+        }
+
+        // Contact-map axis over the full sequence.
+        let (axis, values) = d_map(
+            BufReader::new(text.as_slice()),
+            Some("G".to_string()),
+            Representative::Ca,
+            "cif",
+            &all,
+        )
+        .unwrap();
+        let (gapped_axis, gapped) = insert_gaps(&axis, &values, g);
+        let spread = d_map_gapped(
+            BufReader::new(text.as_slice()),
+            "G".to_string(),
+            Representative::Ca,
+            "cif",
+            &all,
+        )
+        .unwrap();
+        assert_eq!(spread.0, gapped_axis);
+        assert_eq!(gapped_axis.len(), g.sequence.len());
+        assert_eq!(gapped_axis[2], axis[0]);
+        assert_eq!(gapped_axis[50], "G-51-D");
+        assert!(gapped[50].iter().all(|v| v.is_nan()));
+        assert_eq!(gapped[2][0], values[0][0]);
+        assert!(gapped[38][0].is_nan());
+
+        // Fragment ranges report the modeled ends and reject unmodeled stretches.
+        let fragment = |start: isize, end: isize| {
+            extract_fragment(
+                BufReader::new(text.as_slice()),
+                "G".to_string(),
+                ResidueRange::new(Some(start.into()), Some(end.into())),
+                "cif",
+                &all,
+            )
+        };
+        let (_, start, end) = fragment(30, 50).unwrap();
+        assert_eq!((start.serial, end.serial), (30, 39));
+        assert_eq!(fragment(45, 60).unwrap_err().kind(), "invalid_argument");
+        // 未建模的 N 端残基也在序列范围内
+        let (_, start, end) = fragment(1, 10).unwrap();
+        assert_eq!((start.serial, end.serial), (3, 10));
+        assert_eq!(fragment(0, 10).unwrap_err().kind(), "invalid_range");
+    }
+
+    #[test]
+    fn test_align() {
+        let options = |mode, matrix| AlignOptions {
+            mode,
+            matrix,
+            ..Default::default()
+        };
+        let global = AlignOptions::default();
+        assert_eq!(align("W", "W", &global).score, 11);
+        let pam = options(AlignMode::Global, ScoringMatrix::Pam250);
+        assert_eq!(align("W", "W", &pam).score, 17);
+        assert_eq!(
+            align("HEAGAWGHEE", "PAWHEAE", &global).score,
+            align("PAWHEAE", "HEAGAWGHEE", &global).score
+        );
+
+        let same = align("HEAGAWGHEE", "HEAGAWGHEE", &global);
+        assert_eq!(same.identity, 1.0);
+        assert_eq!(same.pairs, (0..10).map(|i| (i, i)).collect::<Vec<_>>());
+
+        // 一段长度为 2 的空位：10 + 1
+        let gapped = align("WWWWW", "WWW", &global);
+        assert_eq!(gapped.score, 3 * 11 - 11);
+        assert_eq!(gapped.aligned_b.matches('-').count(), 2);
+        assert_eq!(gapped.map_a_to_b(5).iter().flatten().count(), 3);
+
+        let local = align(
+            "KKKKHEAGAWGHEEKKKK",
+            "PPHEAGAWGHEEPP",
+            &options(AlignMode::Local, ScoringMatrix::Blosum62),
+        );
+        assert_eq!(local.aligned_a, "HEAGAWGHEE");
+        assert_eq!(local.pairs[0], (4, 2));
+
+        let semi = align(
+            "AWGHE",
+            "HEAGAWGHEE",
+            &options(AlignMode::SemiGlobal, ScoringMatrix::Auto),
+        );
+        assert_eq!(semi.pairs.first(), Some(&(0, 4)));
+        assert_eq!(semi.pairs.last(), Some(&(4, 8)));
+        assert_eq!(semi.score, align("AWGHE", "AWGHE", &global).score);
+
+        // 核酸序列自动使用核苷酸打分，T 与 U 视为同一碱基
+        assert_eq!(align("ACGU", "ACGT", &global).score, 20);
+        assert!("nw".parse::<AlignMode>().is_ok());
+        assert_eq!(
+            "blosum".parse::<ScoringMatrix>().unwrap(),
+            ScoringMatrix::Blosum62
+        );
+        assert!("identity".parse::<ScoringMatrix>().is_err());
+    }
+
+    #[test]
+    fn test_residue_graph() {
+        use std::fs::File;
+        let all = Selection::all();
+        let pdb_path = "./test_pdbs/8W2S.cif";
+        let reader = || BufReader::new(File::open(pdb_path).unwrap());
+        let chain = || Some("A".to_string());
+
+        let graph = residue_graph(reader(), chain(), GraphOptions::default(), "cif", &all).unwrap();
+        let contacts = contact_list(
+            reader(),
+            chain(),
+            12.0,
+            Representative::Centroid,
+            "cif",
+            &all,
+        )
+        .unwrap();
+        assert_eq!(graph.axis, contacts.axis);
+        assert_eq!(graph.coords.len(), graph.axis.len());
+        assert_eq!(graph.edges.len(), 2 * contacts.rows.len());
+        for (k, pair) in graph.edges.chunks(2).enumerate() {
+            let (i, j) = (contacts.rows[k], contacts.cols[k]);
+            assert_eq!(pair, [(i, j), (j, i)]);
+            let f = graph.features[2 * k];
+            assert!((f[0] - contacts.distances[k]).abs() < 1e-9);
+            assert_eq!(f[4], (j - i) as f64);
+            assert!((f[5] - f[4].ln() / f[0]).abs() < 1e-12);
+            let unit = (f[1] * f[1] + f[2] * f[2] + f[3] * f[3]).sqrt();
+            assert!((unit - 1.0).abs() < 1e-9);
+            assert_eq!(graph.features[2 * k + 1][1], -f[1]);
+        }
+
+        let knn = GraphOptions {
+            edges: "knn:6".parse().unwrap(),
+            ..Default::default()
+        };
+        assert_eq!(knn.edges, EdgeRule::Knn(6));
+        let graph = residue_graph(reader(), chain(), knn, "cif", &all).unwrap();
+        let mut degree = vec![0; graph.axis.len()];
+        for &(s, _) in &graph.edges {
+            degree[s as usize] += 1;
+        }
+        assert!(degree.iter().all(|&d| d >= 6));
+        assert!("knn".parse::<EdgeRule>().is_err());
+
+        // 重合的残基互为近邻
+        let coincident = "\
+ATOM      1  CA  GLY A   1       0.000   0.000   0.000  1.00  0.00           C
+ATOM      2  CA  ALA A   2       0.000   0.000   0.000  1.00  0.00           C
+ATOM      3  CA  SER A   3       5.000   0.000   0.000  1.00  0.00           C
+END
+";
+        let knn1 = GraphOptions {
+            edges: EdgeRule::Knn(1),
+            ..Default::default()
+        };
+        let graph = residue_graph(
+            BufReader::new(coincident.as_bytes()),
+            None,
+            knn1,
+            "pdb",
+            &all,
+        )
+        .unwrap();
+        assert_eq!(graph.edges, [(0, 1), (1, 0), (0, 2), (2, 0)]);
+        let min_heavy = GraphOptions {
+            repr: Representative::MinHeavy,
+            ..Default::default()
+        };
+        assert!(residue_graph(reader(), chain(), min_heavy, "cif", &all).is_err());
+
+        let npz = graph.to_npz();
+        assert_eq!(&npz[..4], b"PK\x03\x04");
+        assert_eq!(&npz[30..44], b"edge_index.npy");
+        assert_eq!(&npz[npz.len() - 22..npz.len() - 18], b"PK\x05\x06");
+    }
+
+    #[test]
+    fn test_batch() {
+        use std::io::Cursor;
+        let sources: Vec<BatchSource> = [
+            "./test_pdbs/7U5E.cif",
+            "./test_pdbs/missing.cif",
+            "./test_pdbs/8W2S.cif",
+        ]
+        .into_iter()
+        .map(|p| std::path::PathBuf::from(p).into())
+        .collect();
+        let options = BatchOptions {
+            threads: 2,
+            ..Default::default()
+        };
+        let mut results = Vec::new();
+        let mut updates = Vec::new();
+        let progress = run_batch(
+            &sources,
+            &options,
+            |input| {
+                assert_eq!(input.format, "cif");
+                let reader = BufReader::new(Cursor::new(input.data));
+                let (axis, _) = d_map(
+                    reader,
+                    None,
+                    Representative::Auto,
+                    input.format,
+                    &Selection::all(),
+                )?;
+                Ok(axis.len())
+            },
+            |item| results.push(item),
+            |progress| updates.push(progress),
+        );
+        assert_eq!((progress.done, progress.failed, progress.total), (3, 1, 3));
+        assert_eq!(updates.len(), 3);
+        assert_eq!(updates.last(), Some(&progress));
+        results.sort_by_key(|item| item.index);
+        assert_eq!(
+            results.iter().map(|item| item.index).collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+        assert_eq!(results[1].result.as_ref().unwrap_err().kind(), "io");
+        assert!(*results[0].result.as_ref().unwrap() > 0);
+
+        // 单个文件 panic 只算该文件失败
+        let mut failed = Vec::new();
+        let progress = run_batch(
+            &sources,
+            &options,
+            |input| {
+                if input.source.name().ends_with("7U5E.cif") {
+                    panic!("broken structure");
+                }
+                Ok(input.data.len())
+            },
+            |item| {
+                if let Err(err) = item.result {
+                    failed.push((item.index, err));
+                }
+            },
+            |_| {},
+        );
+        assert_eq!((progress.done, progress.failed), (3, 2));
+        failed.sort_by_key(|(index, _)| *index);
+        assert_eq!(failed[0].0, 0);
+        assert_eq!(
+            failed[0].1,
+            PskitError::Internal("broken structure".to_string())
+        );
+        assert_eq!(failed[1].1.kind(), "io");
+
+        assert_eq!(detect_format("x.PDB", b"data_x"), "pdb");
+        assert_eq!(detect_format("upload", b"data_7U5E\n"), "cif");
+        assert_eq!(detect_format("upload", b"ATOM  "), "pdb");
+    }
+
+    #[test]
+    fn test_base_pairs() {
+        use std::fs::File;
+        let all = Selection::all();
+        let criteria = BasePairCriteria::default();
+        let reader = |path| BufReader::new(File::open(path).unwrap());
+
+        let rna = compute_base_pairs(
+            reader("./test_pdbs/8W2S.cif"),
+            "cif",
+            ModifiedResidues::default(),
+            &all,
+            &criteria,
+        )
+        .unwrap();
+        let pair = |a: &str, b: &str| {
+            rna.pairs
+                .iter()
+                .find(|p| p.a.to_string() == a && p.b.to_string() == b)
+                .unwrap()
+        };
+        let au = pair("B-22-U", "B-57-A");
+        assert_eq!(
+            (au.family().as_str(), au.class),
+            ("cWW", PairClass::WatsonCrick)
+        );
+        assert_eq!(au.hbonds.len(), 2);
+        let gu = pair("B-61-G", "B-139-U");
+        assert_eq!((gu.family().as_str(), gu.class), ("cWW", PairClass::Wobble));
+        assert!(rna.pairs.iter().any(|p| p.family().starts_with('t')));
+        assert!(!rna.stacks.is_empty());
+
+        assert_eq!(rna.chains.len(), 1);
+        let chain = &rna.chains[0];
+        assert_eq!(chain.chain_id, "B");
+        assert_eq!(chain.dot_bracket.chars().count(), chain.residues.len());
+        assert_eq!(chain.sequence.len(), chain.residues.len());
+        let count = |c| chain.dot_bracket.matches(c).count();
+        assert_eq!(count('('), count(')'));
+        // 8W2S 含一个假结
+        assert!(count('[') > 0 && count('[') == count(']'));
+        let dbn = String::from_utf8(write_dot_bracket(&rna.chains, "8W2S")).unwrap();
+        assert_eq!(
+            dbn,
+            format!(">8W2S_B\n{}\n{}\n", chain.sequence, chain.dot_bracket)
+        );
+
+        // 7U5E：RNA 与 DNA 链之间的配对不进入单链的 dot-bracket
+        let hybrid = compute_base_pairs(
+            reader("./test_pdbs/7U5E.cif"),
+            "cif",
+            ModifiedResidues::default(),
+            &all,
+            &criteria,
+        )
+        .unwrap();
+        assert!(hybrid.pairs.iter().any(|p| p.a.chain_id != p.b.chain_id));
+        let dna = hybrid.chains.iter().find(|c| c.chain_id == "3").unwrap();
+        assert!(dna.dot_bracket.chars().all(|c| c == '.'));
+
+        let binding = compute_binding_pairs(
+            reader("./test_pdbs/8W2S.cif"),
+            3.5,
+            "cif",
+            ModifiedResidues::default(),
+            &all,
+        )
+        .unwrap();
+        assert!(binding.iter().any(|p| p.nucleic_paired));
+        assert!(binding.iter().any(|p| !p.nucleic_paired));
+        for p in &binding {
+            assert_eq!(p.nucleic_paired, rna.is_paired(&p.nucleic));
+        }
+    }
 }
@@ -0,0 +1,266 @@
+// Python bindings for pskit-core, so the AI pipeline runs the same code as the browser.
+// Structures are passed in as `bytes` plus a format ("pdb" or "cif"); files come back as
+// `bytes`, maps and per-pair values as NumPy arrays.
+
+use numpy::{IntoPyArray, PyArray2};
+use pskit_core::annotate;
+use pskit_core::chem::ModifiedResidues;
+use pskit_core::contact::{self, Representative};
+use pskit_core::export::square_from_upper;
+use pskit_core::split;
+use pskit_core::{PskitError as CoreError, ResidueNumber, ResidueRange, Selection};
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict};
+use std::collections::HashMap;
+use std::io::Cursor;
+
+create_exception!(
+    pskit,
+    PskitError,
+    PyException,
+    "Error raised by pskit; `kind` names the variant (\"parse\", \"chain_not_found\", ...)."
+);
+
+/// Convert a core error into a `PskitError` carrying `kind` and the per-kind fields, like the
+/// JS errors of the wasm build.
+fn py_error(py: Python<'_>, err: CoreError) -> PyErr {
+    let py_err = PskitError::new_err(err.to_string());
+    let value = py_err.value(py);
+    let _ = value.setattr("kind", err.kind());
+    match err {
+        CoreError::Parse(issues) => {
+            let items: Vec<_> = issues
+                .into_iter()
+                .map(|issue| {
+                    let item = PyDict::new(py);
+                    let _ = item.set_item("level", issue.level);
+                    let _ = item.set_item("message", issue.message);
+                    let _ = item.set_item("detail", issue.detail);
+                    let _ = item.set_item("line", issue.line);
+                    let _ = item.set_item("context", issue.context);
+                    item
+                })
+                .collect();
+            let _ = value.setattr("issues", items);
+        }
+        CoreError::ChainNotFound { chain_id, valid } => {
+            let _ = value.setattr("chain_id", chain_id);
+            let _ = value.setattr("valid_chains", valid);
+        }
+        CoreError::InvalidRange { chain_id, min, max } => {
+            let _ = value.setattr("chain_id", chain_id);
+            let _ = value.setattr("min", min.to_string());
+            let _ = value.setattr("max", max.to_string());
+        }
+        CoreError::InvalidArgument(_)
+        | CoreError::NotComplex
+        | CoreError::Io(_)
+        | CoreError::Internal(_) => {}
+    }
+    py_err
+}
+
+fn parse_or_default<T>(py: Python<'_>, value: Option<&str>) -> PyResult<T>
+where
+    T: std::str::FromStr<Err = CoreError> + Default,
+{
+    value
+        .map(str::parse)
+        .transpose()
+        .map(Option::unwrap_or_default)
+        .map_err(|err| py_error(py, err))
+}
+
+fn parts_dict(py: Python<'_>, parts: HashMap<String, Vec<u8>>) -> PyResult<Bound<'_, PyDict>> {
+    let dict = PyDict::new(py);
+    for (name, bytes) in parts {
+        dict.set_item(name, PyBytes::new(py, &bytes))?;
+    }
+    Ok(dict)
+}
+
+type Map<'py> = (Vec<String>, Bound<'py, PyArray2<f64>>);
+
+fn square_map(py: Python<'_>, axis: Vec<String>, upper: Vec<Vec<f64>>) -> PyResult<Map<'_>> {
+    let values = PyArray2::from_vec2(py, &square_from_upper(&upper))
+        .map_err(|err| PskitError::new_err(err.to_string()))?;
+    Ok((axis, values))
+}
+
+/// Split a protein–nucleic complex into `{"Prot": bytes, "NA": bytes}`.
+#[pyfunction]
+#[pyo3(signature = (data, format, modified=None, selection=None))]
+fn split_complex<'py>(
+    py: Python<'py>,
+    data: &[u8],
+    format: &str,
+    modified: Option<&str>,
+    selection: Option<&str>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let modified: ModifiedResidues = parse_or_default(py, modified)?;
+    let selection: Selection = parse_or_default(py, selection)?;
+    let parts = py
+        .detach(|| split::split_complex(Cursor::new(data), format, modified, &selection))
+        .map_err(|err| py_error(py, err))?;
+    parts_dict(py, parts)
+}
+
+/// Split a structure into one file per chain, `{chain_id: bytes}`.
+#[pyfunction]
+#[pyo3(signature = (data, format, selection=None))]
+fn split_by_chain<'py>(
+    py: Python<'py>,
+    data: &[u8],
+    format: &str,
+    selection: Option<&str>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let selection: Selection = parse_or_default(py, selection)?;
+    let parts = py
+        .detach(|| split::split_by_chain(Cursor::new(data), format, &selection))
+        .map_err(|err| py_error(py, err))?;
+    parts_dict(py, parts)
+}
+
+/// Cut residues `start..=end` (e.g. "52A"; open ends when omitted) out of one chain.
+///
+/// Returns `(bytes, first, last)` with the first and last residues actually modeled.
+#[pyfunction]
+#[pyo3(signature = (data, format, chain_id, start=None, end=None, selection=None))]
+fn extract_fragment<'py>(
+    py: Python<'py>,
+    data: &[u8],
+    format: &str,
+    chain_id: String,
+    start: Option<&str>,
+    end: Option<&str>,
+    selection: Option<&str>,
+) -> PyResult<(Bound<'py, PyBytes>, String, String)> {
+    let selection: Selection = parse_or_default(py, selection)?;
+    let number = |v: Option<&str>| {
+        v.filter(|s| !s.trim().is_empty())
+            .map(str::parse::<ResidueNumber>)
+            .transpose()
+            .map_err(|err| py_error(py, err))
+    };
+    let range = ResidueRange::new(number(start)?, number(end)?);
+    let (bytes, first, last) = py
+        .detach(|| split::extract_fragment(Cursor::new(data), chain_id, range, format, &selection))
+        .map_err(|err| py_error(py, err))?;
+    Ok((
+        PyBytes::new(py, &bytes),
+        first.to_string(),
+        last.to_string(),
+    ))
+}
+
+/// Residue distance map, `(axis, values)` with a symmetric `N × N` array.
+#[pyfunction]
+#[pyo3(signature = (data, format, chain_id=None, selection=None, representative=None))]
+fn d_map<'py>(
+    py: Python<'py>,
+    data: &[u8],
+    format: &str,
+    chain_id: Option<String>,
+    selection: Option<&str>,
+    representative: Option<&str>,
+) -> PyResult<Map<'py>> {
+    let selection: Selection = parse_or_default(py, selection)?;
+    let repr: Representative = parse_or_default(py, representative)?;
+    let (axis, upper) = py
+        .detach(|| contact::d_map(Cursor::new(data), chain_id, repr, format, &selection))
+        .map_err(|err| py_error(py, err))?;
+    square_map(py, axis, upper)
+}
+
+/// Distance map keeping, for each residue, the `k` nearest of the following residues (0
+/// elsewhere), as `(axis, values)` with a symmetric `N × N` array.
+#[pyfunction]
+#[pyo3(signature = (data, format, k, chain_id=None, selection=None, representative=None))]
+fn knn_map<'py>(
+    py: Python<'py>,
+    data: &[u8],
+    format: &str,
+    k: usize,
+    chain_id: Option<String>,
+    selection: Option<&str>,
+    representative: Option<&str>,
+) -> PyResult<Map<'py>> {
+    let selection: Selection = parse_or_default(py, selection)?;
+    let repr: Representative = parse_or_default(py, representative)?;
+    let (axis, upper) = py
+        .detach(|| contact::knn_map(Cursor::new(data), chain_id, k, repr, format, &selection))
+        .map_err(|err| py_error(py, err))?;
+    square_map(py, axis, upper)
+}
+
+/// Protein–nucleic residue pairs within `cutoff` Å, as a dict of parallel columns: residue
+/// ids ("A-52A-LYS"), closest atoms and region as lists, `distance`, the contact counts and
+/// `nucleic_paired` (whether the nucleotide is base-paired) as NumPy arrays.
+#[pyfunction]
+#[pyo3(signature = (data, format, cutoff, modified=None, selection=None))]
+fn compute_binding_pairs<'py>(
+    py: Python<'py>,
+    data: &[u8],
+    format: &str,
+    cutoff: f64,
+    modified: Option<&str>,
+    selection: Option<&str>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let modified: ModifiedResidues = parse_or_default(py, modified)?;
+    let selection: Selection = parse_or_default(py, selection)?;
+    let pairs = py
+        .detach(|| {
+            annotate::compute_binding_pairs(Cursor::new(data), cutoff, format, modified, &selection)
+        })
+        .map_err(|err| py_error(py, err))?;
+
+    let dict = PyDict::new(py);
+    let strings =
+        |f: &dyn Fn(&annotate::BindingPair) -> String| pairs.iter().map(f).collect::<Vec<_>>();
+    let counts = |f: &dyn Fn(&annotate::BindingPair) -> usize| {
+        pairs
+            .iter()
+            .map(|p| f(p) as u32)
+            .collect::<Vec<_>>()
+            .into_pyarray(py)
+    };
+    dict.set_item("protein", strings(&|p| p.protein.to_string()))?;
+    dict.set_item("nucleic", strings(&|p| p.nucleic.to_string()))?;
+    dict.set_item("protein_atom", strings(&|p| p.protein_atom.clone()))?;
+    dict.set_item("nucleic_atom", strings(&|p| p.nucleic_atom.clone()))?;
+    dict.set_item("region", strings(&|p| p.region().as_str().to_string()))?;
+    dict.set_item(
+        "distance",
+        pairs
+            .iter()
+            .map(|p| p.distance)
+            .collect::<Vec<_>>()
+            .into_pyarray(py),
+    )?;
+    dict.set_item("contacts", counts(&|p| p.contacts))?;
+    dict.set_item("backbone_contacts", counts(&|p| p.backbone_contacts))?;
+    dict.set_item("base_contacts", counts(&|p| p.base_contacts))?;
+    dict.set_item(
+        "nucleic_paired",
+        pairs
+            .iter()
+            .map(|p| p.nucleic_paired)
+            .collect::<Vec<_>>()
+            .into_pyarray(py),
+    )?;
+    Ok(dict)
+}
+
+#[pymodule]
+fn pskit(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("PskitError", m.py().get_type::<PskitError>())?;
+    m.add_function(wrap_pyfunction!(split_complex, m)?)?;
+    m.add_function(wrap_pyfunction!(split_by_chain, m)?)?;
+    m.add_function(wrap_pyfunction!(extract_fragment, m)?)?;
+    m.add_function(wrap_pyfunction!(d_map, m)?)?;
+    m.add_function(wrap_pyfunction!(knn_map, m)?)?;
+    m.add_function(wrap_pyfunction!(compute_binding_pairs, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,102 @@
+# Tests of the Python layer: argument defaults, NumPy conversion and error mapping.
+# Run after `maturin develop` from crates/pskit-py: `pytest tests`.
+
+from pathlib import Path
+
+import numpy as np
+import pytest
+
+import pskit
+
+TEST_PDBS = Path(__file__).resolve().parents[2] / "pskit-core" / "test_pdbs"
+
+
+@pytest.fixture(scope="module")
+def complex_cif():
+    return (TEST_PDBS / "7U5E.cif").read_bytes()
+
+
+@pytest.fixture(scope="module")
+def rna_cif():
+    return (TEST_PDBS / "8W2S.cif").read_bytes()
+
+
+def test_split_complex(complex_cif):
+    parts = pskit.split_complex(complex_cif, "cif")
+    assert set(parts) == {"Prot", "NA"}
+    assert all(isinstance(v, bytes) and v for v in parts.values())
+    assert pskit.split_complex(complex_cif, "cif", modified="polymer") == parts
+
+
+def test_split_by_chain(rna_cif):
+    chains = pskit.split_by_chain(rna_cif, "cif")
+    assert set(chains) == {"A", "B"}
+    assert all(isinstance(v, bytes) and v for v in chains.values())
+    assert list(pskit.split_by_chain(rna_cif, "cif", selection="chain B")) == ["B"]
+
+
+def test_extract_fragment(complex_cif):
+    data, first, last = pskit.extract_fragment(complex_cif, "cif", "G", "30", "50")
+    assert isinstance(data, bytes)
+    assert (first, last) == ("30", "39")
+    # 省略区间时取整条链
+    _, first, last = pskit.extract_fragment(complex_cif, "cif", "G")
+    assert int(first) <= 30 and int(last) >= 39
+
+
+def test_d_map(complex_cif):
+    axis, values = pskit.d_map(complex_cif, "cif", chain_id="G")
+    assert isinstance(values, np.ndarray)
+    assert values.dtype == np.float64
+    assert values.shape == (len(axis), len(axis))
+    assert all(label.startswith("G-") for label in axis)
+    np.testing.assert_allclose(values, values.T)
+    assert np.all(np.diag(values) == 0)
+
+    _, auto = pskit.d_map(complex_cif, "cif", chain_id="G", representative="auto")
+    np.testing.assert_array_equal(values, auto)
+    _, ca = pskit.d_map(complex_cif, "cif", chain_id="G", representative="ca")
+    assert ca.shape == values.shape
+
+
+def test_knn_map(complex_cif):
+    k = 3
+    axis, values = pskit.knn_map(complex_cif, "cif", k, chain_id="G")
+    assert values.dtype == np.float64
+    assert values.shape == (len(axis), len(axis))
+    np.testing.assert_allclose(values, values.T)
+    assert np.all(np.count_nonzero(np.triu(values, 1), axis=1) <= k)
+
+
+def test_compute_binding_pairs(complex_cif):
+    pairs = pskit.compute_binding_pairs(complex_cif, "cif", 3.5)
+    n = len(pairs["protein"])
+    assert n > 0
+    for key in ("nucleic", "protein_atom", "nucleic_atom", "region"):
+        assert isinstance(pairs[key], list) and len(pairs[key]) == n
+    assert pairs["distance"].dtype == np.float64
+    assert pairs["distance"].shape == (n,)
+    assert np.all(pairs["distance"] <= 3.5)
+    for key in ("contacts", "backbone_contacts", "base_contacts"):
+        assert pairs[key].dtype == np.uint32
+        assert pairs[key].shape == (n,)
+    assert pairs["nucleic_paired"].dtype == np.bool_
+    assert set(pairs["region"]) <= {"backbone", "base"}
+
+
+def test_errors(complex_cif):
+    with pytest.raises(pskit.PskitError) as info:
+        pskit.extract_fragment(complex_cif, "cif", "ZZ")
+    err = info.value
+    assert err.kind == "chain_not_found"
+    assert err.chain_id == "ZZ"
+    assert "G" in err.valid_chains
+
+    with pytest.raises(pskit.PskitError) as info:
+        pskit.d_map(complex_cif, "cif", selection="chain")
+    assert info.value.kind == "invalid_argument"
+
+    with pytest.raises(pskit.PskitError) as info:
+        pskit.d_map(b"ATOM      1  N   MET A   1      xx.xxx  24.447   2.594\n", "pdb")
+    assert info.value.kind == "parse"
+    assert info.value.issues
@@ -1,9 +1,74 @@
 use crate::{annotate, contact, split};
-use js_sys::{Array, Uint8Array};
+use js_sys::{Array, Object, Reflect, Uint32Array, Uint8Array};
+use pskit_core::align::{self, AlignOptions};
+use pskit_core::annotate::{LabelTarget, LabelValue};
+use pskit_core::basepair::{self, BasePairCriteria};
+use pskit_core::chem::ModifiedResidues;
+use pskit_core::contact::Representative;
+use pskit_core::dssp;
+use pskit_core::export::{self, MapFormat};
+use pskit_core::graph::{self, GraphOptions};
+use pskit_core::interaction::{self, Interaction, InteractionCriteria};
+use pskit_core::quality;
+use pskit_core::sasa::{self, SasaOptions};
+use pskit_core::sequence::{self, ChainSequence, SequenceOptions, SequenceSource};
+use pskit_core::superpose::{self, ResidueMatch};
+use pskit_core::{PskitError, ResidueNumber, ResidueRange, Selection};
 use std::collections::HashMap;
 use std::io::Cursor;
 use wasm_bindgen::prelude::*;
 
+fn set(target: &Object, key: &str, value: &JsValue) {
+    let _ = Reflect::set(target, &JsValue::from_str(key), value);
+}
+
+fn string_array<S: AsRef<str>>(items: &[S]) -> Array {
+    items
+        .iter()
+        .map(|s| JsValue::from_str(s.as_ref()))
+        .collect()
+}
+
+/// Convert a core error into a JS `Error` whose `kind` (and per-kind fields) can be branched on.
+fn js_error(err: PskitError) -> JsValue {
+    let js_err = js_sys::Error::new(&err.to_string());
+    js_err.set_name("PskitError");
+    let obj: &Object = js_err.as_ref();
+    set(obj, "kind", &JsValue::from_str(err.kind()));
+    match err {
+        PskitError::Parse(issues) => {
+            let arr = Array::new();
+            for issue in issues {
+                let item = Object::new();
+                set(&item, "level", &JsValue::from_str(&issue.level));
+                set(&item, "message", &JsValue::from_str(&issue.message));
+                set(&item, "detail", &JsValue::from_str(&issue.detail));
+                let line = issue
+                    .line
+                    .map_or(JsValue::NULL, |l| JsValue::from(l as f64));
+                set(&item, "line", &line);
+                set(&item, "context", &JsValue::from_str(&issue.context));
+                arr.push(&item);
+            }
+            set(obj, "issues", &arr);
+        }
+        PskitError::ChainNotFound { chain_id, valid } => {
+            set(obj, "chainId", &JsValue::from_str(&chain_id));
+            set(obj, "validChains", &string_array(&valid));
+        }
+        PskitError::InvalidRange { chain_id, min, max } => {
+            set(obj, "chainId", &JsValue::from_str(&chain_id));
+            set(obj, "min", &JsValue::from_str(&min.to_string()));
+            set(obj, "max", &JsValue::from_str(&max.to_string()));
+        }
+        PskitError::InvalidArgument(_) => {}
+        PskitError::NotComplex => {}
+        PskitError::Io(_) => {}
+        PskitError::Internal(_) => {}
+    }
+    js_err.into()
+}
+
 #[wasm_bindgen]
 pub struct Chunks {
     parts: HashMap<String, Vec<u8>>,
@@ -51,8 +116,8 @@ impl Chunks {
 #[wasm_bindgen]
 pub struct Fragment {
     bytes: Option<Vec<u8>>,
-    start: isize,
-    end: isize,
+    start: ResidueNumber,
+    end: ResidueNumber,
 }
 
 #[wasm_bindgen]
@@ -64,35 +129,73 @@ impl Fragment {
         self.bytes.take().map(|v| Uint8Array::from(v.as_slice()))
     }
 
+    /// First residue number of the fragment, including insertion code (e.g. "52A").
     #[wasm_bindgen(getter)]
-    pub fn start(&self) -> isize {
-        self.start
+    pub fn start(&self) -> String {
+        self.start.to_string()
     }
 
+    /// Last residue number of the fragment, including insertion code.
     #[wasm_bindgen(getter)]
-    pub fn end(&self) -> isize {
-        self.end
+    pub fn end(&self) -> String {
+        self.end.to_string()
     }
 }
 
 #[wasm_bindgen]
 pub struct ContactMap {
-    axis: Option<Vec<String>>,
+    row_axis: Option<Vec<String>>,
+    col_axis: Option<Vec<String>>,
     values: Option<Vec<f64>>,
+    triangular: bool,
+}
+
+impl ContactMap {
+    /// Upper-triangle map of one residue set (row `i` holds distances to residues `i+1..n`).
+    fn upper_triangle(axis: Vec<String>, values: Vec<Vec<f64>>) -> Self {
+        ContactMap {
+            row_axis: Some(axis.clone()),
+            col_axis: Some(axis),
+            values: Some(values.into_iter().flatten().collect()),
+            triangular: true,
+        }
+    }
+
+    /// Full `rows × cols` map between two residue sets.
+    fn full(row_axis: Vec<String>, col_axis: Vec<String>, values: Vec<Vec<f64>>) -> Self {
+        ContactMap {
+            row_axis: Some(row_axis),
+            col_axis: Some(col_axis),
+            values: Some(values.into_iter().flatten().collect()),
+            triangular: false,
+        }
+    }
 }
 
 #[wasm_bindgen]
 impl ContactMap {
-    /// Take the axis labels (consuming).
+    /// Take the axis labels (consuming). Same as `take_row_axis`.
     #[wasm_bindgen]
     pub fn take_axis(&mut self) -> Option<Array> {
-        self.axis.take().map(|axis| {
-            let arr = Array::new();
-            for res_name in axis {
-                arr.push(&JsValue::from_str(&res_name));
-            }
-            arr
-        })
+        self.take_row_axis()
+    }
+
+    /// Take the row axis labels (consuming).
+    #[wasm_bindgen]
+    pub fn take_row_axis(&mut self) -> Option<Array> {
+        self.row_axis.take().map(|axis| string_array(&axis))
+    }
+
+    /// Take the column axis labels (consuming).
+    #[wasm_bindgen]
+    pub fn take_col_axis(&mut self) -> Option<Array> {
+        self.col_axis.take().map(|axis| string_array(&axis))
+    }
+
+    /// Whether `values` is an upper triangle (square maps) or the full row-major matrix.
+    #[wasm_bindgen(getter)]
+    pub fn triangular(&self) -> bool {
+        self.triangular
     }
 
     /// Take the values as a flat Float64Array (consuming, row-major order).
@@ -104,46 +207,478 @@ impl ContactMap {
     }
 }
 
+#[wasm_bindgen]
+pub struct SparseContactMap {
+    axis: Option<Vec<String>>,
+    indptr: Option<Vec<u32>>,
+    rows: Option<Vec<u32>>,
+    cols: Option<Vec<u32>>,
+    distances: Option<Vec<f64>>,
+}
+
+#[wasm_bindgen]
+impl SparseContactMap {
+    /// Take the axis labels (consuming).
+    #[wasm_bindgen]
+    pub fn take_axis(&mut self) -> Option<Array> {
+        self.axis.take().map(|axis| string_array(&axis))
+    }
+
+    /// Take the CSR row pointer as Uint32Array of length `axis.length + 1` (consuming).
+    #[wasm_bindgen]
+    pub fn take_indptr(&mut self) -> Option<Uint32Array> {
+        self.indptr.take().map(|v| Uint32Array::from(v.as_slice()))
+    }
+
+    /// Take the row index of every contact as Uint32Array (consuming).
+    #[wasm_bindgen]
+    pub fn take_rows(&mut self) -> Option<Uint32Array> {
+        self.rows.take().map(|v| Uint32Array::from(v.as_slice()))
+    }
+
+    /// Take the column index of every contact as Uint32Array (consuming).
+    #[wasm_bindgen]
+    pub fn take_cols(&mut self) -> Option<Uint32Array> {
+        self.cols.take().map(|v| Uint32Array::from(v.as_slice()))
+    }
+
+    /// Take the contact distances as Float64Array (consuming).
+    #[wasm_bindgen]
+    pub fn take_distances(&mut self) -> Option<js_sys::Float64Array> {
+        self.distances
+            .take()
+            .map(|v| js_sys::Float64Array::from(v.as_slice()))
+    }
+}
+
 #[wasm_bindgen]
 pub struct BindingPairs {
-    pairs: Option<Vec<String>>,
+    protein_chains: Option<Vec<String>>,
+    protein_numbers: Option<Vec<String>>,
+    protein_names: Option<Vec<String>>,
+    protein_atoms: Option<Vec<String>>,
+    nucleic_chains: Option<Vec<String>>,
+    nucleic_numbers: Option<Vec<String>>,
+    nucleic_names: Option<Vec<String>>,
+    nucleic_atoms: Option<Vec<String>>,
+    regions: Option<Vec<String>>,
     distances: Option<Vec<f64>>,
+    contacts: Option<Vec<u32>>,
+    backbone_contacts: Option<Vec<u32>>,
+    base_contacts: Option<Vec<u32>>,
+    nucleic_paired: Option<Vec<u8>>,
+}
+
+impl BindingPairs {
+    fn new(pairs: Vec<annotate::BindingPair>) -> Self {
+        let col =
+            |f: &dyn Fn(&annotate::BindingPair) -> String| Some(pairs.iter().map(f).collect());
+        let count = |f: &dyn Fn(&annotate::BindingPair) -> usize| {
+            Some(pairs.iter().map(|p| f(p) as u32).collect())
+        };
+        BindingPairs {
+            protein_chains: col(&|p| p.protein.chain_id.clone()),
+            protein_numbers: col(&|p| p.protein.number.to_string()),
+            protein_names: col(&|p| p.protein.name.clone()),
+            protein_atoms: col(&|p| p.protein_atom.clone()),
+            nucleic_chains: col(&|p| p.nucleic.chain_id.clone()),
+            nucleic_numbers: col(&|p| p.nucleic.number.to_string()),
+            nucleic_names: col(&|p| p.nucleic.name.clone()),
+            nucleic_atoms: col(&|p| p.nucleic_atom.clone()),
+            regions: col(&|p| p.region().as_str().to_string()),
+            distances: Some(pairs.iter().map(|p| p.distance).collect()),
+            contacts: count(&|p| p.contacts),
+            backbone_contacts: count(&|p| p.backbone_contacts),
+            base_contacts: count(&|p| p.base_contacts),
+            nucleic_paired: Some(pairs.iter().map(|p| p.nucleic_paired as u8).collect()),
+        }
+    }
+}
+
+fn take_strings(field: &mut Option<Vec<String>>) -> Option<Array> {
+    field.take().map(|v| string_array(&v))
+}
+
+fn take_u32(field: &mut Option<Vec<u32>>) -> Option<Uint32Array> {
+    field.take().map(|v| Uint32Array::from(v.as_slice()))
 }
 
+/// Parallel arrays, one entry per protein–nucleic residue pair (all getters consume).
 #[wasm_bindgen]
 impl BindingPairs {
-    /// Take the pairs array (consuming).
     #[wasm_bindgen]
-    pub fn take_pairs(&mut self) -> Option<Array> {
-        self.pairs.take().map(|pairs| {
-            let arr = Array::new();
-            for pair in pairs {
-                arr.push(&JsValue::from_str(&pair));
-            }
-            arr
-        })
+    pub fn take_protein_chains(&mut self) -> Option<Array> {
+        take_strings(&mut self.protein_chains)
+    }
+
+    /// Residue numbers as strings, including insertion codes ("52A").
+    #[wasm_bindgen]
+    pub fn take_protein_numbers(&mut self) -> Option<Array> {
+        take_strings(&mut self.protein_numbers)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_protein_names(&mut self) -> Option<Array> {
+        take_strings(&mut self.protein_names)
+    }
+
+    /// Protein atom of the closest atom pair.
+    #[wasm_bindgen]
+    pub fn take_protein_atoms(&mut self) -> Option<Array> {
+        take_strings(&mut self.protein_atoms)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_nucleic_chains(&mut self) -> Option<Array> {
+        take_strings(&mut self.nucleic_chains)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_nucleic_numbers(&mut self) -> Option<Array> {
+        take_strings(&mut self.nucleic_numbers)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_nucleic_names(&mut self) -> Option<Array> {
+        take_strings(&mut self.nucleic_names)
+    }
+
+    /// Nucleotide atom of the closest atom pair.
+    #[wasm_bindgen]
+    pub fn take_nucleic_atoms(&mut self) -> Option<Array> {
+        take_strings(&mut self.nucleic_atoms)
+    }
+
+    /// "backbone" or "base": nucleotide region of the closest atom pair.
+    #[wasm_bindgen]
+    pub fn take_regions(&mut self) -> Option<Array> {
+        take_strings(&mut self.regions)
+    }
+
+    /// Closest atom-pair distances as Float64Array.
+    #[wasm_bindgen]
+    pub fn take_distances(&mut self) -> Option<js_sys::Float64Array> {
+        self.distances
+            .take()
+            .map(|v| js_sys::Float64Array::from(v.as_slice()))
+    }
+
+    /// Atom pairs under the cutoff as Uint32Array.
+    #[wasm_bindgen]
+    pub fn take_contacts(&mut self) -> Option<Uint32Array> {
+        take_u32(&mut self.contacts)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_backbone_contacts(&mut self) -> Option<Uint32Array> {
+        take_u32(&mut self.backbone_contacts)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_base_contacts(&mut self) -> Option<Uint32Array> {
+        take_u32(&mut self.base_contacts)
+    }
+
+    /// 1 where the nucleotide forms a base pair, 0 where it is unpaired.
+    #[wasm_bindgen]
+    pub fn take_nucleic_paired(&mut self) -> Option<Uint8Array> {
+        self.nucleic_paired
+            .take()
+            .map(|v| Uint8Array::from(v.as_slice()))
+    }
+}
+
+#[wasm_bindgen]
+pub struct Interactions {
+    kinds: Option<Vec<String>>,
+    protein_chains: Option<Vec<String>>,
+    protein_numbers: Option<Vec<String>>,
+    protein_names: Option<Vec<String>>,
+    protein_atoms: Option<Vec<String>>,
+    nucleic_chains: Option<Vec<String>>,
+    nucleic_numbers: Option<Vec<String>>,
+    nucleic_names: Option<Vec<String>>,
+    nucleic_atoms: Option<Vec<String>>,
+    distances: Option<Vec<f64>>,
+    angles: Option<Vec<f64>>,
+}
+
+impl Interactions {
+    fn new(found: Vec<Interaction>) -> Self {
+        let col = |f: &dyn Fn(&Interaction) -> String| Some(found.iter().map(f).collect());
+        Interactions {
+            kinds: col(&|i| i.kind.as_str().to_string()),
+            protein_chains: col(&|i| i.protein.chain_id.clone()),
+            protein_numbers: col(&|i| i.protein.number.to_string()),
+            protein_names: col(&|i| i.protein.name.clone()),
+            protein_atoms: col(&|i| i.protein_atom.clone()),
+            nucleic_chains: col(&|i| i.nucleic.chain_id.clone()),
+            nucleic_numbers: col(&|i| i.nucleic.number.to_string()),
+            nucleic_names: col(&|i| i.nucleic.name.clone()),
+            nucleic_atoms: col(&|i| i.nucleic_atom.clone()),
+            distances: Some(found.iter().map(|i| i.distance).collect()),
+            angles: Some(found.iter().map(|i| i.angle.unwrap_or(f64::NAN)).collect()),
+        }
+    }
+}
+
+/// Parallel arrays, one entry per classified contact (all getters consume).
+#[wasm_bindgen]
+impl Interactions {
+    /// "hbond", "salt_bridge", "pi_stacking", "cation_pi" or "vdw".
+    #[wasm_bindgen]
+    pub fn take_kinds(&mut self) -> Option<Array> {
+        take_strings(&mut self.kinds)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_protein_chains(&mut self) -> Option<Array> {
+        take_strings(&mut self.protein_chains)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_protein_numbers(&mut self) -> Option<Array> {
+        take_strings(&mut self.protein_numbers)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_protein_names(&mut self) -> Option<Array> {
+        take_strings(&mut self.protein_names)
+    }
+
+    /// Protein atom, or ring atoms joined by "+".
+    #[wasm_bindgen]
+    pub fn take_protein_atoms(&mut self) -> Option<Array> {
+        take_strings(&mut self.protein_atoms)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_nucleic_chains(&mut self) -> Option<Array> {
+        take_strings(&mut self.nucleic_chains)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_nucleic_numbers(&mut self) -> Option<Array> {
+        take_strings(&mut self.nucleic_numbers)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_nucleic_names(&mut self) -> Option<Array> {
+        take_strings(&mut self.nucleic_names)
+    }
+
+    /// Nucleotide atom, or ring atoms joined by "+".
+    #[wasm_bindgen]
+    pub fn take_nucleic_atoms(&mut self) -> Option<Array> {
+        take_strings(&mut self.nucleic_atoms)
     }
 
-    /// Take the distances as Float64Array (consuming).
     #[wasm_bindgen]
     pub fn take_distances(&mut self) -> Option<js_sys::Float64Array> {
         self.distances
             .take()
             .map(|v| js_sys::Float64Array::from(v.as_slice()))
     }
+
+    /// Geometry angle in degrees; NaN for salt bridges and vdW contacts.
+    #[wasm_bindgen]
+    pub fn take_angles(&mut self) -> Option<js_sys::Float64Array> {
+        self.angles
+            .take()
+            .map(|v| js_sys::Float64Array::from(v.as_slice()))
+    }
+}
+
+/// Read overrides such as `{ hbondDistance: 3.2, stackingAngle: 20 }` onto the default criteria.
+fn interaction_criteria(criteria: Option<Object>) -> Result<InteractionCriteria, JsValue> {
+    let mut out = InteractionCriteria::default();
+    let Some(criteria) = criteria else {
+        return Ok(out);
+    };
+    let fields: [(&str, &mut f64); 8] = [
+        ("hbondDistance", &mut out.hbond_distance),
+        ("hbondAngle", &mut out.hbond_angle),
+        ("saltBridgeDistance", &mut out.salt_bridge_distance),
+        ("stackingDistance", &mut out.stacking_distance),
+        ("stackingAngle", &mut out.stacking_angle),
+        ("cationPiDistance", &mut out.cation_pi_distance),
+        ("cationPiAngle", &mut out.cation_pi_angle),
+        ("vdwTolerance", &mut out.vdw_tolerance),
+    ];
+    for (key, field) in fields {
+        let value = Reflect::get(&criteria, &JsValue::from_str(key))?;
+        if value.is_undefined() || value.is_null() {
+            continue;
+        }
+        *field = value.as_f64().ok_or_else(|| {
+            js_error(PskitError::InvalidArgument(format!(
+                "Interaction criterion {key} must be a number"
+            )))
+        })?;
+    }
+    Ok(out)
+}
+
+/// Read base-pair criteria (`hbondDistance`, `pairAngle`, `pairOffset`, `stackingDistance`,
+/// `stackingAngle`, `stackingRise`) from an optional JS object.
+fn base_pair_criteria(criteria: Option<Object>) -> Result<BasePairCriteria, JsValue> {
+    let mut out = BasePairCriteria::default();
+    let Some(criteria) = criteria else {
+        return Ok(out);
+    };
+    let fields: [(&str, &mut f64); 6] = [
+        ("hbondDistance", &mut out.hbond_distance),
+        ("pairAngle", &mut out.pair_angle),
+        ("pairOffset", &mut out.pair_offset),
+        ("stackingDistance", &mut out.stacking_distance),
+        ("stackingAngle", &mut out.stacking_angle),
+        ("stackingRise", &mut out.stacking_rise),
+    ];
+    for (key, field) in fields {
+        let value = Reflect::get(&criteria, &JsValue::from_str(key))?;
+        if value.is_undefined() || value.is_null() {
+            continue;
+        }
+        *field = value.as_f64().ok_or_else(|| {
+            js_error(PskitError::InvalidArgument(format!(
+                "Base-pair criterion {key} must be a number"
+            )))
+        })?;
+    }
+    Ok(out)
+}
+
+/// Read `probeRadius`, `nPoints` and `includeHydrogens` from an optional JS object.
+fn sasa_options(options: Option<Object>) -> Result<SasaOptions, JsValue> {
+    let mut out = SasaOptions::default();
+    let Some(options) = options else {
+        return Ok(out);
+    };
+    let get = |key: &str| -> Result<Option<JsValue>, JsValue> {
+        let value = Reflect::get(&options, &JsValue::from_str(key))?;
+        Ok((!value.is_undefined() && !value.is_null()).then_some(value))
+    };
+    let invalid = |key: &str, kind: &str| {
+        js_error(PskitError::InvalidArgument(format!(
+            "SASA option {key} must be a {kind}"
+        )))
+    };
+    if let Some(v) = get("probeRadius")? {
+        out.probe_radius = v.as_f64().ok_or_else(|| invalid("probeRadius", "number"))?;
+    }
+    if let Some(v) = get("nPoints")? {
+        let n = v.as_f64().ok_or_else(|| invalid("nPoints", "number"))?;
+        out.n_points = n.max(1.0) as usize;
+    }
+    if let Some(v) = get("includeHydrogens")? {
+        out.include_hydrogens = v
+            .as_bool()
+            .ok_or_else(|| invalid("includeHydrogens", "boolean"))?;
+    }
+    Ok(out)
+}
+
+/// Parse the optional "polymer" / "ligand" switch for modified residues (default: polymer).
+fn modified_mode(modified: Option<String>) -> Result<ModifiedResidues, JsValue> {
+    modified
+        .map(|m| m.parse())
+        .transpose()
+        .map(Option::unwrap_or_default)
+        .map_err(js_error)
+}
+
+/// Parse an optional atom selection such as "chain A and not water" (default: everything).
+fn selection_of(selection: Option<String>) -> Result<Selection, JsValue> {
+    selection
+        .map(|s| s.parse())
+        .transpose()
+        .map(Option::unwrap_or_default)
+        .map_err(js_error)
+}
+
+/// Parse an optional representative atom ("ca", "cb", "c1'", "p", "sidechain", "min_heavy",
+/// "centroid").
+fn representative_of(representative: Option<String>) -> Result<Representative, JsValue> {
+    representative
+        .map(|r| r.parse())
+        .transpose()
+        .map(Option::unwrap_or_default)
+        .map_err(js_error)
+}
+
+#[wasm_bindgen]
+pub struct BindingFrequencies {
+    pairs: Option<Vec<String>>,
+    frequencies: Option<Vec<f64>>,
+}
+
+#[wasm_bindgen]
+impl BindingFrequencies {
+    /// Take the pairs array (consuming).
+    #[wasm_bindgen]
+    pub fn take_pairs(&mut self) -> Option<Array> {
+        self.pairs.take().map(|pairs| string_array(&pairs))
+    }
+
+    /// Take the per-pair fraction of models in contact as Float64Array (consuming).
+    #[wasm_bindgen]
+    pub fn take_frequencies(&mut self) -> Option<js_sys::Float64Array> {
+        self.frequencies
+            .take()
+            .map(|v| js_sys::Float64Array::from(v.as_slice()))
+    }
+}
+
+#[wasm_bindgen]
+pub fn split_complex(
+    input: &[u8],
+    format: &str,
+    modified: Option<String>,
+    selection: Option<String>,
+) -> Result<Chunks, JsValue> {
+    let modified = modified_mode(modified)?;
+    let selection = selection_of(selection)?;
+    let cursor = Cursor::new(input);
+    let parts = split::split_complex(cursor, format, modified, &selection).map_err(js_error)?;
+    Ok(Chunks { parts })
+}
+
+/// Keep only the atoms matched by `selection`, returned as a structure file in `format`.
+#[wasm_bindgen]
+pub fn extract_selection(
+    input: &[u8],
+    format: &str,
+    modified: Option<String>,
+    selection: &str,
+) -> Result<Uint8Array, JsValue> {
+    let modified = modified_mode(modified)?;
+    let selection: Selection = selection.parse().map_err(js_error)?;
+    let cursor = Cursor::new(input);
+    let bytes = split::extract_selection(cursor, format, modified, &selection).map_err(js_error)?;
+    Ok(Uint8Array::from(bytes.as_slice()))
 }
 
 #[wasm_bindgen]
-pub fn split_complex(input: &[u8], format: &str) -> Result<Chunks, JsValue> {
+pub fn split_by_chain(
+    input: &[u8],
+    format: &str,
+    selection: Option<String>,
+) -> Result<Chunks, JsValue> {
+    let selection = selection_of(selection)?;
     let cursor = Cursor::new(input);
-    let parts = split::split_complex(cursor, format).map_err(|e| JsValue::from_str(&e))?;
+    let parts = split::split_by_chain(cursor, format, &selection).map_err(js_error)?;
     Ok(Chunks { parts })
 }
 
 #[wasm_bindgen]
-pub fn split_by_chain(input: &[u8], format: &str) -> Result<Chunks, JsValue> {
+pub fn split_by_model(
+    input: &[u8],
+    format: &str,
+    selection: Option<String>,
+) -> Result<Chunks, JsValue> {
+    let selection = selection_of(selection)?;
     let cursor = Cursor::new(input);
-    let parts = split::split_by_chain(cursor, format).map_err(|e| JsValue::from_str(&e))?;
+    let parts = split::split_by_model(cursor, format, &selection).map_err(js_error)?;
     Ok(Chunks { parts })
 }
 
@@ -151,13 +686,22 @@ pub fn split_by_chain(input: &[u8], format: &str) -> Result<Chunks, JsValue> {
 pub fn extract_fragment(
     input: &[u8],
     chain_id: String,
-    start: Option<isize>,
-    end: Option<isize>,
+    start: Option<String>,
+    end: Option<String>,
     format: &str,
+    selection: Option<String>,
 ) -> Result<Fragment, JsValue> {
+    let selection = selection_of(selection)?;
+    let parse = |v: Option<String>| {
+        v.filter(|s| !s.trim().is_empty())
+            .map(|s| s.parse::<ResidueNumber>())
+            .transpose()
+            .map_err(js_error)
+    };
+    let range = ResidueRange::new(parse(start)?, parse(end)?);
     let cursor = Cursor::new(input);
-    let (bytes, start, end) = split::extract_fragment(cursor, chain_id, start, end, format)
-        .map_err(|e| JsValue::from_str(&e))?;
+    let (bytes, start, end) =
+        split::extract_fragment(cursor, chain_id, range, format, &selection).map_err(js_error)?;
     Ok(Fragment {
         bytes: Some(bytes),
         start,
@@ -165,31 +709,1327 @@ pub fn extract_fragment(
     })
 }
 
+/// Distance map; with `gapped` the axis of the single chain `chain_id` runs over its deposited
+/// sequence and unmodeled residues are NaN.
+#[wasm_bindgen]
+pub fn d_map(
+    input: &[u8],
+    chain_id: Option<String>,
+    format: &str,
+    selection: Option<String>,
+    representative: Option<String>,
+    gapped: Option<bool>,
+) -> Result<ContactMap, JsValue> {
+    let selection = selection_of(selection)?;
+    let repr = representative_of(representative)?;
+    let cursor = Cursor::new(input);
+    let (axis, values) = match (gapped.unwrap_or(false), chain_id) {
+        (true, Some(chain_id)) => contact::d_map_gapped(cursor, chain_id, repr, format, &selection),
+        (true, None) => Err(PskitError::InvalidArgument(
+            "A gapped distance map needs a chain_id".to_string(),
+        )),
+        (false, chain_id) => contact::d_map(cursor, chain_id, repr, format, &selection),
+    }
+    .map_err(js_error)?;
+
+    Ok(ContactMap::upper_triangle(axis, values))
+}
+
 #[wasm_bindgen]
-pub fn d_map(input: &[u8], chain_id: Option<String>, format: &str) -> Result<ContactMap, JsValue> {
+pub fn knn_map(
+    input: &[u8],
+    chain_id: Option<String>,
+    k: usize,
+    format: &str,
+    selection: Option<String>,
+    representative: Option<String>,
+) -> Result<ContactMap, JsValue> {
+    let selection = selection_of(selection)?;
+    let repr = representative_of(representative)?;
     let cursor = Cursor::new(input);
     let (axis, values) =
-        contact::d_map(cursor, chain_id, format).map_err(|e| JsValue::from_str(&e))?;
+        contact::knn_map(cursor, chain_id, k, repr, format, &selection).map_err(js_error)?;
 
-    Ok(ContactMap {
-        axis: Some(axis),
-        values: Some(values.into_iter().flatten().collect()),
-    })
+    Ok(ContactMap::upper_triangle(axis, values))
 }
 
+/// Serialize a map taken from a `ContactMap` as "npy", "csv" or "rr".
+///
+/// Without `col_axis` the values are an upper triangle over `axis` (from `d_map`/`knn_map`);
+/// with it they are the full row-major matrix of `d_map_between` (RR is not available then).
 #[wasm_bindgen]
-pub fn annotate_binding_pairs(
+pub fn write_contact_map(
+    axis: Vec<String>,
+    col_axis: Option<Vec<String>>,
+    values: &[f64],
+    output: &str,
+) -> Result<Uint8Array, JsValue> {
+    let output: MapFormat = output.parse().map_err(js_error)?;
+    let bytes = match col_axis {
+        None => {
+            let n = axis.len();
+            if values.len() != n * n.saturating_sub(1) / 2 {
+                return Err(js_error(PskitError::InvalidArgument(format!(
+                    "Expected {} upper-triangle values for {n} residues, got {}",
+                    n * n.saturating_sub(1) / 2,
+                    values.len()
+                ))));
+            }
+            let mut rest = values;
+            let upper: Vec<Vec<f64>> = (0..n)
+                .map(|i| {
+                    let (row, tail) = rest.split_at(n - i - 1);
+                    rest = tail;
+                    row.to_vec()
+                })
+                .collect();
+            export::write_map(&axis, &upper, output)
+        }
+        Some(col_axis) => {
+            if values.len() != axis.len() * col_axis.len() {
+                return Err(js_error(PskitError::InvalidArgument(format!(
+                    "Expected {}×{} values, got {}",
+                    axis.len(),
+                    col_axis.len(),
+                    values.len()
+                ))));
+            }
+            let full: Vec<Vec<f64>> = values
+                .chunks(col_axis.len().max(1))
+                .map(<[f64]>::to_vec)
+                .collect();
+            match output {
+                MapFormat::Npy => export::write_npy(&full),
+                MapFormat::Csv => export::write_csv(&axis, &col_axis, &full),
+                MapFormat::CaspRr => {
+                    return Err(js_error(PskitError::InvalidArgument(
+                        "RR output needs a square map".to_string(),
+                    )))
+                }
+            }
+        }
+    };
+    Ok(Uint8Array::from(bytes.as_slice()))
+}
+
+/// Residue pairs closer than `cutoff` Å as parallel typed arrays (COO plus CSR row pointer).
+#[wasm_bindgen]
+pub fn contact_list(
     input: &[u8],
+    chain_id: Option<String>,
     cutoff: f64,
     format: &str,
-) -> Result<BindingPairs, JsValue> {
+    selection: Option<String>,
+    representative: Option<String>,
+) -> Result<SparseContactMap, JsValue> {
+    let selection = selection_of(selection)?;
+    let repr = representative_of(representative)?;
     let cursor = Cursor::new(input);
-    let pairs = annotate::compute_binding_pairs(cursor, cutoff, format)
-        .map_err(|e| JsValue::from_str(&e))?;
-    let (pairs, distances): (Vec<String>, Vec<f64>) = pairs.into_iter().unzip();
+    let contacts = contact::contact_list(cursor, chain_id, cutoff, repr, format, &selection)
+        .map_err(js_error)?;
+    let (indptr, _, _) = contacts.to_csr();
 
-    Ok(BindingPairs {
-        pairs: Some(pairs),
-        distances: Some(distances),
+    Ok(SparseContactMap {
+        axis: Some(contacts.axis),
+        indptr: Some(indptr),
+        rows: Some(contacts.rows),
+        cols: Some(contacts.cols),
+        distances: Some(contacts.distances),
+    })
+}
+
+#[wasm_bindgen]
+pub struct ResidueGraphArrays {
+    axis: Option<Vec<String>>,
+    sources: Option<Vec<u32>>,
+    targets: Option<Vec<u32>>,
+    features: Option<Vec<f64>>,
+    coords: Option<Vec<f64>>,
+}
+
+/// Residue graph as typed arrays (getters consume): one source/target per directed edge,
+/// row-major `E × 6` edge features (distance, dx, dy, dz, separation, weight) and `N × 3`
+/// node coordinates.
+#[wasm_bindgen]
+impl ResidueGraphArrays {
+    #[wasm_bindgen]
+    pub fn take_axis(&mut self) -> Option<Array> {
+        take_strings(&mut self.axis)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_sources(&mut self) -> Option<Uint32Array> {
+        take_u32(&mut self.sources)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_targets(&mut self) -> Option<Uint32Array> {
+        take_u32(&mut self.targets)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_features(&mut self) -> Option<js_sys::Float64Array> {
+        take_f64(&mut self.features)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_coords(&mut self) -> Option<js_sys::Float64Array> {
+        take_f64(&mut self.coords)
+    }
+}
+
+fn read_graph(
+    input: &[u8],
+    chain_id: Option<String>,
+    format: &str,
+    selection: Option<String>,
+    representative: Option<String>,
+    edges: Option<String>,
+) -> Result<graph::ResidueGraph, JsValue> {
+    let selection = selection_of(selection)?;
+    let defaults = GraphOptions::default();
+    let options = GraphOptions {
+        edges: edges
+            .map(|e| e.parse())
+            .transpose()
+            .map_err(js_error)?
+            .unwrap_or(defaults.edges),
+        repr: match representative {
+            Some(_) => representative_of(representative)?,
+            None => defaults.repr,
+        },
+    };
+    graph::residue_graph(Cursor::new(input), chain_id, options, format, &selection)
+        .map_err(js_error)
+}
+
+/// Residue graph for GNN inputs; `edges` is "radius:<Å>" (default "radius:12") or "knn:<k>",
+/// and nodes sit at the residue centroid unless `representative` says otherwise.
+#[wasm_bindgen]
+pub fn residue_graph(
+    input: &[u8],
+    chain_id: Option<String>,
+    format: &str,
+    selection: Option<String>,
+    representative: Option<String>,
+    edges: Option<String>,
+) -> Result<ResidueGraphArrays, JsValue> {
+    let graph = read_graph(input, chain_id, format, selection, representative, edges)?;
+    Ok(ResidueGraphArrays {
+        sources: Some(graph.edges.iter().map(|&(s, _)| s).collect()),
+        targets: Some(graph.edges.iter().map(|&(_, t)| t).collect()),
+        features: Some(graph.features.iter().flatten().copied().collect()),
+        coords: Some(graph.coords.iter().flatten().copied().collect()),
+        axis: Some(graph.axis),
+    })
+}
+
+/// Residue graph as an `.npz` archive with `edge_index`, `edge_attr` and `coords`.
+#[wasm_bindgen]
+pub fn write_residue_graph_npz(
+    input: &[u8],
+    chain_id: Option<String>,
+    format: &str,
+    selection: Option<String>,
+    representative: Option<String>,
+    edges: Option<String>,
+) -> Result<Uint8Array, JsValue> {
+    let graph = read_graph(input, chain_id, format, selection, representative, edges)?;
+    Ok(Uint8Array::from(graph.to_npz().as_slice()))
+}
+
+/// Full distance map between two selections, e.g. `rows = "chain H"`, `cols = "chain A"`.
+#[wasm_bindgen]
+pub fn d_map_between(
+    input: &[u8],
+    rows: &str,
+    cols: &str,
+    format: &str,
+) -> Result<ContactMap, JsValue> {
+    let rows: Selection = rows.parse().map_err(js_error)?;
+    let cols: Selection = cols.parse().map_err(js_error)?;
+    let cursor = Cursor::new(input);
+    let (row_axis, col_axis, values) =
+        contact::d_map_between(cursor, &rows, &cols, format).map_err(js_error)?;
+
+    Ok(ContactMap::full(row_axis, col_axis, values))
+}
+
+/// Contacts and interface labels of [`annotate_interface`].
+#[wasm_bindgen]
+pub struct InterfaceAnnotation {
+    a_residues: Option<Vec<String>>,
+    b_residues: Option<Vec<String>>,
+    a_atoms: Option<Vec<String>>,
+    b_atoms: Option<Vec<String>>,
+    distances: Option<Vec<f64>>,
+    contacts: Option<Vec<u32>>,
+    residues: Option<Vec<String>>,
+    sides: Option<Vec<String>>,
+    residue_distances: Option<Vec<f64>>,
+    partners: Option<Vec<u32>>,
+}
+
+impl InterfaceAnnotation {
+    fn new(interface: annotate::Interface) -> Self {
+        let contacts = &interface.contacts;
+        let residues = &interface.residues;
+        InterfaceAnnotation {
+            a_residues: Some(contacts.iter().map(|c| c.a.to_string()).collect()),
+            b_residues: Some(contacts.iter().map(|c| c.b.to_string()).collect()),
+            a_atoms: Some(contacts.iter().map(|c| c.a_atom.clone()).collect()),
+            b_atoms: Some(contacts.iter().map(|c| c.b_atom.clone()).collect()),
+            distances: Some(contacts.iter().map(|c| c.distance).collect()),
+            contacts: Some(contacts.iter().map(|c| c.contacts as u32).collect()),
+            residues: Some(residues.iter().map(|r| r.key.to_string()).collect()),
+            sides: Some(
+                residues
+                    .iter()
+                    .map(|r| match r.side {
+                        annotate::InterfaceSide::A => "a".to_string(),
+                        annotate::InterfaceSide::B => "b".to_string(),
+                    })
+                    .collect(),
+            ),
+            residue_distances: Some(
+                residues
+                    .iter()
+                    .map(|r| r.distance.unwrap_or(f64::NAN))
+                    .collect(),
+            ),
+            partners: Some(residues.iter().map(|r| r.partners as u32).collect()),
+        }
+    }
+}
+
+/// Residue pairs in contact (`take_a_residues` .. `take_contacts`) and one interface label
+/// per selected residue (`take_residues` .. `take_partners`); all getters consume.
+#[wasm_bindgen]
+impl InterfaceAnnotation {
+    /// Residue labels of side A, formatted "A-52A-ARG".
+    #[wasm_bindgen]
+    pub fn take_a_residues(&mut self) -> Option<Array> {
+        take_strings(&mut self.a_residues)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_b_residues(&mut self) -> Option<Array> {
+        take_strings(&mut self.b_residues)
+    }
+
+    /// Side A atom of the closest atom pair.
+    #[wasm_bindgen]
+    pub fn take_a_atoms(&mut self) -> Option<Array> {
+        take_strings(&mut self.a_atoms)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_b_atoms(&mut self) -> Option<Array> {
+        take_strings(&mut self.b_atoms)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_distances(&mut self) -> Option<js_sys::Float64Array> {
+        self.distances
+            .take()
+            .map(|v| js_sys::Float64Array::from(v.as_slice()))
+    }
+
+    #[wasm_bindgen]
+    pub fn take_contacts(&mut self) -> Option<Uint32Array> {
+        take_u32(&mut self.contacts)
+    }
+
+    /// Every residue of selection A followed by every residue of selection B.
+    #[wasm_bindgen]
+    pub fn take_residues(&mut self) -> Option<Array> {
+        take_strings(&mut self.residues)
+    }
+
+    /// "a" or "b".
+    #[wasm_bindgen]
+    pub fn take_sides(&mut self) -> Option<Array> {
+        take_strings(&mut self.sides)
+    }
+
+    /// Distance to the other side; NaN for residues outside the interface.
+    #[wasm_bindgen]
+    pub fn take_residue_distances(&mut self) -> Option<js_sys::Float64Array> {
+        self.residue_distances
+            .take()
+            .map(|v| js_sys::Float64Array::from(v.as_slice()))
+    }
+
+    #[wasm_bindgen]
+    pub fn take_partners(&mut self) -> Option<Uint32Array> {
+        take_u32(&mut self.partners)
+    }
+}
+
+/// Distance map over all models, combined by `aggregate` ("mean", "min", "max" or "std").
+#[wasm_bindgen]
+pub fn d_map_models(
+    input: &[u8],
+    chain_id: Option<String>,
+    aggregate: &str,
+    format: &str,
+    selection: Option<String>,
+) -> Result<ContactMap, JsValue> {
+    let aggregate = aggregate.parse().map_err(js_error)?;
+    let selection = selection_of(selection)?;
+    let cursor = Cursor::new(input);
+    let (axis, values) =
+        contact::d_map_models(cursor, chain_id, aggregate, format, &selection).map_err(js_error)?;
+
+    Ok(ContactMap::upper_triangle(axis, values))
+}
+
+#[wasm_bindgen]
+pub fn annotate_binding_pairs(
+    input: &[u8],
+    cutoff: f64,
+    format: &str,
+    modified: Option<String>,
+    selection: Option<String>,
+) -> Result<BindingPairs, JsValue> {
+    let modified = modified_mode(modified)?;
+    let selection = selection_of(selection)?;
+    let cursor = Cursor::new(input);
+    let pairs = annotate::compute_binding_pairs(cursor, cutoff, format, modified, &selection)
+        .map_err(js_error)?;
+
+    Ok(BindingPairs::new(pairs))
+}
+
+/// Classify protein–nucleic contacts; `criteria` optionally overrides the default geometry.
+#[wasm_bindgen]
+pub fn annotate_interactions(
+    input: &[u8],
+    format: &str,
+    modified: Option<String>,
+    selection: Option<String>,
+    criteria: Option<Object>,
+) -> Result<Interactions, JsValue> {
+    let modified = modified_mode(modified)?;
+    let selection = selection_of(selection)?;
+    let criteria = interaction_criteria(criteria)?;
+    let cursor = Cursor::new(input);
+    let found = interaction::classify_interactions(cursor, format, modified, &selection, &criteria)
+        .map_err(js_error)?;
+
+    Ok(Interactions::new(found))
+}
+
+#[wasm_bindgen]
+pub struct BasePairs {
+    first: Option<Vec<String>>,
+    second: Option<Vec<String>>,
+    families: Option<Vec<String>>,
+    classes: Option<Vec<String>>,
+    hbonds: Option<Vec<u32>>,
+    stack_first: Option<Vec<String>>,
+    stack_second: Option<Vec<String>>,
+    chains: Option<Vec<String>>,
+    sequences: Option<Vec<String>>,
+    dot_brackets: Option<Vec<String>>,
+    dbn: Option<Vec<u8>>,
+}
+
+/// Base pairs, stacks and per-chain dot-bracket as parallel arrays; residues are labelled
+/// "B-12-G" (all getters consume).
+#[wasm_bindgen]
+impl BasePairs {
+    #[wasm_bindgen]
+    pub fn take_first(&mut self) -> Option<Array> {
+        take_strings(&mut self.first)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_second(&mut self) -> Option<Array> {
+        take_strings(&mut self.second)
+    }
+
+    /// Leontis–Westhof family of each pair, e.g. "cWW" or "tSH".
+    #[wasm_bindgen]
+    pub fn take_families(&mut self) -> Option<Array> {
+        take_strings(&mut self.families)
+    }
+
+    /// "watson_crick", "wobble" or "non_canonical".
+    #[wasm_bindgen]
+    pub fn take_classes(&mut self) -> Option<Array> {
+        take_strings(&mut self.classes)
+    }
+
+    /// Number of H-bonds of each pair.
+    #[wasm_bindgen]
+    pub fn take_hbonds(&mut self) -> Option<Uint32Array> {
+        take_u32(&mut self.hbonds)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_stack_first(&mut self) -> Option<Array> {
+        take_strings(&mut self.stack_first)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_stack_second(&mut self) -> Option<Array> {
+        take_strings(&mut self.stack_second)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_chains(&mut self) -> Option<Array> {
+        take_strings(&mut self.chains)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_sequences(&mut self) -> Option<Array> {
+        take_strings(&mut self.sequences)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_dot_brackets(&mut self) -> Option<Array> {
+        take_strings(&mut self.dot_brackets)
+    }
+
+    /// All chains as a `.dbn` file.
+    #[wasm_bindgen]
+    pub fn take_dbn(&mut self) -> Option<Uint8Array> {
+        self.dbn.take().map(|v| Uint8Array::from(v.as_slice()))
+    }
+}
+
+/// Nucleic-acid base pairs, stacking and dot-bracket secondary structure; `criteria`
+/// optionally overrides the default geometry.
+#[wasm_bindgen]
+pub fn annotate_base_pairs(
+    input: &[u8],
+    format: &str,
+    modified: Option<String>,
+    selection: Option<String>,
+    criteria: Option<Object>,
+) -> Result<BasePairs, JsValue> {
+    let modified = modified_mode(modified)?;
+    let selection = selection_of(selection)?;
+    let criteria = base_pair_criteria(criteria)?;
+    let cursor = Cursor::new(input);
+    let structure = basepair::compute_base_pairs(cursor, format, modified, &selection, &criteria)
+        .map_err(js_error)?;
+    let (pairs, stacks, chains) = (&structure.pairs, &structure.stacks, &structure.chains);
+
+    Ok(BasePairs {
+        first: Some(pairs.iter().map(|p| p.a.to_string()).collect()),
+        second: Some(pairs.iter().map(|p| p.b.to_string()).collect()),
+        families: Some(pairs.iter().map(|p| p.family()).collect()),
+        classes: Some(pairs.iter().map(|p| p.class.as_str().to_string()).collect()),
+        hbonds: Some(pairs.iter().map(|p| p.hbonds.len() as u32).collect()),
+        stack_first: Some(stacks.iter().map(|s| s.a.to_string()).collect()),
+        stack_second: Some(stacks.iter().map(|s| s.b.to_string()).collect()),
+        chains: Some(chains.iter().map(|c| c.chain_id.clone()).collect()),
+        sequences: Some(chains.iter().map(|c| c.sequence.clone()).collect()),
+        dot_brackets: Some(chains.iter().map(|c| c.dot_bracket.clone()).collect()),
+        dbn: Some(export::write_dot_bracket(chains, "")),
+    })
+}
+
+/// Interface between two selections, e.g. `"chain H or chain L"` against `"chain A"` or
+/// `"protein"` against `"ligand"`.
+#[wasm_bindgen]
+pub fn annotate_interface(
+    input: &[u8],
+    a: &str,
+    b: &str,
+    cutoff: f64,
+    format: &str,
+    modified: Option<String>,
+) -> Result<InterfaceAnnotation, JsValue> {
+    let modified = modified_mode(modified)?;
+    let a: Selection = a.parse().map_err(js_error)?;
+    let b: Selection = b.parse().map_err(js_error)?;
+    let cursor = Cursor::new(input);
+    let interface =
+        annotate::annotate_interface(cursor, &a, &b, cutoff, format, modified).map_err(js_error)?;
+
+    Ok(InterfaceAnnotation::new(interface))
+}
+
+/// Per-residue DNA/RNA binding-site labels of every protein chain as a label file.
+///
+/// `output` is "fasta" (one `target` per file, default "nucleic") or "json" (DNA and RNA
+/// labels together); `value` is "binary" (default) or "distance"; `source` is "atom"
+/// (default) or "seqres" to align the labels to the deposited sequence.
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn write_binding_labels(
+    input: &[u8],
+    cutoff: f64,
+    format: &str,
+    modified: Option<String>,
+    selection: Option<String>,
+    output: &str,
+    id: Option<String>,
+    target: Option<String>,
+    value: Option<String>,
+    source: Option<String>,
+) -> Result<Uint8Array, JsValue> {
+    let modified = modified_mode(modified)?;
+    let selection = selection_of(selection)?;
+    let target: LabelTarget = target
+        .map(|t| t.parse())
+        .transpose()
+        .map_err(js_error)?
+        .unwrap_or_default();
+    let value: LabelValue = value
+        .map(|v| v.parse())
+        .transpose()
+        .map_err(js_error)?
+        .unwrap_or_default();
+    let source: SequenceSource = source
+        .map(|s| s.parse())
+        .transpose()
+        .map_err(js_error)?
+        .unwrap_or_default();
+    let id = id.unwrap_or_default();
+    let cursor = Cursor::new(input);
+    let labels = annotate::binding_labels(cursor, cutoff, format, modified, &selection, source)
+        .map_err(js_error)?;
+
+    let bytes = match output.to_lowercase().as_str() {
+        "fasta" => export::write_label_fasta(&labels, &id, target, value),
+        "json" => export::write_label_json(&labels, &id, value),
+        _ => {
+            return Err(js_error(PskitError::InvalidArgument(format!(
+                "Unknown label output {output:?}, expected fasta or json"
+            ))))
+        }
+    };
+    Ok(Uint8Array::from(bytes.as_slice()))
+}
+
+#[wasm_bindgen]
+pub fn annotate_binding_frequency(
+    input: &[u8],
+    cutoff: f64,
+    format: &str,
+    modified: Option<String>,
+    selection: Option<String>,
+) -> Result<BindingFrequencies, JsValue> {
+    let modified = modified_mode(modified)?;
+    let selection = selection_of(selection)?;
+    let cursor = Cursor::new(input);
+    let pairs = annotate::compute_binding_frequency(cursor, cutoff, format, modified, &selection)
+        .map_err(js_error)?;
+    let (pairs, frequencies): (Vec<String>, Vec<f64>) = pairs
+        .into_iter()
+        .map(|pair| (pair.to_string(), pair.frequency))
+        .unzip();
+
+    Ok(BindingFrequencies {
+        pairs: Some(pairs),
+        frequencies: Some(frequencies),
+    })
+}
+
+#[wasm_bindgen]
+pub struct SasaResult {
+    total: f64,
+    residues: Option<Vec<String>>,
+    residue_areas: Option<Vec<f64>>,
+    relative: Option<Vec<f64>>,
+    atom_residues: Option<Vec<String>>,
+    atom_names: Option<Vec<String>>,
+    atom_areas: Option<Vec<f64>>,
+}
+
+fn take_f64(field: &mut Option<Vec<f64>>) -> Option<js_sys::Float64Array> {
+    field
+        .take()
+        .map(|v| js_sys::Float64Array::from(v.as_slice()))
+}
+
+/// Per-residue and per-atom accessible areas in Å² (array getters consume).
+#[wasm_bindgen]
+impl SasaResult {
+    #[wasm_bindgen(getter)]
+    pub fn total(&self) -> f64 {
+        self.total
+    }
+
+    /// Residue labels, formatted "A-52A-ARG".
+    #[wasm_bindgen]
+    pub fn take_residues(&mut self) -> Option<Array> {
+        take_strings(&mut self.residues)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_residue_areas(&mut self) -> Option<js_sys::Float64Array> {
+        take_f64(&mut self.residue_areas)
+    }
+
+    /// Relative SASA; NaN for residues other than amino acids.
+    #[wasm_bindgen]
+    pub fn take_relative(&mut self) -> Option<js_sys::Float64Array> {
+        take_f64(&mut self.relative)
+    }
+
+    /// Residue label of each atom.
+    #[wasm_bindgen]
+    pub fn take_atom_residues(&mut self) -> Option<Array> {
+        take_strings(&mut self.atom_residues)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_atom_names(&mut self) -> Option<Array> {
+        take_strings(&mut self.atom_names)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_atom_areas(&mut self) -> Option<js_sys::Float64Array> {
+        take_f64(&mut self.atom_areas)
+    }
+}
+
+#[wasm_bindgen]
+pub struct BuriedSurfaceResult {
+    protein: f64,
+    nucleic: f64,
+    complex: f64,
+    residues: Option<Vec<String>>,
+    unbound: Option<Vec<f64>>,
+    bound: Option<Vec<f64>>,
+}
+
+/// Surface areas of the split parts and the complex; per-residue arrays cover residues that
+/// lose surface on binding (array getters consume).
+#[wasm_bindgen]
+impl BuriedSurfaceResult {
+    #[wasm_bindgen(getter)]
+    pub fn protein(&self) -> f64 {
+        self.protein
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn nucleic(&self) -> f64 {
+        self.nucleic
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn complex(&self) -> f64 {
+        self.complex
+    }
+
+    /// Total buried area, `protein + nucleic - complex`.
+    #[wasm_bindgen(getter)]
+    pub fn buried(&self) -> f64 {
+        self.protein + self.nucleic - self.complex
+    }
+
+    #[wasm_bindgen]
+    pub fn take_residues(&mut self) -> Option<Array> {
+        take_strings(&mut self.residues)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_unbound(&mut self) -> Option<js_sys::Float64Array> {
+        take_f64(&mut self.unbound)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_bound(&mut self) -> Option<js_sys::Float64Array> {
+        take_f64(&mut self.bound)
+    }
+}
+
+/// Shrake–Rupley SASA of the selected atoms (water excluded); `options` may set
+/// `probeRadius` (1.4 Å), `nPoints` (100) and `includeHydrogens` (false).
+#[wasm_bindgen]
+pub fn compute_sasa(
+    input: &[u8],
+    format: &str,
+    selection: Option<String>,
+    options: Option<Object>,
+) -> Result<SasaResult, JsValue> {
+    let selection = selection_of(selection)?;
+    let options = sasa_options(options)?;
+    let cursor = Cursor::new(input);
+    let sasa = sasa::compute_sasa(cursor, format, &selection, &options).map_err(js_error)?;
+
+    Ok(SasaResult {
+        total: sasa.total,
+        residues: Some(sasa.residues.iter().map(|r| r.key.to_string()).collect()),
+        residue_areas: Some(sasa.residues.iter().map(|r| r.area).collect()),
+        relative: Some(
+            sasa.residues
+                .iter()
+                .map(|r| r.relative.unwrap_or(f64::NAN))
+                .collect(),
+        ),
+        atom_residues: Some(sasa.atoms.iter().map(|a| a.residue.to_string()).collect()),
+        atom_names: Some(sasa.atoms.iter().map(|a| a.atom.clone()).collect()),
+        atom_areas: Some(sasa.atoms.iter().map(|a| a.area).collect()),
+    })
+}
+
+/// Buried surface area between the protein and nucleic-acid parts of a complex.
+#[wasm_bindgen]
+pub fn buried_surface_area(
+    input: &[u8],
+    format: &str,
+    modified: Option<String>,
+    selection: Option<String>,
+    options: Option<Object>,
+) -> Result<BuriedSurfaceResult, JsValue> {
+    let modified = modified_mode(modified)?;
+    let selection = selection_of(selection)?;
+    let options = sasa_options(options)?;
+    let cursor = Cursor::new(input);
+    let bsa = sasa::buried_surface_area(cursor, format, modified, &selection, &options)
+        .map_err(js_error)?;
+
+    Ok(BuriedSurfaceResult {
+        protein: bsa.protein,
+        nucleic: bsa.nucleic,
+        complex: bsa.complex,
+        residues: Some(bsa.residues.iter().map(|r| r.key.to_string()).collect()),
+        unbound: Some(bsa.residues.iter().map(|r| r.unbound).collect()),
+        bound: Some(bsa.residues.iter().map(|r| r.bound).collect()),
+    })
+}
+
+#[wasm_bindgen]
+pub struct SecondaryStructures {
+    chains: Option<Vec<String>>,
+    numbers: Option<Vec<String>>,
+    names: Option<Vec<String>>,
+    codes: Option<Vec<String>>,
+    accessibility: Option<Vec<f64>>,
+}
+
+/// Parallel arrays, one entry per protein residue with a complete backbone (all getters consume).
+#[wasm_bindgen]
+impl SecondaryStructures {
+    #[wasm_bindgen]
+    pub fn take_chains(&mut self) -> Option<Array> {
+        take_strings(&mut self.chains)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_numbers(&mut self) -> Option<Array> {
+        take_strings(&mut self.numbers)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_names(&mut self) -> Option<Array> {
+        take_strings(&mut self.names)
+    }
+
+    /// DSSP symbols "H", "B", "E", "G", "I", "T", "S", or "-" for loops.
+    #[wasm_bindgen]
+    pub fn take_codes(&mut self) -> Option<Array> {
+        take_strings(&mut self.codes)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_accessibility(&mut self) -> Option<js_sys::Float64Array> {
+        take_f64(&mut self.accessibility)
+    }
+}
+
+/// DSSP secondary structure of the protein residues, for coloring by structure.
+#[wasm_bindgen]
+pub fn dssp(
+    input: &[u8],
+    format: &str,
+    modified: Option<String>,
+    selection: Option<String>,
+) -> Result<SecondaryStructures, JsValue> {
+    let modified = modified_mode(modified)?;
+    let selection = selection_of(selection)?;
+    let cursor = Cursor::new(input);
+    let result = dssp::compute_dssp(cursor, format, modified, &selection).map_err(js_error)?;
+    let residues = &result.residues;
+
+    Ok(SecondaryStructures {
+        chains: Some(residues.iter().map(|r| r.key.chain_id.clone()).collect()),
+        numbers: Some(residues.iter().map(|r| r.key.number.to_string()).collect()),
+        names: Some(residues.iter().map(|r| r.key.name.clone()).collect()),
+        codes: Some(result.summary().chars().map(String::from).collect()),
+        accessibility: Some(residues.iter().map(|r| r.accessibility).collect()),
+    })
+}
+
+/// DSSP assignment as a classic `.dssp` file (`output` "dssp") or as the structure in mmCIF
+/// with `_struct_conf` records (`output` "mmcif").
+#[wasm_bindgen]
+pub fn write_dssp(
+    input: &[u8],
+    format: &str,
+    modified: Option<String>,
+    selection: Option<String>,
+    output: &str,
+) -> Result<Uint8Array, JsValue> {
+    let modified = modified_mode(modified)?;
+    let selection = selection_of(selection)?;
+    let cursor = Cursor::new(input);
+    let bytes = match output.to_lowercase().as_str() {
+        "dssp" => dssp::compute_dssp(cursor, format, modified, &selection)
+            .map(|result| dssp::write_dssp(&result)),
+        "mmcif" | "cif" => dssp::write_dssp_mmcif(cursor, format, modified, &selection),
+        _ => Err(PskitError::InvalidArgument(format!(
+            "Unknown DSSP output {output:?}, expected dssp or mmcif"
+        ))),
+    }
+    .map_err(js_error)?;
+    Ok(Uint8Array::from(bytes.as_slice()))
+}
+
+#[wasm_bindgen]
+pub struct SuperpositionResult {
+    rmsd_before: f64,
+    rmsd_after: f64,
+    matched_atoms: usize,
+    rotation: Option<Vec<f64>>,
+    translation: Option<Vec<f64>>,
+    structure: Option<Vec<u8>>,
+    mobile_residues: Option<Vec<String>>,
+    target_residues: Option<Vec<String>>,
+    deviations: Option<Vec<f64>>,
+}
+
+/// Fit statistics, the transformed mobile structure and per-residue deviations
+/// (array getters consume).
+#[wasm_bindgen]
+impl SuperpositionResult {
+    #[wasm_bindgen(getter)]
+    pub fn rmsd_before(&self) -> f64 {
+        self.rmsd_before
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn rmsd_after(&self) -> f64 {
+        self.rmsd_after
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn matched_atoms(&self) -> usize {
+        self.matched_atoms
+    }
+
+    /// Row-major 3×3 rotation.
+    #[wasm_bindgen]
+    pub fn take_rotation(&mut self) -> Option<js_sys::Float64Array> {
+        take_f64(&mut self.rotation)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_translation(&mut self) -> Option<js_sys::Float64Array> {
+        take_f64(&mut self.translation)
+    }
+
+    /// Mobile structure moved onto the target, in the mobile file's format.
+    #[wasm_bindgen]
+    pub fn take_structure(&mut self) -> Option<Uint8Array> {
+        self.structure
+            .take()
+            .map(|bytes| Uint8Array::from(bytes.as_slice()))
+    }
+
+    #[wasm_bindgen]
+    pub fn take_mobile_residues(&mut self) -> Option<Array> {
+        take_strings(&mut self.mobile_residues)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_target_residues(&mut self) -> Option<Array> {
+        take_strings(&mut self.target_residues)
+    }
+
+    /// RMSD of each matched residue pair after fitting.
+    #[wasm_bindgen]
+    pub fn take_deviations(&mut self) -> Option<js_sys::Float64Array> {
+        take_f64(&mut self.deviations)
+    }
+}
+
+/// Superpose `mobile` onto `target`; `matching` is "id" (default) or "alignment".
+#[wasm_bindgen]
+pub fn superpose(
+    mobile: &[u8],
+    mobile_format: &str,
+    mobile_selection: Option<String>,
+    target: &[u8],
+    target_format: &str,
+    target_selection: Option<String>,
+    matching: Option<String>,
+) -> Result<SuperpositionResult, JsValue> {
+    let mobile_selection = selection_of(mobile_selection)?;
+    let target_selection = selection_of(target_selection)?;
+    let matching: ResidueMatch = matching
+        .map(|m| m.parse())
+        .transpose()
+        .map_err(js_error)?
+        .unwrap_or_default();
+    let (fit, structure) = superpose::superpose(
+        Cursor::new(mobile),
+        mobile_format,
+        &mobile_selection,
+        Cursor::new(target),
+        target_format,
+        &target_selection,
+        matching,
+    )
+    .map_err(js_error)?;
+
+    Ok(SuperpositionResult {
+        rmsd_before: fit.rmsd_before,
+        rmsd_after: fit.rmsd_after,
+        matched_atoms: fit.matched_atoms,
+        rotation: Some(fit.rotation.iter().flatten().copied().collect()),
+        translation: Some(fit.translation.to_vec()),
+        structure: Some(structure),
+        mobile_residues: Some(fit.residues.iter().map(|r| r.mobile.to_string()).collect()),
+        target_residues: Some(fit.residues.iter().map(|r| r.target.to_string()).collect()),
+        deviations: Some(fit.residues.iter().map(|r| r.rmsd).collect()),
+    })
+}
+
+#[wasm_bindgen]
+pub struct StructureComparison {
+    tm_score: f64,
+    gdt_ts: f64,
+    lddt: f64,
+    aligned: usize,
+    reference_length: usize,
+    rotation: Option<Vec<f64>>,
+    translation: Option<Vec<f64>>,
+    reference_residues: Option<Vec<String>>,
+    model_residues: Option<Vec<String>>,
+    residue_lddt: Option<Vec<f64>>,
+    deviations: Option<Vec<f64>>,
+}
+
+/// Global scores plus one entry per reference residue (array getters consume). Unmatched
+/// residues have an empty model id and NaN deviation; lDDT is NaN without neighbours.
+#[wasm_bindgen]
+impl StructureComparison {
+    #[wasm_bindgen(getter)]
+    pub fn tm_score(&self) -> f64 {
+        self.tm_score
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn gdt_ts(&self) -> f64 {
+        self.gdt_ts
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn lddt(&self) -> f64 {
+        self.lddt
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn aligned(&self) -> usize {
+        self.aligned
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn reference_length(&self) -> usize {
+        self.reference_length
+    }
+
+    /// Row-major 3×3 rotation of the TM-score superposition.
+    #[wasm_bindgen]
+    pub fn take_rotation(&mut self) -> Option<js_sys::Float64Array> {
+        take_f64(&mut self.rotation)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_translation(&mut self) -> Option<js_sys::Float64Array> {
+        take_f64(&mut self.translation)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_reference_residues(&mut self) -> Option<Array> {
+        take_strings(&mut self.reference_residues)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_model_residues(&mut self) -> Option<Array> {
+        take_strings(&mut self.model_residues)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_residue_lddt(&mut self) -> Option<js_sys::Float64Array> {
+        take_f64(&mut self.residue_lddt)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_deviations(&mut self) -> Option<js_sys::Float64Array> {
+        take_f64(&mut self.deviations)
+    }
+}
+
+/// TM-score, GDT-TS and lDDT of `model` against `reference`; `matching` is "id" (default)
+/// or "alignment".
+#[wasm_bindgen]
+pub fn compare_structures(
+    model: &[u8],
+    model_format: &str,
+    model_selection: Option<String>,
+    reference: &[u8],
+    reference_format: &str,
+    reference_selection: Option<String>,
+    matching: Option<String>,
+) -> Result<StructureComparison, JsValue> {
+    let model_selection = selection_of(model_selection)?;
+    let reference_selection = selection_of(reference_selection)?;
+    let matching: ResidueMatch = matching
+        .map(|m| m.parse())
+        .transpose()
+        .map_err(js_error)?
+        .unwrap_or_default();
+    let scores = quality::compare_structures(
+        Cursor::new(model),
+        model_format,
+        &model_selection,
+        Cursor::new(reference),
+        reference_format,
+        &reference_selection,
+        matching,
+    )
+    .map_err(js_error)?;
+
+    let residues = &scores.residues;
+    Ok(StructureComparison {
+        tm_score: scores.tm_score,
+        gdt_ts: scores.gdt_ts,
+        lddt: scores.lddt,
+        aligned: scores.aligned,
+        reference_length: scores.reference_length,
+        rotation: Some(scores.rotation.iter().flatten().copied().collect()),
+        translation: Some(scores.translation.to_vec()),
+        reference_residues: Some(residues.iter().map(|r| r.reference.to_string()).collect()),
+        model_residues: Some(
+            residues
+                .iter()
+                .map(|r| r.model.as_ref().map(|m| m.to_string()).unwrap_or_default())
+                .collect(),
+        ),
+        residue_lddt: Some(
+            residues
+                .iter()
+                .map(|r| r.lddt.unwrap_or(f64::NAN))
+                .collect(),
+        ),
+        deviations: Some(
+            residues
+                .iter()
+                .map(|r| r.deviation.unwrap_or(f64::NAN))
+                .collect(),
+        ),
+    })
+}
+
+#[wasm_bindgen]
+pub struct ChainSequences {
+    chain_ids: Option<Vec<String>>,
+    polymer_types: Option<Vec<String>>,
+    sources: Option<Vec<String>>,
+    sequences: Option<Vec<String>>,
+}
+
+/// Parallel per-chain arrays (getters consume). Polymer type is "protein", "dna", "rna" or
+/// empty; source is the one actually used, "atom" or "seqres".
+#[wasm_bindgen]
+impl ChainSequences {
+    #[wasm_bindgen]
+    pub fn take_chain_ids(&mut self) -> Option<Array> {
+        take_strings(&mut self.chain_ids)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_polymer_types(&mut self) -> Option<Array> {
+        take_strings(&mut self.polymer_types)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_sources(&mut self) -> Option<Array> {
+        take_strings(&mut self.sources)
+    }
+
+    #[wasm_bindgen]
+    pub fn take_sequences(&mut self) -> Option<Array> {
+        take_strings(&mut self.sequences)
+    }
+}
+
+fn read_sequences(
+    input: &[u8],
+    format: &str,
+    modified: Option<String>,
+    selection: Option<String>,
+    source: Option<String>,
+    mark_gaps: Option<bool>,
+) -> Result<Vec<ChainSequence>, JsValue> {
+    let modified = modified_mode(modified)?;
+    let selection = selection_of(selection)?;
+    let options = SequenceOptions {
+        source: source
+            .map(|s| s.parse())
+            .transpose()
+            .map_err(js_error)?
+            .unwrap_or_default(),
+        mark_gaps: mark_gaps.unwrap_or(false),
+    };
+    sequence::chain_sequences(Cursor::new(input), format, modified, &selection, options)
+        .map_err(js_error)
+}
+
+/// Polymer chain sequences; `source` is "atom" (default) or "seqres".
+#[wasm_bindgen]
+pub fn chain_sequences(
+    input: &[u8],
+    format: &str,
+    modified: Option<String>,
+    selection: Option<String>,
+    source: Option<String>,
+    mark_gaps: Option<bool>,
+) -> Result<ChainSequences, JsValue> {
+    let chains = read_sequences(input, format, modified, selection, source, mark_gaps)?;
+    Ok(ChainSequences {
+        chain_ids: Some(chains.iter().map(|c| c.chain_id.clone()).collect()),
+        polymer_types: Some(
+            chains
+                .iter()
+                .map(|c| c.polymer_type.map_or("", |ty| ty.as_str()).to_string())
+                .collect(),
+        ),
+        sources: Some(
+            chains
+                .iter()
+                .map(|c| match c.source {
+                    SequenceSource::Atom => "atom".to_string(),
+                    SequenceSource::Seqres => "seqres".to_string(),
+                })
+                .collect(),
+        ),
+        sequences: Some(chains.into_iter().map(|c| c.sequence).collect()),
+    })
+}
+
+/// Polymer chain sequences as FASTA; `width` wraps sequence lines (0 or absent: no wrapping).
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn write_fasta(
+    input: &[u8],
+    format: &str,
+    modified: Option<String>,
+    selection: Option<String>,
+    source: Option<String>,
+    mark_gaps: Option<bool>,
+    id: Option<String>,
+    width: Option<usize>,
+) -> Result<Uint8Array, JsValue> {
+    let chains = read_sequences(input, format, modified, selection, source, mark_gaps)?;
+    let bytes = export::write_fasta(&chains, &id.unwrap_or_default(), width.unwrap_or(0));
+    Ok(Uint8Array::from(bytes.as_slice()))
+}
+
+/// SEQRES-to-structure mapping of every polymer chain as JSON: missing ranges, chain breaks
+/// and the author number at each sequence position.
+#[wasm_bindgen]
+pub fn sequence_mapping(
+    input: &[u8],
+    format: &str,
+    modified: Option<String>,
+    selection: Option<String>,
+    id: Option<String>,
+) -> Result<Uint8Array, JsValue> {
+    let modified = modified_mode(modified)?;
+    let selection = selection_of(selection)?;
+    let mappings = sequence::map_sequences(Cursor::new(input), format, modified, &selection)
+        .map_err(js_error)?;
+    let bytes = export::write_sequence_mapping_json(&mappings, &id.unwrap_or_default());
+    Ok(Uint8Array::from(bytes.as_slice()))
+}
+
+#[wasm_bindgen]
+pub struct SequenceAlignment {
+    score: i32,
+    identity: f64,
+    aligned_a: String,
+    aligned_b: String,
+    pairs: Option<Vec<u32>>,
+}
+
+/// Score, identity and gapped rows of a pairwise alignment; pairs are flattened `(a, b)`
+/// index pairs (getter consumes).
+#[wasm_bindgen]
+impl SequenceAlignment {
+    #[wasm_bindgen(getter)]
+    pub fn score(&self) -> i32 {
+        self.score
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn identity(&self) -> f64 {
+        self.identity
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn aligned_a(&self) -> String {
+        self.aligned_a.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn aligned_b(&self) -> String {
+        self.aligned_b.clone()
+    }
+
+    #[wasm_bindgen]
+    pub fn take_pairs(&mut self) -> Option<Uint32Array> {
+        take_u32(&mut self.pairs)
+    }
+}
+
+/// Align two one-letter sequences. `mode` is "global" (default), "local" or "semiglobal";
+/// `matrix` is "auto" (default), "blosum62", "pam250" or "nucleotide".
+#[wasm_bindgen]
+pub fn align_sequences(
+    a: &str,
+    b: &str,
+    mode: Option<String>,
+    matrix: Option<String>,
+    gap_open: Option<i32>,
+    gap_extend: Option<i32>,
+) -> Result<SequenceAlignment, JsValue> {
+    let defaults = AlignOptions::default();
+    let options = AlignOptions {
+        mode: mode
+            .map(|m| m.parse())
+            .transpose()
+            .map_err(js_error)?
+            .unwrap_or_default(),
+        matrix: matrix
+            .map(|m| m.parse())
+            .transpose()
+            .map_err(js_error)?
+            .unwrap_or_default(),
+        gap_open: gap_open.unwrap_or(defaults.gap_open),
+        gap_extend: gap_extend.unwrap_or(defaults.gap_extend),
+    };
+    let alignment = align::align(a, b, &options);
+    Ok(SequenceAlignment {
+        score: alignment.score,
+        identity: alignment.identity,
+        pairs: Some(
+            alignment
+                .pairs
+                .iter()
+                .flat_map(|&(i, j)| [i as u32, j as u32])
+                .collect(),
+        ),
+        aligned_a: alignment.aligned_a,
+        aligned_b: alignment.aligned_b,
     })
 }
@@ -16,7 +16,12 @@ function getPskitWorker() {
         _pskitWorkerPending.delete(msg.id);
 
         if (msg.ok) pending.resolve(msg);
-        else pending.reject(new Error(msg.error || "Worker error"));
+        else {
+            const err = new Error(msg.error || "Worker error");
+            if (msg.kind) err.kind = msg.kind;
+            if (msg.details) err.details = msg.details;
+            pending.reject(err);
+        }
     };
 
     _pskitWorker.onerror = (ev) => {
@@ -82,11 +87,11 @@ export async function extractFragmentInWorker(bytes, chain_id, start, end, forma
     if (typeof chain_id !== "string") {
         throw new Error("chain_id must be string");
     }
-    if (typeof start !== "number" && start !== null && start !== undefined) {
-        throw new Error("start must be number|null|undefined");
+    if (!["number", "string"].includes(typeof start) && start !== null && start !== undefined) {
+        throw new Error("start must be number|string|null|undefined");
     }
-    if (typeof end !== "number" && end !== null && end !== undefined) {
-        throw new Error("end must be number|null|undefined");
+    if (!["number", "string"].includes(typeof end) && end !== null && end !== undefined) {
+        throw new Error("end must be number|string|null|undefined");
     }
     if (typeof format !== "string") {
         throw new Error("format must be string");
@@ -133,6 +138,46 @@ export async function dMapInWorker(bytes, chain_id, format) {
     );
 }
 
+export async function contactListInWorker(bytes, chain_id, cutoff, format, options = {}) {
+    if (!(bytes instanceof Uint8Array)) {
+        throw new Error("bytes must be Uint8Array");
+    }
+    if (typeof cutoff !== "number" || Number.isNaN(cutoff)) {
+        throw new Error("cutoff must be number");
+    }
+    if (typeof format !== "string") {
+        throw new Error("format must be string");
+    }
+
+    return await callPskitWorker(
+        {
+            fn: "contact_list",
+            bytes,
+            chain_id: chain_id ?? undefined,
+            cutoff,
+            format,
+            selection: options.selection,
+            representative: options.representative,
+        },
+        [bytes.buffer],
+        { timeoutMs: 120000 },
+    );
+}
+
+export async function dMapBetweenInWorker(bytes, rows, cols, format) {
+    if (!(bytes instanceof Uint8Array)) {
+        throw new Error("bytes must be Uint8Array");
+    }
+    if (typeof rows !== "string" || typeof cols !== "string") {
+        throw new Error("rows and cols must be selection strings");
+    }
+    if (typeof format !== "string") {
+        throw new Error("format must be string");
+    }
+
+    return await callPskitWorker({ fn: "d_map_between", bytes, rows, cols, format }, [bytes.buffer], { timeoutMs: 120000 });
+}
+
 export function getFormatFromFileName(fileName) {
     const m = String(fileName).toLowerCase().match(/\.(pdb|cif)$/);
     return m ? m[1] : "pdb";
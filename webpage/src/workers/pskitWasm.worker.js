@@ -44,7 +44,7 @@ const handlers = {
         assertUint8Array(bytes, "bytes");
         assertString(format, "format");
 
-        const chunks = wasm.split_complex(bytes, format);
+        const chunks = wasm.split_complex(bytes, format, normalizeOptionalString(msg.modified), normalizeOptionalString(msg.selection));
         try {
             const { items, transfer } = chunksToItems(chunks);
             return { payload: { ok: true, kind: "chunks", items }, transfer };
@@ -63,7 +63,7 @@ const handlers = {
         assertUint8Array(bytes, "bytes");
         assertString(format, "format");
 
-        const chunks = wasm.split_by_chain(bytes, format);
+        const chunks = wasm.split_by_chain(bytes, format, normalizeOptionalString(msg.selection));
         try {
             const { items, transfer } = chunksToItems(chunks);
             return { payload: { ok: true, kind: "chunks", items }, transfer };
@@ -79,14 +79,14 @@ const handlers = {
     extract_fragment: (wasm, msg) => {
         const bytes = msg.bytes;
         const chain_id = msg.chain_id;
-        const start = msg.start;
-        const end = msg.end;
+        const start = normalizeOptionalString(msg.start);
+        const end = normalizeOptionalString(msg.end);
         const format = msg.format;
         assertUint8Array(bytes, "bytes");
         assertString(chain_id, "chain_id");
         assertString(format, "format");
 
-        const out = wasm.extract_fragment(bytes, chain_id, start, end, format);
+        const out = wasm.extract_fragment(bytes, chain_id, start, end, format, normalizeOptionalString(msg.selection));
 
         try {
             const outBytes = out.take_bytes();
@@ -114,14 +114,293 @@ const handlers = {
         assertNumber(cutoff, "cutoff");
         assertString(format, "format");
 
-        const out = wasm.annotate_binding_pairs(bytes, cutoff, format);
+        const out = wasm.annotate_binding_pairs(bytes, cutoff, format, normalizeOptionalString(msg.modified), normalizeOptionalString(msg.selection));
 
         try {
-            const pairsRaw = out.take_pairs();
+            const strings = (arr) => Array.from(arr || []).map((x) => String(x));
+            const protein = {
+                chains: strings(out.take_protein_chains()),
+                numbers: strings(out.take_protein_numbers()),
+                names: strings(out.take_protein_names()),
+                atoms: strings(out.take_protein_atoms()),
+            };
+            const nucleic = {
+                chains: strings(out.take_nucleic_chains()),
+                numbers: strings(out.take_nucleic_numbers()),
+                names: strings(out.take_nucleic_names()),
+                atoms: strings(out.take_nucleic_atoms()),
+            };
+            const regions = strings(out.take_regions());
             const distancesRaw = out.take_distances();
-            const pairs = Array.from(pairsRaw || []).map((x) => String(x));
+            const contacts = out.take_contacts();
+            const backboneContacts = out.take_backbone_contacts();
+            const baseContacts = out.take_base_contacts();
+            const nucleicPaired = out.take_nucleic_paired();
             const distances = Array.from(distancesRaw || []).map((x) => Number(x));
-            return { payload: { ok: true, kind: "binding_pairs", pairs, distances }, transfer: [] };
+            // 保留 "A-12-ARG_B-5-G" 形式的 pairs，兼容现有页面
+            const pairs = protein.chains.map(
+                (c, i) =>
+                    `${c}-${protein.numbers[i]}-${protein.names[i]}_${nucleic.chains[i]}-${nucleic.numbers[i]}-${nucleic.names[i]}`,
+            );
+            return {
+                payload: {
+                    ok: true,
+                    kind: "binding_pairs",
+                    pairs,
+                    distances,
+                    protein,
+                    nucleic,
+                    regions,
+                    contacts,
+                    backboneContacts,
+                    baseContacts,
+                    nucleicPaired,
+                },
+                transfer: [contacts.buffer, backboneContacts.buffer, baseContacts.buffer, nucleicPaired.buffer],
+            };
+        } finally {
+            try {
+                out.free();
+            } catch {
+                // ignore
+            }
+        }
+
+    },
+
+    annotate_interactions: (wasm, msg) => {
+        const bytes = msg.bytes;
+        const format = msg.format;
+        assertUint8Array(bytes, "bytes");
+        assertString(format, "format");
+
+        const out = wasm.annotate_interactions(
+            bytes,
+            format,
+            normalizeOptionalString(msg.modified),
+            normalizeOptionalString(msg.selection),
+            msg.criteria ?? undefined,
+        );
+        try {
+            const strings = (arr) => Array.from(arr || []).map((x) => String(x));
+            const kinds = strings(out.take_kinds());
+            const protein = {
+                chains: strings(out.take_protein_chains()),
+                numbers: strings(out.take_protein_numbers()),
+                names: strings(out.take_protein_names()),
+                atoms: strings(out.take_protein_atoms()),
+            };
+            const nucleic = {
+                chains: strings(out.take_nucleic_chains()),
+                numbers: strings(out.take_nucleic_numbers()),
+                names: strings(out.take_nucleic_names()),
+                atoms: strings(out.take_nucleic_atoms()),
+            };
+            const distances = out.take_distances();
+            const angles = out.take_angles();
+            return {
+                payload: { ok: true, kind: "interactions", kinds, protein, nucleic, distances, angles },
+                transfer: [distances.buffer, angles.buffer],
+            };
+        } finally {
+            try {
+                out.free();
+            } catch {
+                // ignore
+            }
+        }
+    },
+
+    annotate_base_pairs: (wasm, msg) => {
+        const bytes = msg.bytes;
+        const format = msg.format;
+        assertUint8Array(bytes, "bytes");
+        assertString(format, "format");
+
+        const out = wasm.annotate_base_pairs(
+            bytes,
+            format,
+            normalizeOptionalString(msg.modified),
+            normalizeOptionalString(msg.selection),
+            msg.criteria ?? undefined,
+        );
+        try {
+            const strings = (arr) => Array.from(arr || []).map((x) => String(x));
+            const pairs = {
+                first: strings(out.take_first()),
+                second: strings(out.take_second()),
+                families: strings(out.take_families()),
+                classes: strings(out.take_classes()),
+                hbonds: out.take_hbonds(),
+            };
+            const stacks = {
+                first: strings(out.take_stack_first()),
+                second: strings(out.take_stack_second()),
+            };
+            const chains = {
+                ids: strings(out.take_chains()),
+                sequences: strings(out.take_sequences()),
+                dotBrackets: strings(out.take_dot_brackets()),
+            };
+            const dbn = out.take_dbn();
+            return {
+                payload: { ok: true, kind: "base_pairs", pairs, stacks, chains, dbn },
+                transfer: [pairs.hbonds.buffer, dbn.buffer],
+            };
+        } finally {
+            try {
+                out.free();
+            } catch {
+                // ignore
+            }
+        }
+    },
+
+    annotate_interface: (wasm, msg) => {
+        const bytes = msg.bytes;
+        const format = msg.format;
+        assertUint8Array(bytes, "bytes");
+        assertString(format, "format");
+        assertString(msg.a, "a");
+        assertString(msg.b, "b");
+        assertNumber(msg.cutoff, "cutoff");
+
+        const out = wasm.annotate_interface(
+            bytes,
+            msg.a,
+            msg.b,
+            msg.cutoff,
+            format,
+            normalizeOptionalString(msg.modified),
+        );
+        try {
+            const strings = (arr) => Array.from(arr || []).map((x) => String(x));
+            const contacts = {
+                a: strings(out.take_a_residues()),
+                b: strings(out.take_b_residues()),
+                aAtoms: strings(out.take_a_atoms()),
+                bAtoms: strings(out.take_b_atoms()),
+                distances: out.take_distances(),
+                counts: out.take_contacts(),
+            };
+            const residues = {
+                labels: strings(out.take_residues()),
+                sides: strings(out.take_sides()),
+                distances: out.take_residue_distances(),
+                partners: out.take_partners(),
+            };
+            return {
+                payload: { ok: true, kind: "interface", contacts, residues },
+                transfer: [
+                    contacts.distances.buffer,
+                    contacts.counts.buffer,
+                    residues.distances.buffer,
+                    residues.partners.buffer,
+                ],
+            };
+        } finally {
+            try {
+                out.free();
+            } catch {
+                // ignore
+            }
+        }
+    },
+
+    compute_sasa: (wasm, msg) => {
+        const bytes = msg.bytes;
+        const format = msg.format;
+        assertUint8Array(bytes, "bytes");
+        assertString(format, "format");
+
+        const out = wasm.compute_sasa(bytes, format, normalizeOptionalString(msg.selection), msg.options ?? undefined);
+        try {
+            const strings = (arr) => Array.from(arr || []).map((x) => String(x));
+            const residues = {
+                labels: strings(out.take_residues()),
+                areas: out.take_residue_areas(),
+                relative: out.take_relative(),
+            };
+            const atoms = {
+                residues: strings(out.take_atom_residues()),
+                names: strings(out.take_atom_names()),
+                areas: out.take_atom_areas(),
+            };
+            return {
+                payload: { ok: true, kind: "sasa", total: out.total, residues, atoms },
+                transfer: [residues.areas.buffer, residues.relative.buffer, atoms.areas.buffer],
+            };
+        } finally {
+            try {
+                out.free();
+            } catch {
+                // ignore
+            }
+        }
+    },
+
+    buried_surface_area: (wasm, msg) => {
+        const bytes = msg.bytes;
+        const format = msg.format;
+        assertUint8Array(bytes, "bytes");
+        assertString(format, "format");
+
+        const out = wasm.buried_surface_area(
+            bytes,
+            format,
+            normalizeOptionalString(msg.modified),
+            normalizeOptionalString(msg.selection),
+            msg.options ?? undefined,
+        );
+        try {
+            const residues = Array.from(out.take_residues() || []).map((x) => String(x));
+            const unbound = out.take_unbound();
+            const bound = out.take_bound();
+            return {
+                payload: {
+                    ok: true,
+                    kind: "buried_surface",
+                    protein: out.protein,
+                    nucleic: out.nucleic,
+                    complex: out.complex,
+                    buried: out.buried,
+                    residues,
+                    unbound,
+                    bound,
+                },
+                transfer: [unbound.buffer, bound.buffer],
+            };
+        } finally {
+            try {
+                out.free();
+            } catch {
+                // ignore
+            }
+        }
+    },
+
+    dssp: (wasm, msg) => {
+        const bytes = msg.bytes;
+        const format = msg.format;
+        assertUint8Array(bytes, "bytes");
+        assertString(format, "format");
+
+        const out = wasm.dssp(bytes, format, normalizeOptionalString(msg.modified), normalizeOptionalString(msg.selection));
+        try {
+            const strings = (arr) => Array.from(arr || []).map((x) => String(x));
+            const accessibility = out.take_accessibility();
+            return {
+                payload: {
+                    ok: true,
+                    kind: "dssp",
+                    chains: strings(out.take_chains()),
+                    numbers: strings(out.take_numbers()),
+                    names: strings(out.take_names()),
+                    codes: strings(out.take_codes()),
+                    accessibility,
+                },
+                transfer: [accessibility.buffer],
+            };
         } finally {
             try {
                 out.free();
@@ -129,7 +408,223 @@ const handlers = {
                 // ignore
             }
         }
+    },
 
+    write_dssp: (wasm, msg) => {
+        const bytes = msg.bytes;
+        const format = msg.format;
+        assertUint8Array(bytes, "bytes");
+        assertString(format, "format");
+        assertString(msg.output, "output");
+
+        const out = wasm.write_dssp(
+            bytes,
+            format,
+            normalizeOptionalString(msg.modified),
+            normalizeOptionalString(msg.selection),
+            msg.output,
+        );
+        return { payload: { ok: true, kind: "bytes", bytes: out }, transfer: [out.buffer] };
+    },
+
+    superpose: (wasm, msg) => {
+        assertUint8Array(msg.mobile, "mobile");
+        assertString(msg.mobileFormat, "mobileFormat");
+        assertUint8Array(msg.target, "target");
+        assertString(msg.targetFormat, "targetFormat");
+
+        const out = wasm.superpose(
+            msg.mobile,
+            msg.mobileFormat,
+            normalizeOptionalString(msg.mobileSelection),
+            msg.target,
+            msg.targetFormat,
+            normalizeOptionalString(msg.targetSelection),
+            normalizeOptionalString(msg.matching),
+        );
+        try {
+            const strings = (arr) => Array.from(arr || []).map((x) => String(x));
+            const rotation = out.take_rotation();
+            const translation = out.take_translation();
+            const structure = out.take_structure();
+            const deviations = out.take_deviations();
+            return {
+                payload: {
+                    ok: true,
+                    kind: "superposition",
+                    rmsdBefore: out.rmsd_before,
+                    rmsdAfter: out.rmsd_after,
+                    matchedAtoms: out.matched_atoms,
+                    rotation,
+                    translation,
+                    structure,
+                    mobileResidues: strings(out.take_mobile_residues()),
+                    targetResidues: strings(out.take_target_residues()),
+                    deviations,
+                },
+                transfer: [rotation.buffer, translation.buffer, structure.buffer, deviations.buffer],
+            };
+        } finally {
+            try {
+                out.free();
+            } catch {
+                // ignore
+            }
+        }
+    },
+
+    compare_structures: (wasm, msg) => {
+        assertUint8Array(msg.model, "model");
+        assertString(msg.modelFormat, "modelFormat");
+        assertUint8Array(msg.reference, "reference");
+        assertString(msg.referenceFormat, "referenceFormat");
+
+        const out = wasm.compare_structures(
+            msg.model,
+            msg.modelFormat,
+            normalizeOptionalString(msg.modelSelection),
+            msg.reference,
+            msg.referenceFormat,
+            normalizeOptionalString(msg.referenceSelection),
+            normalizeOptionalString(msg.matching),
+        );
+        try {
+            const strings = (arr) => Array.from(arr || []).map((x) => String(x));
+            const rotation = out.take_rotation();
+            const translation = out.take_translation();
+            const lddt = out.take_residue_lddt();
+            const deviations = out.take_deviations();
+            return {
+                payload: {
+                    ok: true,
+                    kind: "structure_comparison",
+                    tmScore: out.tm_score,
+                    gdtTs: out.gdt_ts,
+                    lddt: out.lddt,
+                    aligned: out.aligned,
+                    referenceLength: out.reference_length,
+                    rotation,
+                    translation,
+                    referenceResidues: strings(out.take_reference_residues()),
+                    modelResidues: strings(out.take_model_residues()),
+                    residueLddt: lddt,
+                    deviations,
+                },
+                transfer: [rotation.buffer, translation.buffer, lddt.buffer, deviations.buffer],
+            };
+        } finally {
+            try {
+                out.free();
+            } catch {
+                // ignore
+            }
+        }
+    },
+
+    chain_sequences: (wasm, msg) => {
+        const bytes = msg.bytes;
+        const format = msg.format;
+        assertUint8Array(bytes, "bytes");
+        assertString(format, "format");
+
+        const out = wasm.chain_sequences(
+            bytes,
+            format,
+            normalizeOptionalString(msg.modified),
+            normalizeOptionalString(msg.selection),
+            normalizeOptionalString(msg.source),
+            msg.markGaps ?? undefined,
+        );
+        try {
+            const strings = (arr) => Array.from(arr || []).map((x) => String(x));
+            return {
+                payload: {
+                    ok: true,
+                    kind: "chain_sequences",
+                    chainIds: strings(out.take_chain_ids()),
+                    polymerTypes: strings(out.take_polymer_types()),
+                    sources: strings(out.take_sources()),
+                    sequences: strings(out.take_sequences()),
+                },
+            };
+        } finally {
+            try {
+                out.free();
+            } catch {
+                // ignore
+            }
+        }
+    },
+
+    write_fasta: (wasm, msg) => {
+        const bytes = msg.bytes;
+        const format = msg.format;
+        assertUint8Array(bytes, "bytes");
+        assertString(format, "format");
+
+        const out = wasm.write_fasta(
+            bytes,
+            format,
+            normalizeOptionalString(msg.modified),
+            normalizeOptionalString(msg.selection),
+            normalizeOptionalString(msg.source),
+            msg.markGaps ?? undefined,
+            normalizeOptionalString(msg.id),
+            msg.width ?? undefined,
+        );
+        return { payload: { ok: true, kind: "bytes", bytes: out }, transfer: [out.buffer] };
+    },
+
+    sequence_mapping: (wasm, msg) => {
+        const bytes = msg.bytes;
+        const format = msg.format;
+        assertUint8Array(bytes, "bytes");
+        assertString(format, "format");
+
+        const out = wasm.sequence_mapping(
+            bytes,
+            format,
+            normalizeOptionalString(msg.modified),
+            normalizeOptionalString(msg.selection),
+            normalizeOptionalString(msg.id),
+        );
+        const mapping = JSON.parse(new TextDecoder().decode(out));
+        return { payload: { ok: true, kind: "sequence_mapping", mapping } };
+    },
+
+    align_sequences: (wasm, msg) => {
+        assertString(msg.a, "a");
+        assertString(msg.b, "b");
+
+        const out = wasm.align_sequences(
+            msg.a,
+            msg.b,
+            normalizeOptionalString(msg.mode),
+            normalizeOptionalString(msg.matrix),
+            msg.gapOpen ?? undefined,
+            msg.gapExtend ?? undefined,
+        );
+        try {
+            const pairs = out.take_pairs();
+            return {
+                payload: {
+                    ok: true,
+                    kind: "sequence_alignment",
+                    score: out.score,
+                    identity: out.identity,
+                    alignedA: out.aligned_a,
+                    alignedB: out.aligned_b,
+                    pairs,
+                },
+                transfer: [pairs.buffer],
+            };
+        } finally {
+            try {
+                out.free();
+            } catch {
+                // ignore
+            }
+        }
     },
 
     d_map: (wasm, msg) => {
@@ -139,7 +634,7 @@ const handlers = {
         assertUint8Array(bytes, "bytes");
         assertString(format, "format");
 
-        const cm = wasm.d_map(bytes, chain_id, format);
+        const cm = wasm.d_map(bytes, chain_id, format, normalizeOptionalString(msg.selection), normalizeOptionalString(msg.representative), msg.gapped ?? undefined);
         try {
             const axisArr = cm.take_axis();
             const axis = Array.from(axisArr || []).map((x) => String(x));
@@ -159,6 +654,180 @@ const handlers = {
             }
         }
     },
+
+    knn_map: (wasm, msg) => {
+        const bytes = msg.bytes;
+        const format = msg.format;
+        assertUint8Array(bytes, "bytes");
+        assertString(format, "format");
+
+        const cm = wasm.knn_map(
+            bytes,
+            msg.chain_id,
+            Number(msg.k),
+            format,
+            normalizeOptionalString(msg.selection),
+            normalizeOptionalString(msg.representative),
+        );
+        try {
+            const axis = Array.from(cm.take_axis() || []).map((x) => String(x));
+            const rawValues = cm.take_values();
+            const values = rawValues instanceof Float64Array ? rawValues : Float64Array.from(Array.from(rawValues || []));
+
+            return { payload: { ok: true, kind: "contact_map", axis, values }, transfer: [values.buffer] };
+        } finally {
+            try {
+                cm.free();
+            } catch {
+                // ignore
+            }
+        }
+    },
+
+    write_contact_map: (wasm, msg) => {
+        assertString(msg.output, "output");
+        const values = msg.values instanceof Float64Array ? msg.values : Float64Array.from(msg.values || []);
+        const colAxis = Array.isArray(msg.colAxis) ? msg.colAxis : undefined;
+        const bytes = wasm.write_contact_map(msg.axis || [], colAxis, values, msg.output);
+        return { payload: { ok: true, kind: "bytes", bytes }, transfer: [bytes.buffer] };
+    },
+
+    write_binding_labels: (wasm, msg) => {
+        const bytes = msg.bytes;
+        const format = msg.format;
+        assertUint8Array(bytes, "bytes");
+        assertString(format, "format");
+        assertNumber(msg.cutoff, "cutoff");
+        assertString(msg.output, "output");
+
+        const out = wasm.write_binding_labels(
+            bytes,
+            msg.cutoff,
+            format,
+            normalizeOptionalString(msg.modified),
+            normalizeOptionalString(msg.selection),
+            msg.output,
+            normalizeOptionalString(msg.id),
+            normalizeOptionalString(msg.target),
+            normalizeOptionalString(msg.value),
+            normalizeOptionalString(msg.source),
+        );
+        return { payload: { ok: true, kind: "bytes", bytes: out }, transfer: [out.buffer] };
+    },
+
+    contact_list: (wasm, msg) => {
+        const bytes = msg.bytes;
+        const format = msg.format;
+        const cutoff = Number(msg.cutoff);
+        assertUint8Array(bytes, "bytes");
+        assertString(format, "format");
+
+        const sm = wasm.contact_list(
+            bytes,
+            msg.chain_id,
+            cutoff,
+            format,
+            normalizeOptionalString(msg.selection),
+            normalizeOptionalString(msg.representative),
+        );
+        try {
+            const axis = Array.from(sm.take_axis() || []).map((x) => String(x));
+            const indptr = sm.take_indptr();
+            const rows = sm.take_rows();
+            const cols = sm.take_cols();
+            const distances = sm.take_distances();
+
+            return {
+                payload: { ok: true, kind: "sparse_contact_map", axis, indptr, rows, cols, distances },
+                transfer: [indptr.buffer, rows.buffer, cols.buffer, distances.buffer],
+            };
+        } finally {
+            try {
+                sm.free();
+            } catch {
+                // ignore
+            }
+        }
+    },
+
+    residue_graph: (wasm, msg) => {
+        const bytes = msg.bytes;
+        const format = msg.format;
+        assertUint8Array(bytes, "bytes");
+        assertString(format, "format");
+
+        const g = wasm.residue_graph(
+            bytes,
+            normalizeOptionalString(msg.chain_id),
+            format,
+            normalizeOptionalString(msg.selection),
+            normalizeOptionalString(msg.representative),
+            normalizeOptionalString(msg.edges),
+        );
+        try {
+            const axis = Array.from(g.take_axis() || []).map((x) => String(x));
+            const sources = g.take_sources();
+            const targets = g.take_targets();
+            const features = g.take_features();
+            const coords = g.take_coords();
+
+            return {
+                payload: { ok: true, kind: "residue_graph", axis, sources, targets, features, coords },
+                transfer: [sources.buffer, targets.buffer, features.buffer, coords.buffer],
+            };
+        } finally {
+            try {
+                g.free();
+            } catch {
+                // ignore
+            }
+        }
+    },
+
+    write_residue_graph_npz: (wasm, msg) => {
+        const bytes = msg.bytes;
+        const format = msg.format;
+        assertUint8Array(bytes, "bytes");
+        assertString(format, "format");
+
+        const out = wasm.write_residue_graph_npz(
+            bytes,
+            normalizeOptionalString(msg.chain_id),
+            format,
+            normalizeOptionalString(msg.selection),
+            normalizeOptionalString(msg.representative),
+            normalizeOptionalString(msg.edges),
+        );
+        return { payload: { ok: true, kind: "bytes", bytes: out }, transfer: [out.buffer] };
+    },
+
+    d_map_between: (wasm, msg) => {
+        const bytes = msg.bytes;
+        const format = msg.format;
+        assertUint8Array(bytes, "bytes");
+        assertString(format, "format");
+        assertString(msg.rows, "rows");
+        assertString(msg.cols, "cols");
+
+        const cm = wasm.d_map_between(bytes, msg.rows, msg.cols, format);
+        try {
+            const rowAxis = Array.from(cm.take_row_axis() || []).map((x) => String(x));
+            const colAxis = Array.from(cm.take_col_axis() || []).map((x) => String(x));
+            const rawValues = cm.take_values();
+            const values = rawValues instanceof Float64Array ? rawValues : Float64Array.from(Array.from(rawValues || []));
+
+            return {
+                payload: { ok: true, kind: "contact_map", rowAxis, colAxis, triangular: false, values },
+                transfer: [values.buffer],
+            };
+        } finally {
+            try {
+                cm.free();
+            } catch {
+                // ignore
+            }
+        }
+    },
 };
 
 self.onmessage = async (ev) => {
@@ -178,6 +847,8 @@ self.onmessage = async (ev) => {
         return;
     } catch (e) {
         const error = e?.message ? String(e.message) : String(e);
-        self.postMessage({ id, ok: false, error });
+        // PskitError from wasm carries `kind` plus per-kind fields (issues, chainId, validChains, min, max).
+        const details = e?.name === "PskitError" ? { ...e } : undefined;
+        self.postMessage({ id, ok: false, error, kind: e?.kind, details });
     }
 };
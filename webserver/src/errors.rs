@@ -0,0 +1,62 @@
+use axum::{http::StatusCode, response::Json};
+use pskit_core::PskitError;
+use serde_json::{Value, json};
+
+// 接口统一的错误响应：状态码 + JSON 错误体
+pub type ApiError = (StatusCode, Json<Value>);
+
+// 将 pskit-core 的错误类型映射为 HTTP 状态码
+pub fn pskit_status(err: &PskitError) -> StatusCode {
+    match err {
+        PskitError::Parse(_) | PskitError::NotComplex => StatusCode::UNPROCESSABLE_ENTITY,
+        PskitError::ChainNotFound { .. }
+        | PskitError::InvalidRange { .. }
+        | PskitError::InvalidArgument(_) => StatusCode::BAD_REQUEST,
+        PskitError::Io(_) | PskitError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+// 生成结构化的 JSON 错误体，前端可根据 kind 字段分支处理
+pub fn pskit_error(file: &str, err: PskitError) -> ApiError {
+    let status = pskit_status(&err);
+    let mut body = json!({
+        "kind": err.kind(),
+        "message": err.to_string(),
+        "file": file,
+    });
+    match err {
+        PskitError::Parse(issues) => {
+            body["issues"] = issues
+                .into_iter()
+                .map(|issue| {
+                    json!({
+                        "level": issue.level,
+                        "message": issue.message,
+                        "detail": issue.detail,
+                        "line": issue.line,
+                        "context": issue.context,
+                    })
+                })
+                .collect();
+        }
+        PskitError::ChainNotFound { chain_id, valid } => {
+            body["chain_id"] = json!(chain_id);
+            body["valid_chains"] = json!(valid);
+        }
+        PskitError::InvalidRange { chain_id, min, max } => {
+            body["chain_id"] = json!(chain_id);
+            body["min"] = json!(min.to_string());
+            body["max"] = json!(max.to_string());
+        }
+        PskitError::InvalidArgument(_) => {}
+        PskitError::NotComplex => {}
+        PskitError::Io(_) => {}
+        PskitError::Internal(_) => {}
+    }
+    (status, Json(body))
+}
+
+// 非 pskit-core 的错误（表单缺字段、IO 失败等）只带 message
+pub fn api_error(status: StatusCode, message: impl ToString) -> ApiError {
+    (status, Json(json!({ "message": message.to_string() })))
+}
@@ -16,6 +16,7 @@ use tower_http::services::{ServeDir, ServeFile};
 
 mod config;
 mod database;
+mod errors;
 mod models;
 mod tasks;
 
@@ -38,20 +39,40 @@ async fn root() -> Json<serde_json::Value> {
     }))
 }
 
+// 写入 form_data.json 的任务参数，交给 ai.run_pskit 构造任务类
+// selection 只在上传时校验，任务类没有该字段，因此不传给任务
+fn task_params(
+    mut form_data: HashMap<String, String>,
+    upload_dir: &std::path::Path,
+    results_dir: &std::path::Path,
+) -> HashMap<String, String> {
+    form_data.remove("selection");
+    form_data.insert(
+        "input_dir".to_string(),
+        upload_dir.to_string_lossy().to_string(),
+    );
+    form_data.insert(
+        "output_dir".to_string(),
+        results_dir.to_string_lossy().to_string(),
+    );
+    form_data
+}
+
 // 接收用户上传的任务，添加到任务队列
+// 可选的 selection 字段只用于校验上传的结构（语法与链是否存在），不会应用到任务上
 // 如果 Ok(Json(...))，就返回一个 200 状态码、JSON 格式的 HTTP 响应
-// 如果 Err((StatusCode, String))，axum 会自动把这个元组转换成 HTTP 响应
+// 如果 Err((StatusCode, Json))，axum 会自动把这个元组转换成 JSON 格式的错误响应
 async fn upload_task(
     State(state): State<AppState>,
     mut multipart: Multipart,
-) -> Result<Json<TaskCreateResponse>, (StatusCode, String)> {
+) -> Result<Json<TaskCreateResponse>, errors::ApiError> {
     let mut form_data = HashMap::new();
     let mut file_fields = Vec::new(); // 暂存文件数据
 
     while let Some(field) = multipart
         .next_field()
         .await
-        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?
+        .map_err(|e| errors::api_error(StatusCode::BAD_REQUEST, e))?
     {
         let field_name = field.name().unwrap_or("unknown").to_string();
 
@@ -60,25 +81,58 @@ async fn upload_task(
             let data = field
                 .bytes()
                 .await
-                .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+                .map_err(|e| errors::api_error(StatusCode::BAD_REQUEST, e))?;
             file_fields.push((field_name, filename, data));
         } else {
             let value = field
                 .text()
                 .await
-                .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+                .map_err(|e| errors::api_error(StatusCode::BAD_REQUEST, e))?;
             form_data.insert(field_name, value);
         }
     }
     let task_id = form_data
         .get("task_id")
-        .ok_or((StatusCode::BAD_REQUEST, "task_id is required".to_string()))?
+        .ok_or_else(|| errors::api_error(StatusCode::BAD_REQUEST, "task_id is required"))?
         .clone();
     let task_name = form_data
         .get("task_name")
-        .ok_or((StatusCode::BAD_REQUEST, "task_name is required".to_string()))?
+        .ok_or_else(|| errors::api_error(StatusCode::BAD_REQUEST, "task_name is required"))?
         .clone();
 
+    // 可选的原子选择表达式（如 "chain A and not water"），语法错误直接返回 400
+    let selection: Option<pskit_core::Selection> = form_data
+        .get("selection")
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| s.parse())
+        .transpose()
+        .map_err(|e| errors::pskit_error("", e))?;
+
+    // 入队前先用 pskit-core 解析结构文件，解析失败直接返回结构化错误；
+    // 选择表达式只做校验，不写入任务参数，上传文件保持原始内容
+    for (_field_name, filename, data) in &file_fields {
+        let format = match std::path::Path::new(filename)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_lowercase())
+        {
+            Some(ext) if ext == "pdb" || ext == "cif" => ext,
+            _ => continue,
+        };
+        let data = data.clone();
+        let selection = selection.clone();
+        tokio::task::spawn_blocking(move || {
+            let (pdb, _) = pskit_core::utils::read_raw(std::io::Cursor::new(&data[..]), &format)?;
+            match selection {
+                Some(selection) => selection.check_chains(&pdb),
+                None => Ok(()),
+            }
+        })
+        .await
+        .map_err(|e| errors::api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?
+        .map_err(|e| errors::pskit_error(filename, e))?;
+    }
+
     let home = Config::home();
 
     let upload_dir = home.join("tasks").join("uploads").join(task_id.to_string());
@@ -86,20 +140,20 @@ async fn upload_task(
 
     fs::create_dir_all(&upload_dir)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(|e| errors::api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
     fs::create_dir_all(&results_dir)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(|e| errors::api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
     let mut uploaded_files = Vec::new();
     for (_field_name, filename, data) in file_fields {
         let file_path = upload_dir.join(&filename);
         let mut file = fs::File::create(&file_path)
             .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            .map_err(|e| errors::api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
         file.write_all(&data)
             .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            .map_err(|e| errors::api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
         uploaded_files.push(filename);
     }
 
@@ -111,16 +165,9 @@ async fn upload_task(
         .bind(chrono::Utc::now())
         .execute(&state.db_pool)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(|e| errors::api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
-    form_data.insert(
-        "input_dir".to_string(),
-        upload_dir.to_string_lossy().to_string(),
-    );
-    form_data.insert(
-        "output_dir".to_string(),
-        results_dir.to_string_lossy().to_string(),
-    );
+    let form_data = task_params(form_data, &upload_dir, &results_dir);
 
     //保存form_data
     let form_data_path = home
@@ -131,7 +178,7 @@ async fn upload_task(
     let form_data_json = serde_json::to_string(&form_data).unwrap();
     fs::write(form_data_path, form_data_json)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(|e| errors::api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
     // 添加task到队列
     state.task_queue.lock().await.push_back(task_id.clone());
@@ -139,9 +186,9 @@ async fn upload_task(
     // 通过通道通知调度器有新任务
     if let Err(_) = state.task_sender.send(task_id.clone()) {
         eprintln!("Failed to send task {} to dispatcher", task_id);
-        return Err((
+        return Err(errors::api_error(
             StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to queue task".to_string(),
+            "Failed to queue task",
         ));
     }
 
@@ -435,3 +482,25 @@ async fn main() {
     let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
     axum::serve(listener, app).await.unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_params() {
+        let form_data = HashMap::from([
+            ("task_id".to_string(), "t1".to_string()),
+            ("task_name".to_string(), "pred_bs".to_string()),
+            ("selection".to_string(), "chain A".to_string()),
+        ]);
+        let params = task_params(
+            form_data,
+            std::path::Path::new("/tmp/uploads/t1"),
+            std::path::Path::new("/tmp/results/t1"),
+        );
+        assert!(!params.contains_key("selection"));
+        assert_eq!(params["input_dir"], "/tmp/uploads/t1");
+        assert_eq!(params["output_dir"], "/tmp/results/t1");
+    }
+}